@@ -0,0 +1,184 @@
+//! Registered accounts of players persisted in a file.
+
+use crate::store::{PlayerRecord, PlayerRecords};
+use crate::types::Password;
+use hmac::Hmac;
+use rand::Rng;
+use sha2::Sha256;
+use std::io;
+use std::path::PathBuf;
+
+/// A salted hash of the password of a registered player.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Account {
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
+impl PlayerRecord for Account {
+    fn parse(fields: &[&str]) -> Option<Self> {
+        let (salt, hash) = match fields {
+            [salt, hash] => (Accounts::from_hex(salt)?, Accounts::from_hex(hash)?),
+            _ => return None,
+        };
+
+        if salt.len() != Accounts::SALT_LENGTH || hash.len() != Accounts::HASH_LENGTH {
+            return None;
+        }
+
+        Some(Account { salt, hash })
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![Accounts::to_hex(&self.salt), Accounts::to_hex(&self.hash)]
+    }
+}
+
+/// Accounts of registered players indexed by nicknames.
+///
+/// If the accounts have a file, they are stored in it as lines of
+/// `<nickname>;<salt>;<password hash>` with the salt and the hash in hexadecimal.
+/// Passwords are hashed by PBKDF2 with HMAC-SHA256.
+#[derive(Debug, Default)]
+pub struct Accounts {
+    /// Whether the registered nicknames can be used only with their passwords.
+    required: bool,
+    accounts: PlayerRecords<Account>,
+}
+
+impl Accounts {
+    /// Length of the random salt of every password.
+    const SALT_LENGTH: usize = 16;
+    /// Length of the password hash.
+    const HASH_LENGTH: usize = 32;
+    /// Number of the PBKDF2 rounds.
+    const HASH_ROUNDS: u32 = 10_000;
+
+    /// Load the accounts from the file.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        Ok(Accounts {
+            required: false,
+            accounts: PlayerRecords::load(path)?,
+        })
+    }
+
+    /// Set whether the registered nicknames can be used only with their passwords.
+    /// Nicknames which are not registered can always be used by guests.
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Save the accounts.
+    pub fn save(&self) -> io::Result<()> {
+        self.accounts.save()
+    }
+
+    /// Check whether the nickname is registered.
+    pub fn is_registered(&self, nickname: &str) -> bool {
+        self.accounts.contains(nickname)
+    }
+
+    /// Register the nickname with the password.
+    /// Returns false if the nickname is already registered.
+    pub fn register(&mut self, nickname: &str, password: &Password) -> bool {
+        if self.is_registered(nickname) {
+            return false;
+        }
+
+        let salt = rand::thread_rng()
+            .sample_iter(rand::distributions::Standard)
+            .take(Self::SALT_LENGTH)
+            .collect::<Vec<u8>>();
+        let hash = Self::hash(password, &salt);
+
+        self.accounts.insert(nickname, Account { salt, hash });
+
+        true
+    }
+
+    /// Check whether the player can log in with the nickname and the password, if any.
+    /// A wrong password is refused even if the password is not required.
+    pub fn authorize(&self, nickname: &str, password: Option<&Password>) -> bool {
+        match (self.accounts.get(nickname), password) {
+            (None, _) => true,
+            (Some(_), None) => !self.required,
+            (Some(account), Some(password)) => {
+                let hash = Self::hash(password, &account.salt);
+
+                // compare all bytes so the time does not depend on the first difference
+                hash.iter()
+                    .zip(account.hash.iter())
+                    .fold(0, |difference, (a, b)| difference | (a ^ b))
+                    == 0
+            }
+        }
+    }
+
+    /// Hash the password with the salt.
+    fn hash(password: &Password, salt: &[u8]) -> Vec<u8> {
+        let mut hash = vec![0; Self::HASH_LENGTH];
+        pbkdf2::pbkdf2::<Hmac<Sha256>>(
+            password.get().as_bytes(),
+            salt,
+            Self::HASH_ROUNDS,
+            &mut hash,
+        );
+
+        hash
+    }
+
+    /// Format the bytes as a hexadecimal string.
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Parse the bytes from a hexadecimal string, a string of an odd length is invalid.
+    fn from_hex(string: &str) -> Option<Vec<u8>> {
+        (0..string.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(string.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::accounts::{Account, Accounts};
+    use crate::store::PlayerRecord;
+    use crate::types::Password;
+
+    #[test]
+    fn test_authorize() {
+        let password = Password::new(String::from("secret1")).unwrap();
+        let wrong = Password::new(String::from("secret2")).unwrap();
+
+        let mut accounts = Accounts::default();
+        assert!(accounts.register("alice", &password));
+        assert!(!accounts.register("alice", &wrong));
+
+        assert!(accounts.authorize("alice", Some(&password)));
+        assert!(!accounts.authorize("alice", Some(&wrong)));
+        assert!(accounts.authorize("alice", None));
+        assert!(accounts.authorize("bob", None));
+
+        let accounts = accounts.with_required(true);
+        assert!(!accounts.authorize("alice", None));
+        assert!(accounts.authorize("bob", None));
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut accounts = Accounts::default();
+        let password = Password::new(String::from("secret1")).unwrap();
+        accounts.register("alice", &password);
+
+        let account = accounts.accounts.get("alice").unwrap();
+        let fields = account.fields();
+        let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+        assert_eq!(Account::parse(&fields).as_ref(), Some(account));
+
+        assert!(Account::parse(&["00ff", ""]).is_none());
+        assert!(Account::parse(&["zz", "00"]).is_none());
+    }
+}
@@ -1,11 +1,27 @@
-use crate::game::{Game, GameError, ShootResult};
+use crate::accounts::Accounts;
+use crate::bot::Bot;
+use crate::challenges::Challenges;
+use crate::chat::{ChatFilter, ChatLimiter};
+use crate::game::{Game, GameAction, GameError, Series, ShootResult};
+use crate::history::{GameEvent, History};
+use crate::journal::{Journal, Record};
+use crate::presence::Presence;
 use crate::proto::{ClientMessage, ServerMessage};
-use crate::types::{Layout, Nickname, Position, RestoreState, Who};
+use crate::queue::MatchQueue;
+use crate::rating::Ratings;
+use crate::spectators::Spectators;
+use crate::stats::Stats;
+use crate::types::{
+    ChatChannel, ChatMessage, Difficulty, GameMode, GameOverReason, Hits, Layout, Nickname,
+    Password, PlayerStatus, Position, QueuePreference, RestoreState, ResumeToken, RoomCode, Rules,
+    SpectateState, TokenHash, TurnExpiry, Who,
+};
 use crate::Command;
 use crate::Command::Message;
-use log::{debug, info, trace, warn};
+use log::{debug, error, info, trace, warn};
 use rand::Rng;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io;
 use std::time::{Duration, Instant};
 
 pub struct App {
@@ -13,8 +29,24 @@ pub struct App {
     max_players: usize,
     /// Limit of maximum players.
     session_timeout: Duration,
-    /// A player waiting for opponent.
-    pending_player: Option<usize>,
+    /// Rules of newly created games.
+    rules: Rules,
+    /// Ratings of players indexed by nicknames.
+    ratings: Ratings,
+    /// Statistics of players indexed by nicknames.
+    stats: Stats,
+    /// Replays of finished games indexed by game ids.
+    history: History,
+    /// Accounts of registered players indexed by nicknames.
+    accounts: Accounts,
+    /// Journal of the sessions and the running games.
+    journal: Journal,
+    /// Ids of the sessions recorded in the journal.
+    journaled_sessions: HashSet<usize>,
+    /// Number of the actions recorded in the journal indexed by game ids.
+    journaled_games: HashMap<usize, usize>,
+    /// Players waiting for an opponent.
+    queue: MatchQueue,
     /// Player-id-to-nickname indexed by player ids.
     sessions_nicknames: HashMap<usize, String>,
     /// Player-id-to-last-active indexed by player ids.
@@ -29,15 +61,55 @@ pub struct App {
     peers_sessions: HashMap<usize, usize>,
     /// Player-id-to-peer map indexed by player ids.
     sessions_peers: HashMap<usize, usize>,
+    /// Resume-token-to-player-id map indexed by hashes of resume tokens.
+    resume_tokens: HashMap<TokenHash, usize>,
+    /// Player-id-to-resume-token-hash map indexed by player ids.
+    sessions_tokens: HashMap<usize, TokenHash>,
+    /// Bots playing instead of players indexed by their player ids.
+    bots: HashMap<usize, Bot>,
+    /// Series storage indexed by series ids.
+    series: HashMap<usize, Series>,
+    /// Player-id-to-series map indexed by session ids.
+    sessions_series: HashMap<usize, usize>,
+    /// Private room hosts indexed by room codes.
+    rooms: HashMap<RoomCode, usize>,
+    /// Player-id-to-room-code map indexed by session ids.
+    sessions_rooms: HashMap<usize, RoomCode>,
+    /// Pending challenges between players.
+    challenges: Challenges,
+    /// Players spectating running games.
+    spectators: Spectators,
+    /// Filter of chat messages.
+    chat_filter: Box<dyn ChatFilter>,
+    /// Rate limits of chat messages.
+    chat_limiter: ChatLimiter,
+    /// Last announced statuses of players.
+    presence: Presence,
 }
 
 impl App {
     /// Create a new app.
-    pub fn new(max_players: usize, session_timeout: Duration) -> Self {
+    pub fn new(
+        max_players: usize,
+        session_timeout: Duration,
+        rules: Rules,
+        ratings: Ratings,
+        stats: Stats,
+        history: History,
+        chat_filter: Box<dyn ChatFilter>,
+    ) -> Self {
         App {
             max_players,
             session_timeout,
-            pending_player: None,
+            rules,
+            ratings,
+            stats,
+            history,
+            accounts: Default::default(),
+            journal: Default::default(),
+            journaled_sessions: Default::default(),
+            journaled_games: Default::default(),
+            queue: Default::default(),
             sessions_nicknames: Default::default(),
             last_active: Default::default(),
             nicknames_sessions: Default::default(),
@@ -45,17 +117,65 @@ impl App {
             sessions_games: Default::default(),
             peers_sessions: Default::default(),
             sessions_peers: Default::default(),
+            resume_tokens: Default::default(),
+            sessions_tokens: Default::default(),
+            bots: Default::default(),
+            series: Default::default(),
+            sessions_series: Default::default(),
+            rooms: Default::default(),
+            sessions_rooms: Default::default(),
+            challenges: Default::default(),
+            spectators: Default::default(),
+            chat_filter,
+            chat_limiter: Default::default(),
+            presence: Default::default(),
         }
     }
 
+    /// Set the accounts of registered players, no nickname is registered by default.
+    pub fn with_accounts(mut self, accounts: Accounts) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
     /// Pass the message to the sub-handler based on the message type.
     pub fn handle_message(&mut self, peer_id: &usize, message: ClientMessage) -> Vec<Command> {
         match message {
             ClientMessage::Alive => self.handle_alive(&peer_id),
-            ClientMessage::Login(nickname) => self.handle_login(&peer_id, nickname),
-            ClientMessage::JoinGame => self.handle_join_game(&peer_id),
+            ClientMessage::Login(nickname, password) => {
+                self.handle_login(&peer_id, nickname, password)
+            }
+            ClientMessage::Resume(token) => self.handle_resume(&peer_id, token),
+            ClientMessage::Register(password) => self.handle_register(&peer_id, password),
+            ClientMessage::JoinGame(preference) => self.handle_join_game(&peer_id, preference),
+            ClientMessage::JoinGameBot(difficulty) => {
+                self.handle_join_game_bot(&peer_id, difficulty)
+            }
+            ClientMessage::RoomCreate => self.handle_room_create(&peer_id),
+            ClientMessage::RoomJoin(code) => self.handle_room_join(&peer_id, code),
+            ClientMessage::Challenge(nickname) => self.handle_challenge(&peer_id, nickname),
+            ClientMessage::ChallengeAccept(nickname) => {
+                self.handle_challenge_accept(&peer_id, nickname)
+            }
+            ClientMessage::ChallengeDecline(nickname) => {
+                self.handle_challenge_decline(&peer_id, nickname)
+            }
             ClientMessage::Layout(layout) => self.handle_layout(&peer_id, layout),
+            ClientMessage::LayoutRandom => self.handle_layout_random(&peer_id),
             ClientMessage::Shoot(position) => self.handle_shoot(&peer_id, position),
+            ClientMessage::ShootSalvo(positions) => self.handle_shoot_salvo(&peer_id, positions),
+            ClientMessage::Resign => self.handle_resign(&peer_id),
+            ClientMessage::Rating(nickname) => self.handle_rating(&peer_id, nickname),
+            ClientMessage::Leaderboard(count) => self.handle_leaderboard(&peer_id, count),
+            ClientMessage::ListGames => self.handle_list_games(&peer_id),
+            ClientMessage::Spectate(nickname) => self.handle_spectate(&peer_id, nickname),
+            ClientMessage::SpectateLeave => self.handle_spectate_leave(&peer_id),
+            ClientMessage::Chat(channel, message) => self.handle_chat(&peer_id, channel, message),
+            ClientMessage::ListPlayers => self.handle_list_players(&peer_id),
+            ClientMessage::History(game_id) => self.handle_history(&peer_id, game_id),
+            ClientMessage::RematchOffer => self.handle_rematch_offer(&peer_id),
+            ClientMessage::RematchAccept => self.handle_rematch_accept(&peer_id),
+            ClientMessage::RematchDecline => self.handle_rematch_decline(&peer_id),
             ClientMessage::LeaveGame => self.handle_leave_game(&peer_id),
             ClientMessage::LogOut => self.handle_logout(&peer_id),
         }
@@ -81,16 +201,28 @@ impl App {
     }
 
     /// Handle login command from the client.
-    fn handle_login(&mut self, peer_id: &usize, nickname: Nickname) -> Vec<Command> {
+    fn handle_login(
+        &mut self,
+        peer_id: &usize,
+        nickname: Nickname,
+        password: Option<Password>,
+    ) -> Vec<Command> {
         debug!("peer {:0>16X} wants to login as {}", peer_id, nickname);
         let mut commands = Vec::new();
 
         match self.peers_sessions.get(peer_id) {
-            None => match self.nicknames_sessions.get(nickname.get()) {
+            None if !self.accounts.authorize(nickname.get(), password.as_ref()) => {
+                warn!(
+                    "login of peer {:0>16X} as {} refused - wrong or missing password",
+                    peer_id, nickname
+                );
+                commands.push(Message(*peer_id, ServerMessage::LoginUnauthorized));
+            }
+            None => match self.nicknames_sessions.get(nickname.get()).cloned() {
                 None => {
                     trace!("not registered yet - registering");
 
-                    if self.nicknames_sessions.len() >= self.max_players {
+                    if self.seated_players() >= self.max_players {
                         warn!("registration of player {} refused because the maximum number of players is reached: {}",
                                   nickname.get(),
                                   self.max_players);
@@ -108,81 +240,27 @@ impl App {
                         self.sessions_peers.insert(player_id, *peer_id);
                         self.last_active.insert(player_id, Instant::now());
 
-                        commands.push(Message(*peer_id, ServerMessage::LoginOk))
+                        let token = self.issue_resume_token(&player_id);
+                        commands.push(Message(*peer_id, ServerMessage::LoginOk(token)))
                     }
                 }
                 Some(player_id) => {
-                    if let Some(id) = self.sessions_peers.get(player_id) {
+                    if let Some(id) = self.sessions_peers.get(&player_id) {
                         warn!(
                             "{} is already registered and online with peer {:0>16X}",
                             nickname.get(),
                             id
                         );
                         commands.push(Message(*peer_id, ServerMessage::LoginTaken));
+                    } else if password.is_some() && self.accounts.is_registered(nickname.get()) {
+                        info!("{} is already registered but offline and logged in with the password - restoring the session with peer {}", nickname.get(), peer_id);
+                        commands.extend(self.restore_session(peer_id, &player_id));
                     } else {
-                        info!("{} is already registered but offline - restoring the session with peer {}", nickname.get(), peer_id);
-
-                        {
-                            let last_active = self.last_active.get_mut(&player_id).unwrap();
-                            *last_active = Instant::now();
-                        }
-
-                        self.sessions_peers.insert(*player_id, *peer_id);
-                        self.peers_sessions.insert(*peer_id, *player_id);
-
-                        match self.sessions_games.get(player_id) {
-                            None => {
-                                trace!("not in any game");
-                                commands.push(Message(
-                                    *peer_id,
-                                    ServerMessage::LoginRestored(RestoreState::Lobby),
-                                ));
-                            }
-                            Some(game_id) => {
-                                let game = self.games.get(game_id).unwrap();
-                                let opponent_id = &game.other_player(&player_id);
-                                let opponent_nickname =
-                                    self.sessions_nicknames.get(opponent_id).unwrap();
-
-                                trace!(
-                                    "in game {:0>16X} - notifying opponent {}",
-                                    game_id,
-                                    opponent_nickname
-                                );
-
-                                if let Some(opponent_peer_id) = self.sessions_peers.get(opponent_id)
-                                {
-                                    commands.push(Message(
-                                        *opponent_peer_id,
-                                        ServerMessage::OpponentReady,
-                                    ))
-                                }
-
-                                let (
-                                    on_turn,
-                                    player_board_hits,
-                                    player_board_misses,
-                                    layout,
-                                    opponent_board_hits,
-                                    opponent_board_misses,
-                                    sunk_ships,
-                                ) = game.state(*player_id);
-
-                                commands.push(Message(
-                                    *peer_id,
-                                    ServerMessage::LoginRestored(RestoreState::Game {
-                                        opponent: Nickname::new(opponent_nickname.clone()).unwrap(),
-                                        on_turn,
-                                        player_board_hits,
-                                        player_board_misses,
-                                        layout,
-                                        opponent_board_hits,
-                                        opponent_board_misses,
-                                        sunk_ships,
-                                    }),
-                                ));
-                            }
-                        }
+                        warn!(
+                            "{} is already registered but offline - the session can be restored only by its resume token",
+                            nickname.get()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::LoginTaken));
                     }
                 }
             },
@@ -199,8 +277,178 @@ impl App {
         commands
     }
 
+    /// Handle the resume command from the client, the session of the token gets restored.
+    /// If the session is still online with another peer, that peer is closed and the session
+    /// is taken over with its game, queue, room and challenges, so the player can reconnect
+    /// before the broken connection is detected.
+    fn handle_resume(&mut self, peer_id: &usize, token: ResumeToken) -> Vec<Command> {
+        debug!("peer {:0>16X} wants to resume a session", peer_id);
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id) {
+            None => match self.resume_tokens.get(&token.hash()).cloned() {
+                None => {
+                    warn!(
+                        "resume of peer {:0>16X} refused - unknown resume token",
+                        peer_id
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::ResumeFail));
+                }
+                Some(player_id) => {
+                    if let Some(old_peer_id) = self.sessions_peers.get(&player_id).cloned() {
+                        info!(
+                            "{} is still online with peer {:0>16X} - taking over the session",
+                            self.sessions_nicknames.get(&player_id).unwrap(),
+                            old_peer_id
+                        );
+
+                        self.peers_sessions.remove(&old_peer_id);
+                        commands.push(Command::Close(old_peer_id));
+                    }
+
+                    info!(
+                        "restoring the session of {} with peer {}",
+                        self.sessions_nicknames.get(&player_id).unwrap(),
+                        peer_id
+                    );
+                    commands.extend(self.restore_session(peer_id, &player_id));
+                }
+            },
+            Some(_) => {
+                warn!("peer {:0>16X} is already logged in", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState));
+            }
+        }
+
+        commands
+    }
+
+    /// Bind the session to the peer and send the player a new resume token
+    /// with the state of the session.
+    fn restore_session(&mut self, peer_id: &usize, player_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        {
+            let last_active = self.last_active.get_mut(player_id).unwrap();
+            *last_active = Instant::now();
+        }
+
+        self.sessions_peers.insert(*player_id, *peer_id);
+        self.peers_sessions.insert(*peer_id, *player_id);
+
+        let token = self.issue_resume_token(player_id);
+
+        let game_id = match self.sessions_games.get(player_id).cloned() {
+            // the layouting can't be restored, the player leaves the game
+            Some(game_id) if !self.games.get(&game_id).unwrap().playing() => {
+                commands.extend(self.leave_unstarted_game(game_id, player_id));
+                None
+            }
+            game_id => game_id,
+        };
+
+        match game_id {
+            None => {
+                trace!("not in any game");
+                commands.push(Message(
+                    *peer_id,
+                    ServerMessage::LoginRestored(token, RestoreState::Lobby),
+                ));
+            }
+            Some(game_id) => {
+                let game = self.games.get(&game_id).unwrap();
+                let opponent_id = &game.other_player(player_id);
+                let opponent_nickname = self.sessions_nicknames.get(opponent_id).unwrap();
+
+                trace!(
+                    "in game {:0>16X} - notifying opponent {}",
+                    game_id,
+                    opponent_nickname
+                );
+
+                if let Some(opponent_peer_id) = self.sessions_peers.get(opponent_id) {
+                    commands.push(Message(*opponent_peer_id, ServerMessage::OpponentReady))
+                }
+
+                let (
+                    on_turn,
+                    player_board_hits,
+                    player_board_misses,
+                    layout,
+                    opponent_board_hits,
+                    opponent_board_misses,
+                    sunk_ships,
+                ) = game.state(*player_id);
+
+                commands.push(Message(
+                    *peer_id,
+                    ServerMessage::LoginRestored(
+                        token,
+                        RestoreState::Game {
+                            opponent: Nickname::new(opponent_nickname.clone()).unwrap(),
+                            rules: game.rules().clone(),
+                            on_turn,
+                            shots_left: game.shots_left(),
+                            player_board_hits,
+                            player_board_misses,
+                            layout,
+                            opponent_board_hits,
+                            opponent_board_misses,
+                            sunk_ships,
+                        },
+                    ),
+                ));
+
+                if let Some(remaining) = game.remaining_time() {
+                    commands.push(Message(
+                        *peer_id,
+                        ServerMessage::TurnClock(on_turn, Self::clock_seconds(remaining)),
+                    ));
+                }
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the register command from the client, the nickname of the player gets registered.
+    fn handle_register(&mut self, peer_id: &usize, password: Password) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                let nickname = self.sessions_nicknames.get(&player_id).unwrap();
+                debug!("player {} wants to register", nickname);
+
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                if self.accounts.register(nickname, &password) {
+                    info!("player {} registered", nickname);
+
+                    if let Err(error) = self.accounts.save() {
+                        error!("can't save the accounts: {}", error);
+                    }
+
+                    commands.push(Message(*peer_id, ServerMessage::RegisterOk));
+                } else {
+                    warn!("player {} is already registered", nickname);
+                    commands.push(Message(*peer_id, ServerMessage::RegisterFail));
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't register", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
     /// Handle join game command from the client.
-    fn handle_join_game(&mut self, peer_id: &usize) -> Vec<Command> {
+    fn handle_join_game(&mut self, peer_id: &usize, preference: QueuePreference) -> Vec<Command> {
         let mut commands = Vec::new();
 
         match self.peers_sessions.get(peer_id).cloned() {
@@ -216,64 +464,65 @@ impl App {
                 }
 
                 match self.sessions_games.get(&player_id) {
+                    None if self.sessions_rooms.contains_key(&player_id) => {
+                        warn!(
+                            "{} is already waiting in a private room",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                    }
+                    None if self.queue.contains(&player_id) => {
+                        warn!(
+                            "{} is already waiting for a game",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                    }
+                    None if preference.board_size().is_some_and(|board_size| {
+                        self.rules.with_board_size(board_size).is_err()
+                    }) =>
+                    {
+                        warn!("the fleet does not fit on the preferred board");
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                    }
+                    None if !self.seat_available(&player_id) => {
+                        warn!(
+                            "all game seats are taken - {} can't stop spectating",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                    }
                     None => {
                         trace!("not in any game");
 
-                        match self.pending_player {
-                            None => {
-                                info!(
-                                    "no pending player - {} is set as pending player",
-                                    self.sessions_nicknames.get(&player_id).unwrap()
-                                );
-
-                                self.pending_player = Some(player_id);
-
-                                commands.push(Message(*peer_id, ServerMessage::JoinGameWait))
-                            }
-                            Some(opponent_id) => {
-                                if opponent_id == player_id {
-                                    warn!(
-                                        "{} is already waiting for a game",
-                                        self.sessions_nicknames.get(&player_id).unwrap()
-                                    );
+                        self.spectators.remove(&player_id);
+                        commands.extend(self.end_series(&player_id));
 
-                                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
-                                } else {
-                                    let game = Game::new(opponent_id, player_id);
-                                    let game_id = self.unique_game_id();
-                                    self.games.insert(game_id, game);
+                        info!(
+                            "adding player {} to the queue - preferring {}",
+                            self.sessions_nicknames.get(&player_id).unwrap(),
+                            preference
+                        );
 
-                                    self.sessions_games.insert(player_id, game_id);
-                                    self.sessions_games.insert(opponent_id, game_id);
+                        let rating = self
+                            .ratings
+                            .get(self.sessions_nicknames.get(&player_id).unwrap())
+                            .value();
 
-                                    let nickname = self.sessions_nicknames.get(&player_id).unwrap();
-                                    let opponent_nickname =
-                                        self.sessions_nicknames.get(&opponent_id).unwrap();
+                        self.queue.push(player_id, rating, preference);
 
-                                    info!(
-                                        "a pending player {} is present - creating a game with {}",
-                                        opponent_nickname, nickname
-                                    );
-                                    trace!("adding the game {:0>16X}", game_id);
-                                    self.pending_player = None;
+                        commands.extend(self.handle_matchmaking());
 
-                                    let opponent_peer_id =
-                                        self.sessions_peers.get(&opponent_id).unwrap();
+                        if let Some(position) = self.queue.position(&player_id) {
+                            let wait = match self.queue.estimated_wait(&player_id) {
+                                Some(wait) => Self::clock_seconds(wait).max(1),
+                                None => 0,
+                            };
 
-                                    commands.push(Message(
-                                        *opponent_peer_id,
-                                        ServerMessage::OpponentJoined(
-                                            Nickname::new(nickname.clone()).unwrap(),
-                                        ),
-                                    ));
-                                    commands.push(Message(
-                                        *peer_id,
-                                        ServerMessage::JoinGameOk(
-                                            Nickname::new(opponent_nickname.clone()).unwrap(),
-                                        ),
-                                    ));
-                                }
-                            }
+                            commands.push(Message(
+                                *peer_id,
+                                ServerMessage::JoinGameWait(position as u16, wait),
+                            ))
                         }
                     }
                     Some(_) => {
@@ -294,238 +543,2264 @@ impl App {
         return commands;
     }
 
-    /// Handle the layout command from client
-    fn handle_layout(&mut self, peer_id: &usize, layout: Layout) -> Vec<Command> {
+    /// Create a new game of the two players and notify them.
+    /// The first player starts the game.
+    fn create_game(
+        &mut self,
+        first_player: usize,
+        second_player: usize,
+        rules: Rules,
+        mode: GameMode,
+    ) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let game = Game::new(first_player, second_player, rules.clone(), mode);
+        let game_id = self.unique_game_id();
+        self.games.insert(game_id, game);
+
+        self.sessions_games.insert(first_player, game_id);
+        self.sessions_games.insert(second_player, game_id);
+        self.spectators.remove(&first_player);
+        self.spectators.remove(&second_player);
+
+        trace!("adding the game {:0>16X}", game_id);
+
+        let first_nickname = self.sessions_nicknames.get(&first_player).unwrap();
+        let second_nickname = self.sessions_nicknames.get(&second_player).unwrap();
+
+        if let Some(first_peer_id) = self.sessions_peers.get(&first_player) {
+            commands.push(Message(
+                *first_peer_id,
+                ServerMessage::OpponentJoined(
+                    Nickname::new(second_nickname.clone()).unwrap(),
+                    rules.clone(),
+                ),
+            ));
+        }
+
+        if let Some(second_peer_id) = self.sessions_peers.get(&second_player) {
+            commands.push(Message(
+                *second_peer_id,
+                ServerMessage::JoinGameOk(Nickname::new(first_nickname.clone()).unwrap(), rules),
+            ));
+        }
+
+        commands
+    }
+
+    /// Handle join game against a bot command from the client.
+    fn handle_join_game_bot(&mut self, peer_id: &usize, difficulty: Difficulty) -> Vec<Command> {
         let mut commands = Vec::new();
 
         match self.peers_sessions.get(peer_id).cloned() {
             Some(player_id) => {
                 debug!(
-                    "player {} wants to choose a game layout",
-                    self.sessions_nicknames.get(&player_id).unwrap()
+                    "player {} wants to join a game against a {} bot",
+                    self.sessions_nicknames.get(&player_id).unwrap(),
+                    difficulty
                 );
-                trace!("layout: {}", layout);
+
                 {
                     let last_active = self.last_active.get_mut(&player_id).unwrap();
                     *last_active = Instant::now();
                 }
 
-                match self.sessions_games.get(&player_id) {
-                    None => {
-                        trace!("not in game");
-                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
-                    }
-                    Some(game_id) => {
-                        trace!("in game {}", game_id);
+                if self.sessions_games.contains_key(&player_id) {
+                    warn!(
+                        "{} is already in a game",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if self.queue.contains(&player_id) {
+                    warn!(
+                        "{} is already waiting for a game",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if self.sessions_rooms.contains_key(&player_id) {
+                    warn!(
+                        "{} is already waiting in a private room",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if !self.seat_available(&player_id) {
+                    warn!(
+                        "all game seats are taken - {} can't stop spectating",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else {
+                    commands.extend(self.end_series(&player_id));
 
-                        let game = self.games.get_mut(game_id).unwrap();
+                    let bot = Bot::new(difficulty, self.rules.clone());
 
-                        if game.playing() {
-                            warn!(
-                                "player {} is already playing - can't choose layout",
-                                self.sessions_nicknames.get(&player_id).unwrap()
-                            );
+                    match bot.layout() {
+                        None => {
+                            warn!("the bot can't place its fleet on the board");
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                        }
+                        Some(layout) => {
+                            let bot_id = self.unique_session_key();
+                            let bot_nickname = bot.nickname();
 
-                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
-                        } else {
-                            match game.set_layout(player_id, layout) {
-                                Ok(_) => {
-                                    debug!(
-                                        "layout confirmed for the player {}",
-                                        self.sessions_nicknames.get(&player_id).unwrap()
-                                    );
+                            let mut game =
+                                Game::new(player_id, bot_id, self.rules.clone(), GameMode::Casual);
+                            game.set_layout(bot_id, layout).unwrap();
 
-                                    let opponent_id = game.other_player(&player_id);
-                                    let opponent_peer_id =
-                                        self.sessions_peers.get(&opponent_id).unwrap();
+                            let game_id = self.unique_game_id();
+                            self.games.insert(game_id, game);
 
-                                    commands.push(Message(*peer_id, ServerMessage::LayoutOk));
-                                    commands.push(Message(
-                                        *opponent_peer_id,
-                                        ServerMessage::OpponentReady,
-                                    ));
-                                }
-                                Err(error) => match error {
-                                    GameError::AlreadyHasLayout => {
-                                        warn!(
-                                            "player {} has already a layout",
-                                            self.sessions_nicknames.get(&player_id).unwrap()
-                                        );
-                                        commands
-                                            .push(Message(*peer_id, ServerMessage::IllegalState))
-                                    }
-                                    GameError::InvalidLayout => {
-                                        warn!(
-                                            "player {} has invalid layout",
-                                            self.sessions_nicknames.get(&player_id).unwrap()
-                                        );
-                                        commands.push(Message(*peer_id, ServerMessage::LayoutFail))
-                                    }
-                                    _ => {}
-                                },
-                            }
+                            self.sessions_nicknames
+                                .insert(bot_id, bot_nickname.get().clone());
+                            self.bots.insert(bot_id, bot);
+                            self.sessions_games.insert(player_id, game_id);
+                            self.sessions_games.insert(bot_id, game_id);
+                            self.spectators.remove(&player_id);
+
+                            info!(
+                                "creating a game of {} with the bot {}",
+                                self.sessions_nicknames.get(&player_id).unwrap(),
+                                bot_nickname
+                            );
+                            trace!("adding the game {:0>16X}", game_id);
+
+                            commands.push(Message(
+                                *peer_id,
+                                ServerMessage::JoinGameOk(bot_nickname, self.rules.clone()),
+                            ));
+                            commands.push(Message(*peer_id, ServerMessage::OpponentReady));
                         }
                     }
                 }
             }
             None => {
-                warn!(
-                    "peer {:0>16X} is not logged - can't choose a layout",
-                    peer_id
-                );
+                warn!("peer {:0>16X} is not logged - can't join a game", peer_id);
                 commands.push(Message(*peer_id, ServerMessage::IllegalState))
             }
         }
 
-        return commands;
+        commands
     }
 
-    /// Handle the shoot command from client
-    fn handle_shoot(&mut self, peer_id: &usize, position: Position) -> Vec<Command> {
+    /// Handle create private room command from the client.
+    fn handle_room_create(&mut self, peer_id: &usize) -> Vec<Command> {
         let mut commands = Vec::new();
 
         match self.peers_sessions.get(peer_id).cloned() {
             Some(player_id) => {
                 debug!(
-                    "player {} wants to shoot",
+                    "player {} wants to create a private room",
                     self.sessions_nicknames.get(&player_id).unwrap()
                 );
-                trace!("position: {}", position);
 
                 {
                     let last_active = self.last_active.get_mut(&player_id).unwrap();
                     *last_active = Instant::now();
                 }
 
-                match self.sessions_games.get(&player_id) {
-                    None => {
-                        warn!(
-                            "player {} is not in a game - can't shoot",
-                            self.sessions_nicknames.get(&player_id).unwrap()
-                        );
-                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
-                    }
-                    Some(game_id) => {
-                        trace!("in game {}", game_id);
+                if self.sessions_games.contains_key(&player_id) {
+                    warn!(
+                        "{} is already in a game",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if self.queue.contains(&player_id)
+                    || self.sessions_rooms.contains_key(&player_id)
+                {
+                    warn!(
+                        "{} is already waiting for a game",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if !self.seat_available(&player_id) {
+                    warn!(
+                        "all game seats are taken - {} can't stop spectating",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else {
+                    self.spectators.remove(&player_id);
+                    commands.extend(self.end_series(&player_id));
 
-                        let game = self.games.get_mut(game_id).unwrap();
+                    let code = self.unique_room_code();
 
-                        if !game.playing() {
-                            warn!(
-                                "player {} can't shoot while layouting",
-                                self.sessions_nicknames.get(&player_id).unwrap()
-                            );
-                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
-                        } else {
-                            match game.shoot(player_id, position) {
-                                Ok(result) => {
-                                    let opponent_id = game.other_player(&player_id);
+                    info!(
+                        "player {} creates the private room {}",
+                        self.sessions_nicknames.get(&player_id).unwrap(),
+                        code
+                    );
 
-                                    match result {
-                                        ShootResult::Missed => {
-                                            debug!("missed");
+                    self.rooms.insert(code.clone(), player_id);
+                    self.sessions_rooms.insert(player_id, code.clone());
 
-                                            commands.push(Message(
-                                                *peer_id,
-                                                ServerMessage::ShootMissed,
-                                            ));
-                                            if let Some(opponent_peer_id) =
-                                                self.sessions_peers.get(&opponent_id)
-                                            {
-                                                commands.push(Message(
-                                                    *opponent_peer_id,
-                                                    ServerMessage::OpponentMissed(position),
-                                                ));
-                                            }
-                                        }
-                                        ShootResult::Hit => {
-                                            debug!("hit");
+                    commands.push(Message(*peer_id, ServerMessage::RoomCreateOk(code)));
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't create a room", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
 
-                                            commands
-                                                .push(Message(*peer_id, ServerMessage::ShootHit));
-                                            if let Some(opponent_peer_id) =
-                                                self.sessions_peers.get(&opponent_id)
-                                            {
-                                                commands.push(Message(
-                                                    *opponent_peer_id,
-                                                    ServerMessage::OpponentHit(position),
-                                                ));
-                                            }
-                                        }
-                                        ShootResult::Sunk(ship_kind, placement) => {
-                                            debug!("sunk a ship {} at {}", ship_kind, placement);
+        commands
+    }
 
-                                            commands.push(Message(
-                                                *peer_id,
-                                                ServerMessage::ShootSunk(ship_kind, placement),
-                                            ));
-                                            if let Some(opponent_peer_id) =
-                                                self.sessions_peers.get(&opponent_id)
-                                            {
-                                                commands.push(Message(
-                                                    *opponent_peer_id,
-                                                    ServerMessage::OpponentHit(position),
-                                                ));
-                                            }
-                                        }
+    /// Handle join private room command from the client.
+    fn handle_room_join(&mut self, peer_id: &usize, code: RoomCode) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to join the private room {}",
+                    self.sessions_nicknames.get(&player_id).unwrap(),
+                    code
+                );
+
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                if self.sessions_games.contains_key(&player_id) {
+                    warn!(
+                        "{} is already in a game",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if self.queue.contains(&player_id)
+                    || self.sessions_rooms.contains_key(&player_id)
+                {
+                    warn!(
+                        "{} is already waiting for a game",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if !self.seat_available(&player_id) {
+                    warn!(
+                        "all game seats are taken - {} can't stop spectating",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else {
+                    match self.rooms.remove(&code) {
+                        None => {
+                            warn!("there is no private room {}", code);
+                            commands.push(Message(*peer_id, ServerMessage::RoomJoinFail));
+                        }
+                        Some(host_id) => {
+                            self.sessions_rooms.remove(&host_id);
+                            commands.extend(self.end_series(&player_id));
+
+                            info!(
+                                "{} joins the private room {} - creating a game with {}",
+                                self.sessions_nicknames.get(&player_id).unwrap(),
+                                code,
+                                self.sessions_nicknames.get(&host_id).unwrap()
+                            );
+
+                            commands.extend(self.create_game(
+                                host_id,
+                                player_id,
+                                self.rules.clone(),
+                                GameMode::Casual,
+                            ));
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't join a room", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Check whether the player is not in a game nor waiting for one.
+    fn available(&self, player_id: &usize) -> bool {
+        !self.sessions_games.contains_key(player_id)
+            && !self.queue.contains(player_id)
+            && !self.sessions_rooms.contains_key(player_id)
+    }
+
+    /// Get the number of the logged players taking game seats, spectators don't take any.
+    fn seated_players(&self) -> usize {
+        self.nicknames_sessions
+            .values()
+            .filter(|player_id| self.spectators.game(player_id).is_none())
+            .count()
+    }
+
+    /// Check whether the player has a game seat or can take a free one to stop spectating.
+    fn seat_available(&self, player_id: &usize) -> bool {
+        self.spectators.game(player_id).is_none() || self.seated_players() < self.max_players
+    }
+
+    /// Get the player who challenged the player with the given nickname.
+    fn challenger(&self, nickname: &Nickname, player_id: &usize) -> Option<usize> {
+        self.nicknames_sessions
+            .get(nickname.get())
+            .cloned()
+            .filter(|challenger_id| self.challenges.challenged_by(player_id, challenger_id))
+    }
+
+    /// Cancel the challenge of the player and all challenges to him
+    /// and notify the other players.
+    fn cancel_challenges(&mut self, player_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let nickname = self.sessions_nicknames.get(player_id).unwrap();
+
+        for other_id in self.challenges.remove_player(player_id) {
+            trace!(
+                "cancelling the challenge between {} and {}",
+                nickname,
+                self.sessions_nicknames.get(&other_id).unwrap()
+            );
+
+            if let Some(other_peer_id) = self.sessions_peers.get(&other_id) {
+                commands.push(Message(
+                    *other_peer_id,
+                    ServerMessage::ChallengeExpired(Nickname::new(nickname.clone()).unwrap()),
+                ));
+            }
+        }
+
+        commands
+    }
+
+    /// Handle challenge command from the client.
+    fn handle_challenge(&mut self, peer_id: &usize, nickname: Nickname) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to challenge {}",
+                    self.sessions_nicknames.get(&player_id).unwrap(),
+                    nickname
+                );
+
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                if !self.available(&player_id) {
+                    warn!(
+                        "{} is already in a game or waiting for one",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if self.challenges.contains(&player_id) {
+                    warn!(
+                        "{} has already challenged another player",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else if !self.seat_available(&player_id) {
+                    warn!(
+                        "all game seats are taken - {} can't stop spectating",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                } else {
+                    let challenged = self
+                        .nicknames_sessions
+                        .get(nickname.get())
+                        .cloned()
+                        .filter(|id| *id != player_id && self.available(id));
+
+                    match challenged.and_then(|id| self.sessions_peers.get(&id).map(|p| (id, *p))) {
+                        None => {
+                            info!("player {} can't be challenged", nickname);
+                            commands.push(Message(*peer_id, ServerMessage::ChallengeFail));
+                        }
+                        Some((challenged_id, challenged_peer_id)) => {
+                            let challenger_nickname =
+                                self.sessions_nicknames.get(&player_id).unwrap();
+
+                            info!("player {} challenges {}", challenger_nickname, nickname);
+
+                            commands.push(Message(
+                                challenged_peer_id,
+                                ServerMessage::ChallengeReceived(
+                                    Nickname::new(challenger_nickname.clone()).unwrap(),
+                                ),
+                            ));
+                            commands.push(Message(*peer_id, ServerMessage::ChallengeOk));
+
+                            self.challenges
+                                .add(player_id, challenged_id, Instant::now());
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't challenge a player",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle challenge accept command from the client.
+    fn handle_challenge_accept(&mut self, peer_id: &usize, nickname: Nickname) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to accept the challenge of {}",
+                    self.sessions_nicknames.get(&player_id).unwrap(),
+                    nickname
+                );
+
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.challenger(&nickname, &player_id) {
+                    None => {
+                        warn!(
+                            "player {} is not challenged by {}",
+                            self.sessions_nicknames.get(&player_id).unwrap(),
+                            nickname
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                    }
+                    Some(_) if !self.available(&player_id) => {
+                        warn!(
+                            "{} is already in a game or waiting for one",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                    }
+                    Some(_) if !self.seat_available(&player_id) => {
+                        warn!(
+                            "all game seats are taken - {} can't stop spectating",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                    }
+                    Some(challenger_id) => {
+                        self.challenges.remove(&challenger_id);
+
+                        if !self.available(&challenger_id)
+                            || !self.sessions_peers.contains_key(&challenger_id)
+                            || !self.seat_available(&challenger_id)
+                        {
+                            info!("challenger {} is not available anymore", nickname);
+                            commands.push(Message(*peer_id, ServerMessage::ChallengeFail));
+                        } else {
+                            info!(
+                                "player {} accepts the challenge of {} - creating a game",
+                                self.sessions_nicknames.get(&player_id).unwrap(),
+                                nickname
+                            );
+
+                            commands.extend(self.end_series(&player_id));
+                            commands.extend(self.end_series(&challenger_id));
+                            commands.extend(self.create_game(
+                                challenger_id,
+                                player_id,
+                                self.rules.clone(),
+                                GameMode::Casual,
+                            ));
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't accept a challenge",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle challenge decline command from the client.
+    fn handle_challenge_decline(&mut self, peer_id: &usize, nickname: Nickname) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to decline the challenge of {}",
+                    self.sessions_nicknames.get(&player_id).unwrap(),
+                    nickname
+                );
+
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.challenger(&nickname, &player_id) {
+                    None => {
+                        warn!(
+                            "player {} is not challenged by {}",
+                            self.sessions_nicknames.get(&player_id).unwrap(),
+                            nickname
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState));
+                    }
+                    Some(challenger_id) => {
+                        let challenged_nickname = self.sessions_nicknames.get(&player_id).unwrap();
+
+                        info!(
+                            "player {} declines the challenge of {}",
+                            challenged_nickname, nickname
+                        );
+
+                        self.challenges.remove(&challenger_id);
+
+                        if let Some(challenger_peer_id) = self.sessions_peers.get(&challenger_id) {
+                            commands.push(Message(
+                                *challenger_peer_id,
+                                ServerMessage::ChallengeDeclined(
+                                    Nickname::new(challenged_nickname.clone()).unwrap(),
+                                ),
+                            ));
+                        }
+
+                        commands.push(Message(*peer_id, ServerMessage::ChallengeDeclineOk));
+                    }
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't decline a challenge",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the layout command from client
+    fn handle_layout(&mut self, peer_id: &usize, layout: Layout) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to choose a game layout",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                trace!("layout: {}", layout);
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.sessions_games.get(&player_id).cloned() {
+                    None => {
+                        trace!("not in game");
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                    }
+                    Some(game_id) => {
+                        trace!("in game {}", game_id);
+
+                        let game = self.games.get_mut(&game_id).unwrap();
+
+                        if game.playing() {
+                            warn!(
+                                "player {} is already playing - can't choose layout",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else {
+                            match game.set_layout(player_id, layout) {
+                                Ok(playing) => {
+                                    debug!(
+                                        "layout confirmed for the player {}",
+                                        self.sessions_nicknames.get(&player_id).unwrap()
+                                    );
+
+                                    let opponent_id = game.other_player(&player_id);
+
+                                    commands.push(Message(*peer_id, ServerMessage::LayoutOk));
+                                    if let Some(opponent_peer_id) =
+                                        self.sessions_peers.get(&opponent_id)
+                                    {
+                                        commands.push(Message(
+                                            *opponent_peer_id,
+                                            ServerMessage::OpponentReady,
+                                        ));
                                     }
 
-                                    if let Some(winner) = game.winner() {
-                                        info!(
-                                            "{} vs {} - game over, winner: {}",
-                                            self.sessions_nicknames.get(&player_id).unwrap(),
-                                            self.sessions_nicknames.get(&opponent_id).unwrap(),
-                                            self.sessions_nicknames.get(&winner).unwrap()
-                                        );
+                                    if playing {
+                                        commands.extend(self.turn_clock(game_id));
+                                        commands.extend(self.spectate_turn(game_id));
+                                    }
+                                }
+                                Err(error) => match error {
+                                    GameError::AlreadyHasLayout => {
+                                        warn!(
+                                            "player {} has already a layout",
+                                            self.sessions_nicknames.get(&player_id).unwrap()
+                                        );
+                                        commands
+                                            .push(Message(*peer_id, ServerMessage::IllegalState))
+                                    }
+                                    GameError::InvalidLayout(error) => {
+                                        warn!(
+                                            "player {} has invalid layout: {}",
+                                            self.sessions_nicknames.get(&player_id).unwrap(),
+                                            error
+                                        );
+                                        commands.push(Message(
+                                            *peer_id,
+                                            ServerMessage::LayoutFail(error),
+                                        ))
+                                    }
+                                    error => {
+                                        warn!(
+                                            "player {} can't choose a layout: {:?}",
+                                            self.sessions_nicknames.get(&player_id).unwrap(),
+                                            error
+                                        );
+                                        commands
+                                            .push(Message(*peer_id, ServerMessage::IllegalState))
+                                    }
+                                },
+                            }
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't choose a layout",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        return commands;
+    }
+
+    /// Handle the random layout command from client
+    fn handle_layout_random(&mut self, peer_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants a random game layout",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.sessions_games.get(&player_id).cloned() {
+                    None => {
+                        trace!("not in game");
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                    }
+                    Some(game_id) => {
+                        trace!("in game {}", game_id);
+
+                        let game = self.games.get_mut(&game_id).unwrap();
+
+                        if game.playing() {
+                            warn!(
+                                "player {} is already playing - can't choose layout",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else {
+                            match Layout::random(game.rules()) {
+                                None => {
+                                    warn!(
+                                        "failed to generate a random layout for the player {}",
+                                        self.sessions_nicknames.get(&player_id).unwrap()
+                                    );
+                                    commands
+                                        .push(Message(*peer_id, ServerMessage::LayoutRandomFail))
+                                }
+                                Some(layout) => {
+                                    trace!("layout: {}", layout);
+
+                                    match game.set_layout(player_id, layout.clone()) {
+                                        Ok(playing) => {
+                                            debug!(
+                                                "random layout confirmed for the player {}",
+                                                self.sessions_nicknames.get(&player_id).unwrap()
+                                            );
+
+                                            let opponent_id = game.other_player(&player_id);
+
+                                            commands.push(Message(
+                                                *peer_id,
+                                                ServerMessage::LayoutRandomOk(layout),
+                                            ));
+                                            if let Some(opponent_peer_id) =
+                                                self.sessions_peers.get(&opponent_id)
+                                            {
+                                                commands.push(Message(
+                                                    *opponent_peer_id,
+                                                    ServerMessage::OpponentReady,
+                                                ));
+                                            }
+
+                                            if playing {
+                                                commands.extend(self.turn_clock(game_id));
+                                                commands.extend(self.spectate_turn(game_id));
+                                            }
+                                        }
+                                        Err(error) => {
+                                            warn!(
+                                                "player {} can't use the random layout: {:?}",
+                                                self.sessions_nicknames.get(&player_id).unwrap(),
+                                                error
+                                            );
+                                            commands.push(Message(
+                                                *peer_id,
+                                                ServerMessage::IllegalState,
+                                            ))
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't choose a layout",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the shoot command from client
+    fn handle_shoot(&mut self, peer_id: &usize, position: Position) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to shoot",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                trace!("position: {}", position);
+
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.sessions_games.get(&player_id).cloned() {
+                    None => {
+                        warn!(
+                            "player {} is not in a game - can't shoot",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                    }
+                    Some(game_id) => {
+                        trace!("in game {}", game_id);
+
+                        let game = self.games.get_mut(&game_id).unwrap();
+
+                        if !game.playing() {
+                            warn!(
+                                "player {} can't shoot while layouting",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else {
+                            let turn = game.turn();
+                            let repeated = game.already_shot(player_id, &position);
+
+                            match game.shoot(player_id, position) {
+                                Ok(result) => {
+                                    debug!("{}", result);
+
+                                    commands.extend(self.shot_fired(
+                                        game_id, player_id, position, result, repeated,
+                                    ));
+
+                                    let game = self.games.get(&game_id).unwrap();
+
+                                    if game.winner().is_some() {
+                                        commands.extend(
+                                            self.finish_game(game_id, GameOverReason::FleetSunk),
+                                        );
+                                    } else if game.turn() != turn {
+                                        commands.extend(self.next_turn(game_id));
+                                    }
+                                }
+                                Err(error) => {
+                                    match error {
+                                        GameError::OutOfBoard => warn!(
+                                            "player {} shoots out of the board",
+                                            self.sessions_nicknames.get(&player_id).unwrap()
+                                        ),
+                                        _ => warn!(
+                                            "player {} is not on turn",
+                                            self.sessions_nicknames.get(&player_id).unwrap()
+                                        ),
+                                    }
+                                    commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't shoot", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the shoot salvo command from client
+    fn handle_shoot_salvo(&mut self, peer_id: &usize, positions: Vec<Position>) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to shoot a salvo",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                trace!("salvo: {}", Hits::new(positions.clone()));
+
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.sessions_games.get(&player_id).cloned() {
+                    None => {
+                        warn!(
+                            "player {} is not in a game - can't shoot",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                    }
+                    Some(game_id) => {
+                        trace!("in game {}", game_id);
+
+                        let game = self.games.get_mut(&game_id).unwrap();
+
+                        if !game.playing() {
+                            warn!(
+                                "player {} can't shoot while layouting",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else {
+                            let turn = game.turn();
+
+                            match game.shoot_salvo(player_id, &positions) {
+                                Ok(results) => {
+                                    let opponent_id = game.other_player(&player_id);
+                                    let shots_left =
+                                        if game.winner().is_none() && game.on_turn() == player_id {
+                                            game.shots_left()
+                                        } else {
+                                            0
+                                        };
+
+                                    debug!("salvo of {} shots fired", results.len());
+
+                                    for (position, result) in &results {
+                                        commands.extend(self.record_shot(
+                                            game_id, player_id, *position, *result, false,
+                                        ));
+                                    }
+
+                                    let mut hits = Vec::new();
+                                    let mut misses = Vec::new();
+
+                                    for (position, result) in &results {
+                                        match result {
+                                            ShootResult::Missed => misses.push(*position),
+                                            _ => hits.push(*position),
+                                        }
+                                    }
+
+                                    commands.push(Message(
+                                        *peer_id,
+                                        ServerMessage::ShootSalvo(results, shots_left),
+                                    ));
+                                    if let Some(opponent_peer_id) =
+                                        self.sessions_peers.get(&opponent_id)
+                                    {
+                                        commands.push(Message(
+                                            *opponent_peer_id,
+                                            ServerMessage::OpponentSalvo(
+                                                Hits::new(hits),
+                                                Hits::new(misses),
+                                                shots_left,
+                                            ),
+                                        ));
+                                    }
+
+                                    let game = self.games.get(&game_id).unwrap();
+
+                                    if game.winner().is_some() {
+                                        commands.extend(
+                                            self.finish_game(game_id, GameOverReason::FleetSunk),
+                                        );
+                                    } else if game.turn() != turn {
+                                        commands.extend(self.next_turn(game_id));
+                                    }
+                                }
+                                Err(error) => {
+                                    match error {
+                                        GameError::OutOfBoard => warn!(
+                                            "player {} shoots out of the board",
+                                            self.sessions_nicknames.get(&player_id).unwrap()
+                                        ),
+                                        GameError::InvalidSalvo => warn!(
+                                            "player {} fires an invalid salvo of {} shots",
+                                            self.sessions_nicknames.get(&player_id).unwrap(),
+                                            positions.len()
+                                        ),
+                                        _ => warn!(
+                                            "player {} is not on turn",
+                                            self.sessions_nicknames.get(&player_id).unwrap()
+                                        ),
+                                    }
+                                    commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't shoot", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Start the next turn of the game - let the bot play if it is on turn
+    /// and notify the players about the time left to the player on turn.
+    fn next_turn(&mut self, game_id: usize) -> Vec<Command> {
+        let mut commands = self.play_bot(game_id);
+
+        if self.games.contains_key(&game_id) {
+            commands.extend(self.turn_clock(game_id));
+            commands.extend(self.spectate_turn(game_id));
+        }
+
+        commands
+    }
+
+    /// Notify both players of the game about the time left to the player on turn
+    /// if the game is played with a shot clock.
+    fn turn_clock(&self, game_id: usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let game = self.games.get(&game_id).unwrap();
+
+        if let Some(remaining) = game.remaining_time() {
+            let seconds = Self::clock_seconds(remaining);
+            let player_id = game.on_turn();
+            let opponent_id = game.other_player(&player_id);
+
+            trace!(
+                "{} seconds left to the player {}",
+                seconds,
+                self.sessions_nicknames.get(&player_id).unwrap()
+            );
+
+            if let Some(player_peer_id) = self.sessions_peers.get(&player_id) {
+                commands.push(Message(
+                    *player_peer_id,
+                    ServerMessage::TurnClock(Who::You, seconds),
+                ));
+            }
+
+            if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
+                commands.push(Message(
+                    *opponent_peer_id,
+                    ServerMessage::TurnClock(Who::Opponent, seconds),
+                ));
+            }
+        }
+
+        commands
+    }
+
+    /// Notify the spectators of the game about the player on turn.
+    fn spectate_turn(&self, game_id: usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let game = self.games.get(&game_id).unwrap();
+        let on_turn = self.sessions_nicknames.get(&game.on_turn()).unwrap();
+
+        for spectator_id in self.spectators.of_game(&game_id) {
+            if let Some(spectator_peer_id) = self.sessions_peers.get(spectator_id) {
+                commands.push(Message(
+                    *spectator_peer_id,
+                    ServerMessage::SpectatorTurn(Nickname::new(on_turn.clone()).unwrap()),
+                ));
+            }
+        }
+
+        commands
+    }
+
+    /// Record the shot of the player into the statistics
+    /// and notify the player, the opponent and the spectators about it.
+    fn shot_fired(
+        &mut self,
+        game_id: usize,
+        player_id: usize,
+        position: Position,
+        result: ShootResult,
+        repeated: bool,
+    ) -> Vec<Command> {
+        let mut commands = self.record_shot(game_id, player_id, position, result, repeated);
+
+        let opponent_id = self.games.get(&game_id).unwrap().other_player(&player_id);
+
+        if let Some(player_peer_id) = self.sessions_peers.get(&player_id) {
+            let message = match result {
+                ShootResult::Missed => ServerMessage::ShootMissed,
+                ShootResult::Hit => ServerMessage::ShootHit,
+                ShootResult::Sunk(ship, placement) => ServerMessage::ShootSunk(ship, placement),
+            };
+            commands.push(Message(*player_peer_id, message));
+        }
+
+        if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
+            let message = match result {
+                ShootResult::Missed => ServerMessage::OpponentMissed(position),
+                _ => ServerMessage::OpponentHit(position),
+            };
+            commands.push(Message(*opponent_peer_id, message));
+        }
+
+        commands
+    }
+
+    /// Record the shot of the player into the statistics, unless a bot plays the game
+    /// or the position was already shot at, and notify the spectators about it.
+    fn record_shot(
+        &mut self,
+        game_id: usize,
+        player_id: usize,
+        position: Position,
+        result: ShootResult,
+        repeated: bool,
+    ) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let opponent_id = self.games.get(&game_id).unwrap().other_player(&player_id);
+        let nickname = self.sessions_nicknames.get(&player_id).unwrap();
+
+        if !repeated && !self.bots.contains_key(&player_id) && !self.bots.contains_key(&opponent_id)
+        {
+            self.stats
+                .record_shot(nickname, result != ShootResult::Missed);
+        }
+
+        let shooter = Nickname::new(nickname.clone()).unwrap();
+        for spectator_id in self.spectators.of_game(&game_id) {
+            if let Some(spectator_peer_id) = self.sessions_peers.get(spectator_id) {
+                commands.push(Message(
+                    *spectator_peer_id,
+                    ServerMessage::SpectatorShot(shooter.clone(), position, result),
+                ));
+            }
+        }
+
+        commands
+    }
+
+    /// Notify the spectators of the game that the player left it and stop their spectating.
+    fn spectated_game_left(&mut self, game_id: usize, player_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let nickname = self.sessions_nicknames.get(player_id).unwrap();
+
+        for spectator_id in self.spectators.remove_game(&game_id) {
+            if let Some(spectator_peer_id) = self.sessions_peers.get(&spectator_id) {
+                commands.push(Message(
+                    *spectator_peer_id,
+                    ServerMessage::SpectatorGameLeft(Nickname::new(nickname.clone()).unwrap()),
+                ));
+            }
+        }
+
+        commands
+    }
+
+    /// Get the remaining time in whole seconds rounded up.
+    fn clock_seconds(remaining: Duration) -> u16 {
+        remaining.as_millis().div_ceil(1000) as u16
+    }
+
+    /// Take the shot clock action on the player on turn who did not fire in time.
+    fn expire_turn(&mut self, game_id: usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let game = self.games.get_mut(&game_id).unwrap();
+        let player_id = game.on_turn();
+        let opponent_id = game.other_player(&player_id);
+        let expiry = game.rules().shot_clock().unwrap().expiry();
+        let turn = game.turn();
+
+        info!(
+            "player {} did not fire in time - {}",
+            self.sessions_nicknames.get(&player_id).unwrap(),
+            expiry
+        );
+
+        if let Some(player_peer_id) = self.sessions_peers.get(&player_id) {
+            commands.push(Message(
+                *player_peer_id,
+                ServerMessage::TurnExpired(Who::You, expiry),
+            ));
+        }
+
+        if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
+            commands.push(Message(
+                *opponent_peer_id,
+                ServerMessage::TurnExpired(Who::Opponent, expiry),
+            ));
+        }
+
+        match expiry {
+            TurnExpiry::RandomShot => {
+                // fire all shots left in the turn
+                loop {
+                    let game = self.games.get_mut(&game_id).unwrap();
+
+                    if game.winner().is_some() || game.turn() != turn {
+                        break;
+                    }
+
+                    let position = game.random_target(player_id);
+                    let result = game.shoot(player_id, position).unwrap();
+
+                    debug!("random shot at {}: {}", position, result);
+
+                    commands.extend(self.shot_fired(game_id, player_id, position, result, false));
+                }
+            }
+            TurnExpiry::Pass => game.pass(player_id).unwrap(),
+            TurnExpiry::Forfeit => game.forfeit(player_id),
+        }
+
+        if self.games.get(&game_id).unwrap().winner().is_some() {
+            let reason = match expiry {
+                TurnExpiry::Forfeit => GameOverReason::TimedOut,
+                _ => GameOverReason::FleetSunk,
+            };
+
+            commands.extend(self.finish_game(game_id, reason));
+        } else {
+            commands.extend(self.next_turn(game_id));
+        }
+
+        commands
+    }
+
+    /// Let the bot on turn shoot until the turn passes to its opponent or the game ends.
+    fn play_bot(&mut self, game_id: usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        loop {
+            let game = self.games.get_mut(&game_id).unwrap();
+            let bot_id = game.on_turn();
+
+            let bot = match self.bots.get_mut(&bot_id) {
+                None => break,
+                Some(bot) => bot,
+            };
+
+            let position = bot.next_shot();
+            let result = game.shoot(bot_id, position).unwrap();
+            bot.record(position, &result);
+
+            debug!(
+                "bot {} shoots at {}: {}",
+                self.sessions_nicknames.get(&bot_id).unwrap(),
+                position,
+                result
+            );
+
+            commands.extend(self.shot_fired(game_id, bot_id, position, result, false));
+
+            if self.games.get(&game_id).unwrap().winner().is_some() {
+                commands.extend(self.finish_game(game_id, GameOverReason::FleetSunk));
+                break;
+            }
+        }
+
+        commands
+    }
+
+    /// Close the private room hosted by the player if there is any.
+    fn close_room(&mut self, player_id: &usize) {
+        if let Some(code) = self.sessions_rooms.remove(player_id) {
+            info!(
+                "closing the private room {} of player {}",
+                code,
+                self.sessions_nicknames.get(player_id).unwrap()
+            );
+            self.rooms.remove(&code);
+        }
+    }
+
+    /// Issue a new resume token of the player's session, the previous one is revoked.
+    fn issue_resume_token(&mut self, player_id: &usize) -> ResumeToken {
+        self.remove_resume_token(player_id);
+
+        let token = self.unique_resume_token();
+        self.resume_tokens.insert(token.hash(), *player_id);
+        self.sessions_tokens.insert(*player_id, token.hash());
+
+        // the session is journaled again with the new token
+        self.journaled_sessions.remove(player_id);
+
+        token
+    }
+
+    /// Remove the resume token of the player's session.
+    fn remove_resume_token(&mut self, player_id: &usize) {
+        if let Some(token) = self.sessions_tokens.remove(player_id) {
+            self.resume_tokens.remove(&token);
+        }
+    }
+
+    /// Remove the bot if the player is a bot.
+    fn remove_bot(&mut self, player_id: &usize) {
+        if self.bots.remove(player_id).is_some() {
+            trace!(
+                "removing the bot {}",
+                self.sessions_nicknames.get(player_id).unwrap()
+            );
+            self.sessions_nicknames.remove(player_id);
+        }
+    }
+
+    /// Notify both players of the finished game about the winner and the reason and remove the game.
+    fn finish_game(&mut self, game_id: usize, reason: GameOverReason) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let game = self.games.remove(&game_id).unwrap();
+        let winner = game.winner().unwrap();
+        let loser = game.other_player(&winner);
+
+        info!(
+            "{} vs {} - game over, winner: {}, reason: {}",
+            self.sessions_nicknames.get(&winner).unwrap(),
+            self.sessions_nicknames.get(&loser).unwrap(),
+            self.sessions_nicknames.get(&winner).unwrap(),
+            reason
+        );
+
+        if let Some(winner_peer_id) = self.sessions_peers.get(&winner) {
+            commands.push(Message(
+                *winner_peer_id,
+                ServerMessage::GameOver(Who::You, reason),
+            ));
+        }
+
+        if let Some(loser_peer_id) = self.sessions_peers.get(&loser) {
+            commands.push(Message(
+                *loser_peer_id,
+                ServerMessage::GameOver(Who::Opponent, reason),
+            ));
+        }
+
+        let winner_nickname = self.sessions_nicknames.get(&winner).unwrap();
+
+        for spectator_id in self.spectators.remove_game(&game_id) {
+            if let Some(spectator_peer_id) = self.sessions_peers.get(&spectator_id) {
+                commands.push(Message(
+                    *spectator_peer_id,
+                    ServerMessage::SpectatorGameOver(
+                        Nickname::new(winner_nickname.clone()).unwrap(),
+                        reason,
+                    ),
+                ));
+            }
+        }
+
+        let end = GameEvent::Won(
+            game.elapsed(),
+            Nickname::new(winner_nickname.clone()).unwrap(),
+            reason,
+        );
+        commands.extend(self.record_history(game_id, &game, end));
+
+        trace!("removing the game {:0>16X}", game_id);
+
+        self.sessions_games.remove(&winner);
+        self.sessions_games.remove(&loser);
+
+        if !self.bots.contains_key(&winner) && !self.bots.contains_key(&loser) {
+            commands.extend(self.record_series(&game));
+            self.update_stats(&game);
+
+            if game.mode() == GameMode::Rated {
+                commands.extend(self.update_ratings(&game));
+            }
+        }
+
+        self.remove_bot(&winner);
+        self.remove_bot(&loser);
+
+        commands
+    }
+
+    /// Update the ratings of the players of the finished rated game and notify them.
+    fn update_ratings(&mut self, game: &Game) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let winner = game.winner().unwrap();
+        let loser = game.other_player(&winner);
+        let winner_nickname = self.sessions_nicknames.get(&winner).unwrap();
+        let loser_nickname = self.sessions_nicknames.get(&loser).unwrap();
+
+        let (winner_change, loser_change) = self.ratings.record(winner_nickname, loser_nickname);
+
+        info!(
+            "rating of {} changed by {}, rating of {} changed by {}",
+            winner_nickname, winner_change, loser_nickname, loser_change
+        );
+
+        if let Err(error) = self.ratings.save() {
+            error!("can't save the ratings: {}", error);
+        }
+
+        for &(player_id, change) in &[(winner, winner_change), (loser, loser_change)] {
+            if let Some(peer_id) = self.sessions_peers.get(&player_id) {
+                let nickname = self.sessions_nicknames.get(&player_id).unwrap();
+                commands.push(Message(
+                    *peer_id,
+                    ServerMessage::RatingChanged(self.ratings.get(nickname).value(), change),
+                ));
+            }
+        }
+
+        commands
+    }
+
+    /// Record the finished game into the statistics of its players.
+    fn update_stats(&mut self, game: &Game) {
+        let winner = game.winner().unwrap();
+        let loser = game.other_player(&winner);
+
+        self.stats.record_game(
+            self.sessions_nicknames.get(&winner).unwrap(),
+            true,
+            game.player_turns(winner) as u32,
+        );
+        self.stats.record_game(
+            self.sessions_nicknames.get(&loser).unwrap(),
+            false,
+            game.player_turns(loser) as u32,
+        );
+
+        if let Err(error) = self.stats.save() {
+            error!("can't save the statistics: {}", error);
+        }
+    }
+
+    /// Record the history of the ended game and tell its players the id of the replay.
+    fn record_history(&mut self, game_id: usize, game: &Game, end: GameEvent) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let nickname = |player_id: &usize| {
+            Nickname::new(self.sessions_nicknames.get(player_id).unwrap().clone()).unwrap()
+        };
+        let (first_player, second_player) = game.players();
+
+        let mut events = vec![GameEvent::Started(
+            game.created_at(),
+            nickname(&first_player),
+            nickname(&second_player),
+            game.rules().clone(),
+            game.mode(),
+        )];
+        events.extend(game.history().iter().map(|(time, player_id, action)| {
+            let player = nickname(player_id);
+
+            match action {
+                GameAction::Layout(layout) => GameEvent::Layout(*time, player, layout.clone()),
+                GameAction::Shot(position, result) => {
+                    GameEvent::Shot(*time, player, *position, *result)
+                }
+                GameAction::Pass => GameEvent::Pass(*time, player),
+            }
+        }));
+        events.push(end);
+
+        if let Err(error) = self.history.record(game_id, &events) {
+            error!(
+                "can't record the history of the game {:0>16X}: {}",
+                game_id, error
+            );
+        }
+
+        for player_id in &[first_player, second_player] {
+            if let Some(peer_id) = self.sessions_peers.get(player_id) {
+                commands.push(Message(*peer_id, ServerMessage::GameRecorded(game_id)));
+            }
+        }
+
+        commands
+    }
+
+    /// Remove the player from the game which has not started yet and notify the opponent.
+    fn leave_unstarted_game(&mut self, game_id: usize, player_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let game = self.games.remove(&game_id).unwrap();
+        let opponent_id = &game.other_player(player_id);
+
+        info!(
+            "removing player {} from the non-started game with {}",
+            self.sessions_nicknames.get(player_id).unwrap(),
+            self.sessions_nicknames.get(opponent_id).unwrap()
+        );
+        trace!("notifying opponent");
+
+        commands.extend(self.spectated_game_left(game_id, player_id));
+        commands.extend(self.record_abandoned(game_id, &game, player_id));
+        self.sessions_games.remove(player_id);
+        self.sessions_games.remove(opponent_id);
+        self.remove_bot(opponent_id);
+
+        if let Some(opponent_peer_id) = self.sessions_peers.get(opponent_id) {
+            commands.push(Message(*opponent_peer_id, ServerMessage::OpponentLeft))
+        }
+
+        commands
+    }
+
+    /// Record the history of the game left by the player before it was decided.
+    fn record_abandoned(&mut self, game_id: usize, game: &Game, player_id: &usize) -> Vec<Command> {
+        let nickname = self.sessions_nicknames.get(player_id).unwrap();
+        let end = GameEvent::Abandoned(game.elapsed(), Nickname::new(nickname.clone()).unwrap());
+
+        self.record_history(game_id, game, end)
+    }
+
+    /// Handle the game history query from client
+    fn handle_history(&mut self, peer_id: &usize, game_id: usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants the history of the game {:0>16X}",
+                    self.sessions_nicknames.get(&player_id).unwrap(),
+                    game_id
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.history.replay(game_id) {
+                    Ok(Some(lines)) => {
+                        commands.push(Message(*peer_id, ServerMessage::HistoryOk(lines)));
+                    }
+                    Ok(None) => {
+                        debug!("the game {:0>16X} is not recorded", game_id);
+                        commands.push(Message(*peer_id, ServerMessage::HistoryFail));
+                    }
+                    Err(error) => {
+                        error!(
+                            "can't read the history of the game {:0>16X}: {}",
+                            game_id, error
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::HistoryFail));
+                    }
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't get a game history",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the leaderboard query from client
+    fn handle_leaderboard(&mut self, peer_id: &usize, count: u8) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                let nickname = self.sessions_nicknames.get(&player_id).unwrap();
+
+                debug!(
+                    "player {} wants to know the top {} players",
+                    nickname, count
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                let leaderboard = self.stats.leaderboard();
+                let rank = leaderboard
+                    .iter()
+                    .position(|(other, _)| *other == nickname)
+                    .map_or(0, |index| index as u32 + 1);
+                let players = leaderboard
+                    .into_iter()
+                    .take(count as usize)
+                    .map(|(nickname, stats)| (Nickname::new(nickname.clone()).unwrap(), *stats))
+                    .collect();
+
+                commands.push(Message(*peer_id, ServerMessage::Leaderboard(players, rank)));
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't query the leaderboard",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the running games query from client
+    fn handle_list_games(&mut self, peer_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to list the running games",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                let games = self
+                    .games
+                    .values()
+                    .map(|game| {
+                        let (first_player, second_player) = game.players();
+
+                        (
+                            Nickname::new(
+                                self.sessions_nicknames.get(&first_player).unwrap().clone(),
+                            )
+                            .unwrap(),
+                            Nickname::new(
+                                self.sessions_nicknames.get(&second_player).unwrap().clone(),
+                            )
+                            .unwrap(),
+                        )
+                    })
+                    .collect();
+
+                commands.push(Message(*peer_id, ServerMessage::GamesList(games)));
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't list games", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the spectate command from client
+    fn handle_spectate(&mut self, peer_id: &usize, nickname: Nickname) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to spectate the game of {}",
+                    self.sessions_nicknames.get(&player_id).unwrap(),
+                    nickname
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                let game_id = self
+                    .nicknames_sessions
+                    .get(nickname.get())
+                    .and_then(|spectated_id| self.sessions_games.get(spectated_id))
+                    .cloned();
+
+                if self.sessions_games.contains_key(&player_id) {
+                    warn!(
+                        "player {} is in a game - can't spectate",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                } else if let Some(game_id) = game_id {
+                    info!(
+                        "player {} spectates the game of {}",
+                        self.sessions_nicknames.get(&player_id).unwrap(),
+                        nickname
+                    );
+
+                    self.spectators.add(player_id, game_id);
+
+                    let game = self.games.get(&game_id).unwrap();
+                    let (first_player, second_player) = game.players();
+
+                    commands.push(Message(
+                        *peer_id,
+                        ServerMessage::SpectateOk(SpectateState {
+                            first_player: Nickname::new(
+                                self.sessions_nicknames.get(&first_player).unwrap().clone(),
+                            )
+                            .unwrap(),
+                            second_player: Nickname::new(
+                                self.sessions_nicknames.get(&second_player).unwrap().clone(),
+                            )
+                            .unwrap(),
+                            rules: game.rules().clone(),
+                            on_turn: Nickname::new(
+                                self.sessions_nicknames
+                                    .get(&game.on_turn())
+                                    .unwrap()
+                                    .clone(),
+                            )
+                            .unwrap(),
+                            first_board: game.board_view(first_player),
+                            second_board: game.board_view(second_player),
+                        }),
+                    ));
+                } else {
+                    warn!("player {} is not in a game", nickname);
+                    commands.push(Message(*peer_id, ServerMessage::SpectateFail));
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't spectate", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the spectate leave command from client
+    fn handle_spectate_leave(&mut self, peer_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to stop spectating",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                if self.spectators.game(&player_id).is_none() {
+                    warn!(
+                        "player {} is not spectating any game",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                } else if !self.seat_available(&player_id) {
+                    warn!(
+                        "all game seats are taken - {} can't stop spectating",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                } else {
+                    self.spectators.remove(&player_id);
+                    commands.push(Message(*peer_id, ServerMessage::SpectateLeaveOk));
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't stop spectating",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Get the status of the logged player.
+    fn player_status(&self, player_id: &usize) -> PlayerStatus {
+        if !self.sessions_peers.contains_key(player_id) {
+            PlayerStatus::Offline
+        } else if self.sessions_games.contains_key(player_id) {
+            PlayerStatus::Game
+        } else {
+            PlayerStatus::Lobby
+        }
+    }
+
+    /// Handle the online players query from client
+    fn handle_list_players(&mut self, peer_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to list the players",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                let players = self
+                    .nicknames_sessions
+                    .iter()
+                    .map(|(nickname, player_id)| {
+                        (
+                            Nickname::new(nickname.clone()).unwrap(),
+                            self.player_status(player_id),
+                        )
+                    })
+                    .collect();
+
+                commands.push(Message(*peer_id, ServerMessage::PlayersList(players)));
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't list players", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the chat message from client
+    fn handle_chat(
+        &mut self,
+        peer_id: &usize,
+        channel: ChatChannel,
+        message: ChatMessage,
+    ) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                let nickname = self.sessions_nicknames.get(&player_id).unwrap().clone();
+
+                debug!("player {} wants to chat in {}", nickname, channel);
+                trace!("message: {}", message);
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                let game_id = self.sessions_games.get(&player_id).cloned();
+
+                let recipients = match (channel, game_id) {
+                    (ChatChannel::Opponent, Some(game_id)) => {
+                        let game = self.games.get(&game_id).unwrap();
+                        let opponent_id = game.other_player(&player_id);
+
+                        Some(
+                            self.sessions_peers
+                                .get(&opponent_id)
+                                .cloned()
+                                .into_iter()
+                                .collect::<Vec<_>>(),
+                        )
+                    }
+                    (ChatChannel::Lobby, None) => Some(
+                        self.sessions_peers
+                            .iter()
+                            .filter(|(other_id, _)| {
+                                **other_id != player_id
+                                    && !self.sessions_games.contains_key(other_id)
+                            })
+                            .map(|(_, other_peer_id)| *other_peer_id)
+                            .collect(),
+                    ),
+                    _ => None,
+                };
+
+                match recipients {
+                    None => {
+                        warn!("player {} can't chat in {} now", nickname, channel);
+                        commands.push(Message(*peer_id, ServerMessage::ChatFail));
+                    }
+                    Some(_) if !self.chat_limiter.allow(player_id, Instant::now()) => {
+                        warn!("player {} sends chat messages too often", nickname);
+                        commands.push(Message(*peer_id, ServerMessage::ChatRateLimited));
+                    }
+                    Some(recipients) => match self.chat_filter.filter(&nickname, message) {
+                        None => {
+                            warn!("chat message of player {} was blocked", nickname);
+                            commands.push(Message(*peer_id, ServerMessage::ChatBlocked));
+                        }
+                        Some(message) => {
+                            for recipient_peer_id in recipients {
+                                commands.push(Message(
+                                    recipient_peer_id,
+                                    ServerMessage::ChatReceived(
+                                        channel,
+                                        Nickname::new(nickname.clone()).unwrap(),
+                                        message.clone(),
+                                    ),
+                                ));
+                            }
+
+                            commands.push(Message(*peer_id, ServerMessage::ChatOk));
+                        }
+                    },
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't chat", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the rating query from client
+    fn handle_rating(&mut self, peer_id: &usize, nickname: Option<Nickname>) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                let nickname = match nickname {
+                    Some(nickname) => nickname,
+                    None => Nickname::new(self.sessions_nicknames.get(&player_id).unwrap().clone())
+                        .unwrap(),
+                };
+
+                debug!(
+                    "player {} wants to know the rating of {}",
+                    self.sessions_nicknames.get(&player_id).unwrap(),
+                    nickname
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                if self.ratings.contains(nickname.get())
+                    || self.nicknames_sessions.contains_key(nickname.get())
+                {
+                    let rating = self.ratings.get(nickname.get());
+
+                    commands.push(Message(
+                        *peer_id,
+                        ServerMessage::RatingOk(nickname, rating.value(), rating.games()),
+                    ));
+                } else {
+                    warn!("player {} is unknown", nickname);
+                    commands.push(Message(*peer_id, ServerMessage::RatingFail));
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't query ratings", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Record the result of the finished game into the series of its players
+    /// and notify them about the score.
+    fn record_series(&mut self, game: &Game) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let winner = game.winner().unwrap();
+        let loser = game.other_player(&winner);
+
+        let series_id = match self.sessions_series.get(&winner).cloned() {
+            Some(series_id) => series_id,
+            None => {
+                let series_id = self.unique_series_id();
+                let first_player = game.starting_player();
+                let second_player = game.other_player(&first_player);
+
+                trace!("adding the series {:0>16X}", series_id);
+
+                self.series.insert(
+                    series_id,
+                    Series::new(
+                        first_player,
+                        second_player,
+                        game.rules().clone(),
+                        game.mode(),
+                    ),
+                );
+                self.sessions_series.insert(winner, series_id);
+                self.sessions_series.insert(loser, series_id);
+
+                series_id
+            }
+        };
+
+        let series = self.series.get_mut(&series_id).unwrap();
+        series.record(game);
+
+        for player_id in &[winner, loser] {
+            if let Some(peer_id) = self.sessions_peers.get(player_id) {
+                let (wins, opponent_wins) = series.score(player_id);
+                commands.push(Message(
+                    *peer_id,
+                    ServerMessage::SeriesScore(wins, opponent_wins),
+                ));
+            }
+        }
+
+        commands
+    }
+
+    /// End the series of the player, the opponent is notified that no rematch is possible.
+    fn end_series(&mut self, player_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        if let Some(series_id) = self.sessions_series.remove(player_id) {
+            let series = self.series.remove(&series_id).unwrap();
+            let opponent_id = series.other_player(player_id);
+
+            trace!("removing the series {:0>16X}", series_id);
+
+            self.sessions_series.remove(&opponent_id);
+
+            if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
+                commands.push(Message(
+                    *opponent_peer_id,
+                    ServerMessage::OpponentRematchDecline,
+                ));
+            }
+        }
+
+        commands
+    }
 
-                                        commands.push(Message(
-                                            *peer_id,
-                                            ServerMessage::GameOver(if winner == player_id {
-                                                Who::You
-                                            } else {
-                                                Who::Opponent
-                                            }),
-                                        ));
+    /// Handle the rematch offer command from client
+    fn handle_rematch_offer(&mut self, peer_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
 
-                                        if let Some(opponent_peer_id) =
-                                            self.sessions_peers.get(&opponent_id)
-                                        {
-                                            commands.push(Message(
-                                                *opponent_peer_id,
-                                                ServerMessage::GameOver(if winner == opponent_id {
-                                                    Who::You
-                                                } else {
-                                                    Who::Opponent
-                                                }),
-                                            ));
-                                        }
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to offer a rematch",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
 
-                                        trace!("removing the game {:0>16X}", game_id);
+                match self.sessions_series.get(&player_id).cloned() {
+                    None => {
+                        warn!(
+                            "player {} has no finished game - can't offer a rematch",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                    }
+                    Some(series_id) => {
+                        let seat_available = self.seat_available(&player_id);
+                        let series = self.series.get_mut(&series_id).unwrap();
+                        let opponent_id = series.other_player(&player_id);
 
-                                        self.games.remove(game_id);
-                                        self.sessions_games.remove(&player_id);
-                                        self.sessions_games.remove(&opponent_id);
-                                    }
-                                }
-                                Err(_) => {
+                        if self.sessions_games.contains_key(&player_id) {
+                            warn!(
+                                "player {} is already in a game",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else if series.offered_by().is_some() {
+                            warn!("a rematch is already offered");
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else if !seat_available {
+                            warn!(
+                                "all game seats are taken - {} can't stop spectating",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else {
+                            match self.sessions_peers.get(&opponent_id) {
+                                None => {
                                     warn!(
-                                        "player {} is not on turn",
-                                        self.sessions_nicknames.get(&player_id).unwrap()
+                                        "opponent {} is offline - can't offer a rematch",
+                                        self.sessions_nicknames.get(&opponent_id).unwrap()
                                     );
                                     commands.push(Message(*peer_id, ServerMessage::IllegalState))
                                 }
+                                Some(opponent_peer_id) => {
+                                    info!(
+                                        "player {} offers a rematch to {}",
+                                        self.sessions_nicknames.get(&player_id).unwrap(),
+                                        self.sessions_nicknames.get(&opponent_id).unwrap()
+                                    );
+
+                                    series.offer(player_id);
+
+                                    commands.push(Message(
+                                        *opponent_peer_id,
+                                        ServerMessage::OpponentRematchOffer,
+                                    ));
+                                    commands.push(Message(*peer_id, ServerMessage::RematchOfferOk));
+                                }
                             }
                         }
                     }
                 }
             }
             None => {
-                warn!("peer {:0>16X} is not logged - can't shoot", peer_id);
+                warn!(
+                    "peer {:0>16X} is not logged - can't offer a rematch",
+                    peer_id
+                );
                 commands.push(Message(*peer_id, ServerMessage::IllegalState))
             }
         }
 
-        return commands;
+        commands
+    }
+
+    /// Handle the rematch accept command from client
+    fn handle_rematch_accept(&mut self, peer_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to accept a rematch",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.sessions_series.get(&player_id).cloned() {
+                    None => {
+                        warn!(
+                            "player {} has no finished game - can't accept a rematch",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                    }
+                    Some(series_id) => {
+                        let game_id = self.unique_game_id();
+                        let opponent_id = self
+                            .series
+                            .get(&series_id)
+                            .unwrap()
+                            .other_player(&player_id);
+                        let seats_available =
+                            self.seat_available(&player_id) && self.seat_available(&opponent_id);
+                        let series = self.series.get_mut(&series_id).unwrap();
+
+                        if series.offered_by() != Some(opponent_id) {
+                            warn!(
+                                "no rematch is offered to player {}",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else if !seats_available {
+                            warn!(
+                                "all game seats are taken - {} can't stop spectating",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else {
+                            let game = series.rematch();
+                            let on_turn = game.on_turn();
+                            self.games.insert(game_id, game);
+
+                            self.sessions_games.insert(player_id, game_id);
+                            self.sessions_games.insert(opponent_id, game_id);
+                            self.spectators.remove(&player_id);
+                            self.spectators.remove(&opponent_id);
+
+                            info!(
+                                "player {} accepts the rematch with {}",
+                                self.sessions_nicknames.get(&player_id).unwrap(),
+                                self.sessions_nicknames.get(&opponent_id).unwrap()
+                            );
+                            trace!("adding the game {:0>16X}", game_id);
+
+                            for id in &[opponent_id, player_id] {
+                                if let Some(peer_id) = self.sessions_peers.get(id) {
+                                    let who = if *id == on_turn {
+                                        Who::You
+                                    } else {
+                                        Who::Opponent
+                                    };
+
+                                    commands.push(Message(
+                                        *peer_id,
+                                        ServerMessage::RematchStarted(who),
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't accept a rematch",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the rematch decline command from client
+    fn handle_rematch_decline(&mut self, peer_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to decline a rematch",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                if !self.sessions_series.contains_key(&player_id) {
+                    warn!(
+                        "player {} has no finished game - can't decline a rematch",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                } else if self.sessions_games.contains_key(&player_id) {
+                    warn!(
+                        "player {} is already in a game",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+                    commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                } else {
+                    info!(
+                        "player {} declines a rematch",
+                        self.sessions_nicknames.get(&player_id).unwrap()
+                    );
+
+                    commands.extend(self.end_series(&player_id));
+                    commands.push(Message(*peer_id, ServerMessage::RematchDeclineOk));
+                }
+            }
+            None => {
+                warn!(
+                    "peer {:0>16X} is not logged - can't decline a rematch",
+                    peer_id
+                );
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
+    }
+
+    /// Handle the resign command from client
+    fn handle_resign(&mut self, peer_id: &usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        match self.peers_sessions.get(peer_id).cloned() {
+            Some(player_id) => {
+                debug!(
+                    "player {} wants to resign",
+                    self.sessions_nicknames.get(&player_id).unwrap()
+                );
+                {
+                    let last_active = self.last_active.get_mut(&player_id).unwrap();
+                    *last_active = Instant::now();
+                }
+
+                match self.sessions_games.get(&player_id).cloned() {
+                    None => {
+                        warn!(
+                            "player {} is not in a game - can't resign",
+                            self.sessions_nicknames.get(&player_id).unwrap()
+                        );
+                        commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                    }
+                    Some(game_id) => {
+                        let game = self.games.get_mut(&game_id).unwrap();
+
+                        if !game.playing() {
+                            warn!(
+                                "player {} can't resign while layouting",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+                            commands.push(Message(*peer_id, ServerMessage::IllegalState))
+                        } else {
+                            info!(
+                                "player {} resigns",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+
+                            game.forfeit(player_id);
+                            commands.extend(self.finish_game(game_id, GameOverReason::Resigned));
+                        }
+                    }
+                }
+            }
+            None => {
+                warn!("peer {:0>16X} is not logged - can't resign", peer_id);
+                commands.push(Message(*peer_id, ServerMessage::IllegalState))
+            }
+        }
+
+        commands
     }
 
     /// Handle the leave game command from client
@@ -544,8 +2819,20 @@ impl App {
                 }
 
                 match self.sessions_games.get(&player_id) {
-                    None => match self.pending_player {
-                        None => {
+                    None if self.sessions_rooms.contains_key(&player_id) => {
+                        self.close_room(&player_id);
+
+                        commands.push(Message(*peer_id, ServerMessage::LeaveGameOk));
+                    }
+                    None => {
+                        if self.queue.remove(&player_id) {
+                            info!(
+                                "removing player {} from game pending queue",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
+
+                            commands.push(Message(*peer_id, ServerMessage::LeaveGameOk));
+                        } else {
                             warn!(
                                 "player {} is not in a game - can't leave any",
                                 self.sessions_nicknames.get(&player_id).unwrap()
@@ -553,35 +2840,22 @@ impl App {
 
                             commands.push(Message(*peer_id, ServerMessage::IllegalState))
                         }
-                        Some(pending_player_id) => {
-                            if pending_player_id == player_id {
-                                info!(
-                                    "removing player {} from game pending queue",
-                                    self.sessions_nicknames.get(&player_id).unwrap()
-                                );
-
-                                self.pending_player = None;
-
-                                commands.push(Message(*peer_id, ServerMessage::LeaveGameOk));
-                            }
-                        }
-                    },
+                    }
                     Some(game_id) => {
-                        let game = self.games.remove(game_id).unwrap();
-                        let opponent_id = &game.other_player(&player_id);
-
-                        info!(
-                            "removing player {} from game with {}",
-                            self.sessions_nicknames.get(&player_id).unwrap(),
-                            self.sessions_nicknames.get(opponent_id).unwrap()
-                        );
-                        trace!("notifying opponent");
+                        let game_id = *game_id;
+                        let game = self.games.get_mut(&game_id).unwrap();
 
-                        self.sessions_games.remove(&player_id);
-                        self.sessions_games.remove(opponent_id);
+                        if game.playing() {
+                            info!(
+                                "player {} loses the game - left",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
 
-                        if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
-                            commands.push(Message(*opponent_peer_id, ServerMessage::OpponentLeft))
+                            game.forfeit(player_id);
+                            commands.extend(self.finish_game(game_id, GameOverReason::Resigned));
+                            commands.extend(self.end_series(&player_id));
+                        } else {
+                            commands.extend(self.leave_unstarted_game(game_id, &player_id));
                         }
 
                         commands.push(Message(*peer_id, ServerMessage::LeaveGameOk));
@@ -615,38 +2889,61 @@ impl App {
                 // handle if the session is in any game
                 match self.sessions_games.get(&player_id) {
                     None => {
-                        if let Some(pending_player_id) = self.pending_player {
-                            if pending_player_id == player_id {
-                                info!(
-                                    "removing player {} from game pending queue",
-                                    self.sessions_nicknames.get(&player_id).unwrap()
-                                );
-                                self.pending_player = None;
-                            }
+                        self.close_room(&player_id);
+
+                        if self.queue.remove(&player_id) {
+                            info!(
+                                "removing player {} from game pending queue",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
                         } else {
                             trace!("not in any game");
                         }
                     }
                     Some(game_id) => {
-                        let game = self.games.remove(&game_id).unwrap();
-                        let opponent_id = game.other_player(&player_id);
+                        let game_id = *game_id;
+                        let game = self.games.get_mut(&game_id).unwrap();
 
-                        info!(
-                            "removing player {} from game with {}",
-                            self.sessions_nicknames.get(&player_id).unwrap(),
-                            self.sessions_nicknames.get(&opponent_id).unwrap()
-                        );
-                        trace!("notifying opponent");
+                        if game.playing() {
+                            info!(
+                                "player {} loses the game - logged out",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
 
-                        self.sessions_games.remove(&player_id);
-                        self.sessions_games.remove(&opponent_id);
+                            game.forfeit(player_id);
+                            commands
+                                .extend(self.finish_game(game_id, GameOverReason::Disconnected));
+                        } else {
+                            let game = self.games.remove(&game_id).unwrap();
+                            let opponent_id = game.other_player(&player_id);
 
-                        if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
-                            commands.push(Message(*opponent_peer_id, ServerMessage::OpponentLeft))
+                            info!(
+                                "removing player {} from game with {}",
+                                self.sessions_nicknames.get(&player_id).unwrap(),
+                                self.sessions_nicknames.get(&opponent_id).unwrap()
+                            );
+                            trace!("notifying opponent");
+
+                            commands.extend(self.spectated_game_left(game_id, &player_id));
+                            commands.extend(self.record_abandoned(game_id, &game, &player_id));
+                            self.sessions_games.remove(&player_id);
+                            self.sessions_games.remove(&opponent_id);
+                            self.remove_bot(&opponent_id);
+
+                            if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
+                                commands
+                                    .push(Message(*opponent_peer_id, ServerMessage::OpponentLeft))
+                            }
                         }
                     }
                 }
 
+                commands.extend(self.end_series(&player_id));
+                commands.extend(self.cancel_challenges(&player_id));
+                self.spectators.remove(&player_id);
+                self.chat_limiter.remove(&player_id);
+
+                self.remove_resume_token(&player_id);
                 self.nicknames_sessions
                     .remove(self.sessions_nicknames.get(&player_id).unwrap());
                 self.sessions_nicknames.remove(&player_id);
@@ -678,14 +2975,13 @@ impl App {
                 // handle if the session is in any game
                 match self.sessions_games.get(&player_id).cloned() {
                     None => {
-                        if let Some(pending_player_id) = self.pending_player {
-                            if pending_player_id == player_id {
-                                info!(
-                                    "removing player {} from game pending queue",
-                                    self.sessions_nicknames.get(&player_id).unwrap()
-                                );
-                                self.pending_player = None;
-                            }
+                        self.close_room(&player_id);
+
+                        if self.queue.remove(&player_id) {
+                            info!(
+                                "removing player {} from game pending queue",
+                                self.sessions_nicknames.get(&player_id).unwrap()
+                            );
                         } else {
                             trace!("not in any game");
                         }
@@ -695,21 +2991,7 @@ impl App {
                         let opponent_id = game.other_player(&player_id);
 
                         if !game.playing() {
-                            info!(
-                                "removing player {} from the non-started game with {}",
-                                self.sessions_nicknames.get(&player_id).unwrap(),
-                                self.sessions_nicknames.get(&opponent_id).unwrap()
-                            );
-                            trace!("notifying opponent");
-
-                            self.sessions_games.remove(&player_id);
-                            self.sessions_games.remove(&opponent_id);
-                            self.games.remove(&game_id);
-
-                            if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
-                                commands
-                                    .push(Message(*opponent_peer_id, ServerMessage::OpponentLeft))
-                            }
+                            commands.extend(self.leave_unstarted_game(game_id, &player_id));
                         } else {
                             trace!(
                                 "in the game with {} - notifying",
@@ -726,6 +3008,12 @@ impl App {
                     }
                 }
 
+                commands.extend(self.cancel_challenges(&player_id));
+
+                if let Some(game_id) = self.spectators.remove(&player_id) {
+                    trace!("stopped spectating the game {:0>16X}", game_id);
+                }
+
                 self.sessions_peers.remove(&player_id);
                 self.peers_sessions.remove(&peer_id);
             }
@@ -734,6 +3022,126 @@ impl App {
         commands
     }
 
+    /// Take the shot clock actions in games where the player on turn did not fire in time.
+    pub fn handle_shot_clocks(&mut self) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let expired = self
+            .games
+            .iter()
+            .filter_map(|(game_id, game)| match game.remaining_time() {
+                Some(remaining) if remaining == Duration::from_secs(0) => Some(*game_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for game_id in expired {
+            commands.extend(self.expire_turn(game_id));
+        }
+
+        commands
+    }
+
+    /// Create games for the players in the queue who accept each other.
+    pub fn handle_matchmaking(&mut self) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        for (first_player, second_player, preference) in self.queue.take_matches() {
+            info!(
+                "matching {} with {} - creating a game on {}",
+                self.sessions_nicknames.get(&first_player).unwrap(),
+                self.sessions_nicknames.get(&second_player).unwrap(),
+                preference
+            );
+
+            let rules = match preference.board_size() {
+                Some(board_size) => self.rules.with_board_size(board_size).unwrap(),
+                None => self.rules.clone(),
+            };
+
+            commands.extend(self.create_game(
+                first_player,
+                second_player,
+                rules,
+                preference.mode(),
+            ));
+        }
+
+        commands
+    }
+
+    /// Expire challenges which were not answered in time.
+    pub fn handle_challenges(&mut self) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        for (challenger_id, challenged_id) in self.challenges.expire(Instant::now()) {
+            let challenger_nickname = self.sessions_nicknames.get(&challenger_id).unwrap();
+            let challenged_nickname = self.sessions_nicknames.get(&challenged_id).unwrap();
+
+            info!(
+                "the challenge of {} to {} expired",
+                challenger_nickname, challenged_nickname
+            );
+
+            if let Some(challenger_peer_id) = self.sessions_peers.get(&challenger_id) {
+                commands.push(Message(
+                    *challenger_peer_id,
+                    ServerMessage::ChallengeExpired(
+                        Nickname::new(challenged_nickname.clone()).unwrap(),
+                    ),
+                ));
+            }
+
+            if let Some(challenged_peer_id) = self.sessions_peers.get(&challenged_id) {
+                commands.push(Message(
+                    *challenged_peer_id,
+                    ServerMessage::ChallengeExpired(
+                        Nickname::new(challenger_nickname.clone()).unwrap(),
+                    ),
+                ));
+            }
+        }
+
+        commands
+    }
+
+    /// Notify the online players about the players who changed their status or logged out
+    /// since the last announcement.
+    pub fn handle_presence(&mut self) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let current = self
+            .nicknames_sessions
+            .iter()
+            .map(|(nickname, player_id)| (nickname.clone(), self.player_status(player_id)))
+            .collect();
+
+        for (nickname, status) in self.presence.update(current) {
+            match status {
+                Some(status) => trace!("player {} is now in the status {}", nickname, status),
+                None => trace!("player {} logged out", nickname),
+            }
+
+            let subject_id = self.nicknames_sessions.get(&nickname);
+
+            for (player_id, peer_id) in &self.sessions_peers {
+                if subject_id == Some(player_id) {
+                    continue;
+                }
+
+                let nickname = Nickname::new(nickname.clone()).unwrap();
+                let message = match status {
+                    Some(status) => ServerMessage::PlayerStatusChanged(nickname, status),
+                    None => ServerMessage::PlayerLoggedOut(nickname),
+                };
+
+                commands.push(Message(*peer_id, message));
+            }
+        }
+
+        commands
+    }
+
     /// Do clean up of inactive sessions.
     pub fn handle_cleanup(&mut self) -> Vec<Command> {
         let mut commands = Vec::new();
@@ -755,52 +3163,74 @@ impl App {
             .collect::<Vec<_>>();
 
         to_remove.iter().for_each(|player_id| {
-            let nickname = self.sessions_nicknames.get(player_id).unwrap();
-            warn!("removing player {} - inactive for too long", nickname);
-
-            self.nicknames_sessions.remove(nickname);
-            self.sessions_nicknames.remove(player_id);
-            self.last_active.remove(player_id);
-
-            if let Some(peer_id) = self.sessions_peers.remove(player_id) {
-                self.peers_sessions.remove(&peer_id);
-                commands.push(Command::Close(peer_id));
-            }
+            warn!(
+                "removing player {} - inactive for too long",
+                self.sessions_nicknames.get(player_id).unwrap()
+            );
 
             // handle if the session is in any game
-            match self.sessions_games.get(player_id) {
+            match self.sessions_games.get(player_id).cloned() {
                 None => {
-                    if let Some(pending_player_id) = self.pending_player {
-                        if pending_player_id == *player_id {
-                            info!(
-                                "removing player {} from game pending queue",
-                                self.sessions_nicknames.get(&player_id).unwrap()
-                            );
-                            self.pending_player = None;
-                        }
+                    self.close_room(player_id);
+
+                    if self.queue.remove(player_id) {
+                        info!(
+                            "removing player {} from game pending queue",
+                            self.sessions_nicknames.get(player_id).unwrap()
+                        );
                     } else {
                         trace!("not in any game");
                     }
                 }
                 Some(game_id) => {
-                    let game = self.games.remove(&game_id).unwrap();
-                    let opponent_id = game.other_player(player_id);
+                    let game = self.games.get_mut(&game_id).unwrap();
 
-                    info!(
-                        "removing player {} from game with {}",
-                        self.sessions_nicknames.get(&player_id).unwrap(),
-                        self.sessions_nicknames.get(&opponent_id).unwrap()
-                    );
-                    trace!("notifying opponent");
+                    if game.playing() {
+                        info!(
+                            "player {} loses the game - disconnected",
+                            self.sessions_nicknames.get(player_id).unwrap()
+                        );
+
+                        game.forfeit(*player_id);
+                        commands.extend(self.finish_game(game_id, GameOverReason::Disconnected));
+                    } else {
+                        let opponent_id = game.other_player(player_id);
+
+                        info!(
+                            "removing player {} from game with {}",
+                            self.sessions_nicknames.get(player_id).unwrap(),
+                            self.sessions_nicknames.get(&opponent_id).unwrap()
+                        );
+                        trace!("notifying opponent");
 
-                    self.sessions_games.remove(&player_id);
-                    self.sessions_games.remove(&opponent_id);
+                        commands.extend(self.spectated_game_left(game_id, player_id));
+                        let game = self.games.remove(&game_id).unwrap();
+                        commands.extend(self.record_abandoned(game_id, &game, player_id));
+                        self.sessions_games.remove(player_id);
+                        self.sessions_games.remove(&opponent_id);
+                        self.remove_bot(&opponent_id);
 
-                    if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
-                        commands.push(Message(*opponent_peer_id, ServerMessage::OpponentLeft))
+                        if let Some(opponent_peer_id) = self.sessions_peers.get(&opponent_id) {
+                            commands.push(Message(*opponent_peer_id, ServerMessage::OpponentLeft))
+                        }
                     }
                 }
             }
+
+            commands.extend(self.end_series(player_id));
+            commands.extend(self.cancel_challenges(player_id));
+            self.spectators.remove(player_id);
+            self.chat_limiter.remove(player_id);
+
+            self.remove_resume_token(player_id);
+            let nickname = self.sessions_nicknames.remove(player_id).unwrap();
+            self.nicknames_sessions.remove(&nickname);
+            self.last_active.remove(player_id);
+
+            if let Some(peer_id) = self.sessions_peers.remove(player_id) {
+                self.peers_sessions.remove(&peer_id);
+                commands.push(Command::Close(peer_id));
+            }
         });
 
         commands
@@ -823,13 +3253,224 @@ impl App {
         self.peers_sessions.clear();
         self.nicknames_sessions.clear();
         self.sessions_nicknames.clear();
+        self.resume_tokens.clear();
+        self.sessions_tokens.clear();
         self.last_active.clear();
         self.games.clear();
         self.sessions_games.clear();
+        self.bots.clear();
+        self.series.clear();
+        self.sessions_series.clear();
+        self.rooms.clear();
+        self.sessions_rooms.clear();
+        self.challenges.clear();
 
         commands
     }
 
+    /// Restore the sessions and the running games from the records of the journal,
+    /// take a new snapshot of them and keep recording their changes into the journal.
+    /// The restored players are offline until they log in again.
+    pub fn restore(&mut self, journal: Journal, records: Vec<Record>) -> io::Result<()> {
+        self.journal = journal;
+
+        for record in records {
+            match record {
+                Record::Session(player_id, nickname, token_hash) => {
+                    // a session journaled again has a new resume token
+                    self.remove_resume_token(&player_id);
+                    self.nicknames_sessions.insert(nickname.clone(), player_id);
+                    self.sessions_nicknames.insert(player_id, nickname);
+                    self.resume_tokens.insert(token_hash.clone(), player_id);
+                    self.sessions_tokens.insert(player_id, token_hash);
+                    self.last_active.insert(player_id, Instant::now());
+                }
+                Record::Bot(bot_id, difficulty) => {
+                    let bot = Bot::new(difficulty, self.rules.clone());
+                    self.sessions_nicknames
+                        .insert(bot_id, bot.nickname().get().clone());
+                    self.bots.insert(bot_id, bot);
+                }
+                Record::SessionRemoved(player_id) => {
+                    if let Some(nickname) = self.sessions_nicknames.remove(&player_id) {
+                        if self.bots.remove(&player_id).is_none() {
+                            self.nicknames_sessions.remove(&nickname);
+                        }
+                    }
+                    self.remove_resume_token(&player_id);
+                    self.last_active.remove(&player_id);
+                }
+                Record::Game(game_id, created_at, first_player, second_player, rules, mode) => {
+                    for player_id in &[first_player, second_player] {
+                        // the bot plays by the rules of its game
+                        if let Some(bot) = self.bots.get_mut(player_id) {
+                            *bot = Bot::new(bot.difficulty(), rules.clone());
+                        }
+                        self.sessions_games.insert(*player_id, game_id);
+                    }
+
+                    let game = Game::restore(first_player, second_player, rules, mode, created_at);
+                    self.games.insert(game_id, game);
+                }
+                Record::Action(game_id, time, player_id, action) => {
+                    let game = match self.games.get_mut(&game_id) {
+                        Some(game) => game,
+                        None => {
+                            warn!(
+                                "can't replay an action in the unknown game {:0>16X}",
+                                game_id
+                            );
+                            continue;
+                        }
+                    };
+
+                    if let Err(error) = game.replay(time, player_id, action) {
+                        warn!(
+                            "can't replay an action in the game {:0>16X}: {:?}",
+                            game_id, error
+                        );
+                        continue;
+                    }
+
+                    if let Some(bot) = self.bots.get_mut(&player_id) {
+                        if let Some((_, _, GameAction::Shot(position, result))) =
+                            game.history().last()
+                        {
+                            bot.record(*position, result);
+                        }
+                    }
+                }
+                Record::GameRemoved(game_id) => {
+                    if let Some(game) = self.games.remove(&game_id) {
+                        let (first_player, second_player) = game.players();
+                        self.sessions_games.remove(&first_player);
+                        self.sessions_games.remove(&second_player);
+                    }
+                }
+            }
+        }
+
+        info!(
+            "restored {} sessions and {} games",
+            self.sessions_nicknames.len() - self.bots.len(),
+            self.games.len()
+        );
+
+        self.journaled_sessions = self.sessions_nicknames.keys().cloned().collect();
+        self.journaled_games = self
+            .games
+            .iter()
+            .map(|(game_id, game)| (*game_id, game.history().len()))
+            .collect();
+
+        self.journal.snapshot(&self.state_records())
+    }
+
+    /// Append the changes of the sessions and the running games to the journal,
+    /// a new snapshot is taken once the journal grows long.
+    pub fn handle_persistence(&mut self) {
+        let mut records = Vec::new();
+
+        let games = &self.games;
+        self.journaled_games.retain(|game_id, _| {
+            let running = games.contains_key(game_id);
+            if !running {
+                records.push(Record::GameRemoved(*game_id));
+            }
+            running
+        });
+
+        let sessions_nicknames = &self.sessions_nicknames;
+        self.journaled_sessions.retain(|player_id| {
+            let active = sessions_nicknames.contains_key(player_id);
+            if !active {
+                records.push(Record::SessionRemoved(*player_id));
+            }
+            active
+        });
+
+        for (player_id, nickname) in &self.sessions_nicknames {
+            if self.journaled_sessions.insert(*player_id) {
+                records.push(self.session_record(*player_id, nickname));
+            }
+        }
+
+        for (game_id, game) in &self.games {
+            let journaled = self.journaled_games.entry(*game_id).or_insert_with(|| {
+                records.push(Self::game_record(*game_id, game));
+                0
+            });
+
+            for (time, player_id, action) in &game.history()[*journaled..] {
+                records.push(Record::Action(*game_id, *time, *player_id, action.clone()));
+            }
+            *journaled = game.history().len();
+        }
+
+        if records.is_empty() {
+            return;
+        }
+
+        trace!("appending {} records to the journal", records.len());
+
+        if let Err(error) = self.journal.append(&records) {
+            error!("can't append to the journal: {}", error);
+        }
+
+        if self.journal.needs_snapshot() {
+            debug!("taking a snapshot of the sessions and games");
+
+            if let Err(error) = self.journal.snapshot(&self.state_records()) {
+                error!("can't take a snapshot of the sessions and games: {}", error);
+            }
+        }
+    }
+
+    /// Get the journal records of the current sessions and running games.
+    fn state_records(&self) -> Vec<Record> {
+        let mut records = Vec::new();
+
+        for (player_id, nickname) in &self.sessions_nicknames {
+            records.push(self.session_record(*player_id, nickname));
+        }
+
+        for (game_id, game) in &self.games {
+            records.push(Self::game_record(*game_id, game));
+
+            for (time, player_id, action) in game.history() {
+                records.push(Record::Action(*game_id, *time, *player_id, action.clone()));
+            }
+        }
+
+        records
+    }
+
+    /// Get the journal record of the creation of the session.
+    fn session_record(&self, player_id: usize, nickname: &str) -> Record {
+        match self.bots.get(&player_id) {
+            Some(bot) => Record::Bot(player_id, bot.difficulty()),
+            None => Record::Session(
+                player_id,
+                String::from(nickname),
+                self.sessions_tokens.get(&player_id).unwrap().clone(),
+            ),
+        }
+    }
+
+    /// Get the journal record of the creation of the game.
+    fn game_record(game_id: usize, game: &Game) -> Record {
+        let (first_player, second_player) = game.players();
+
+        Record::Game(
+            game_id,
+            game.created_at(),
+            first_player,
+            second_player,
+            game.rules().clone(),
+            game.mode(),
+        )
+    }
+
     /// Get a unique id for a session.
     fn unique_session_key(&self) -> usize {
         loop {
@@ -840,6 +3481,36 @@ impl App {
         }
     }
 
+    /// Get a unique resume token for a session.
+    fn unique_resume_token(&self) -> ResumeToken {
+        loop {
+            let token = ResumeToken::random();
+            if !self.resume_tokens.contains_key(&token.hash()) {
+                break token;
+            }
+        }
+    }
+
+    /// Get a unique code for a private room.
+    fn unique_room_code(&self) -> RoomCode {
+        loop {
+            let code = RoomCode::random();
+            if !self.rooms.contains_key(&code) {
+                break code;
+            }
+        }
+    }
+
+    /// Get a unique id for a series.
+    fn unique_series_id(&self) -> usize {
+        loop {
+            let id = rand::thread_rng().gen();
+            if !self.series.contains_key(&id) {
+                break id;
+            }
+        }
+    }
+
     /// Get a unique id for a game.
     fn unique_game_id(&self) -> usize {
         loop {
@@ -850,3 +3521,427 @@ impl App {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::app::App;
+    use crate::chat::BlockedWords;
+    use crate::proto::{ClientMessage, ServerMessage};
+    use crate::types::{
+        BoardView, Hits, Layout, Nickname, Orientation, Placement, Position, QueuePreference,
+        ResumeToken, RoomCode, Rules, ShipsPlacements, SpectateState,
+    };
+    use crate::Command;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn app(max_players: usize) -> App {
+        App::new(
+            max_players,
+            Duration::from_secs(60),
+            Rules::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Box::new(BlockedWords::default()),
+        )
+    }
+
+    fn nickname(nickname: &str) -> Nickname {
+        Nickname::new(String::from(nickname)).unwrap()
+    }
+
+    /// Serialize the sent messages with their peers, a closed peer gets `close`.
+    fn serialized(commands: Vec<Command>) -> Vec<(usize, String)> {
+        commands
+            .into_iter()
+            .map(|command| match command {
+                Command::Message(peer_id, message) => (peer_id, message.serialize()),
+                Command::Close(peer_id) => (peer_id, String::from("close")),
+            })
+            .collect()
+    }
+
+    /// Handle the message of the peer and get the serialized messages sent to the peers.
+    fn handle(app: &mut App, peer_id: usize, message: ClientMessage) -> Vec<(usize, String)> {
+        serialized(app.handle_message(&peer_id, message))
+    }
+
+    /// Get the names of the messages sent to the peer.
+    fn sent_to(messages: &[(usize, String)], peer_id: usize) -> Vec<&str> {
+        messages
+            .iter()
+            .filter(|(id, _)| *id == peer_id)
+            .map(|(_, message)| message.split(':').next().unwrap())
+            .collect()
+    }
+
+    fn login(app: &mut App, peer_id: usize, name: &str) -> Vec<(usize, String)> {
+        handle(app, peer_id, ClientMessage::Login(nickname(name), None))
+    }
+
+    /// Get the payload items of the message of the name sent to the peer.
+    fn payload<'a>(messages: &'a [(usize, String)], peer_id: usize, name: &str) -> Vec<&'a str> {
+        let message = messages
+            .iter()
+            .find(|(id, message)| *id == peer_id && message.split(':').next() == Some(name))
+            .map(|(_, message)| message)
+            .unwrap();
+
+        message[name.len() + 1..].split(';').collect()
+    }
+
+    /// Get the resume token sent to the peer in the message of the name.
+    fn resume_token(messages: &[(usize, String)], peer_id: usize, name: &str) -> ResumeToken {
+        ResumeToken::new(String::from(payload(messages, peer_id, name)[0])).unwrap()
+    }
+
+    /// Place the ships of the fleet to every other row.
+    fn layout(rules: &Rules) -> Layout {
+        let placements = rules
+            .fleet()
+            .ships()
+            .into_iter()
+            .enumerate()
+            .map(|(i, ship)| {
+                let position = Position::new(i as u8 * 2, 0).unwrap();
+                (ship, Placement::new(position, Orientation::East))
+            })
+            .collect();
+
+        Layout::new(placements).unwrap()
+    }
+
+    /// Start a game of the two logged players by a challenge.
+    fn challenge(app: &mut App, (peer_id, name): (usize, &str), (other_id, other): (usize, &str)) {
+        handle(app, peer_id, ClientMessage::Challenge(nickname(other)));
+        let messages = handle(
+            app,
+            other_id,
+            ClientMessage::ChallengeAccept(nickname(name)),
+        );
+        assert_eq!(sent_to(&messages, peer_id), vec!["opponent_joined"]);
+    }
+
+    #[test]
+    fn test_seats() {
+        let mut app = app(3);
+        login(&mut app, 1, "alice");
+        login(&mut app, 2, "bob");
+        login(&mut app, 3, "carol");
+        challenge(&mut app, (1, "alice"), (2, "bob"));
+
+        assert_eq!(sent_to(&login(&mut app, 4, "dave"), 4), vec!["login_full"]);
+
+        // the spectator gives up the seat
+        let messages = handle(&mut app, 3, ClientMessage::Spectate(nickname("alice")));
+        assert_eq!(sent_to(&messages, 3), vec!["spectate_ok"]);
+        assert_eq!(sent_to(&login(&mut app, 4, "dave"), 4), vec!["login_ok"]);
+        assert_eq!(sent_to(&login(&mut app, 5, "erin"), 5), vec!["login_full"]);
+
+        // and can't take it back while all seats are taken
+        let messages = handle(&mut app, 3, ClientMessage::SpectateLeave);
+        assert_eq!(sent_to(&messages, 3), vec!["illegal_state"]);
+        let messages = handle(
+            &mut app,
+            3,
+            ClientMessage::JoinGame(QueuePreference::default()),
+        );
+        assert_eq!(sent_to(&messages, 3), vec!["illegal_state"]);
+
+        handle(&mut app, 4, ClientMessage::LogOut);
+        let messages = handle(&mut app, 3, ClientMessage::SpectateLeave);
+        assert_eq!(sent_to(&messages, 3), vec!["spectate_leave_ok"]);
+        assert_eq!(app.seated_players(), 3);
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut app = app(10);
+        let token = resume_token(&login(&mut app, 1, "alice"), 1, "login_ok");
+        app.handle_offline(&1);
+
+        let messages = handle(&mut app, 2, ClientMessage::Resume(token.clone()));
+        assert_eq!(sent_to(&messages, 2), vec!["login_restored"]);
+        let restored = resume_token(&messages, 2, "login_restored");
+        assert_ne!(restored, token);
+
+        // the previous token is revoked by the restore
+        app.handle_offline(&2);
+        let messages = handle(&mut app, 3, ClientMessage::Resume(token));
+        assert_eq!(sent_to(&messages, 3), vec!["resume_fail"]);
+        let messages = handle(&mut app, 3, ClientMessage::Resume(restored));
+        assert_eq!(sent_to(&messages, 3), vec!["login_restored"]);
+
+        // a logged peer can't resume
+        let messages = handle(&mut app, 3, ClientMessage::Resume(ResumeToken::random()));
+        assert_eq!(sent_to(&messages, 3), vec!["illegal_state"]);
+    }
+
+    #[test]
+    fn test_resume_unknown_token() {
+        let mut app = app(10);
+        login(&mut app, 1, "alice");
+
+        let messages = handle(&mut app, 2, ClientMessage::Resume(ResumeToken::random()));
+        assert_eq!(messages, vec![(2, String::from("resume_fail"))]);
+        assert!(app.peers_sessions.get(&2).is_none());
+    }
+
+    #[test]
+    fn test_resume_takeover() {
+        let mut app = app(10);
+        let token = resume_token(&login(&mut app, 1, "alice"), 1, "login_ok");
+        login(&mut app, 2, "bob");
+        challenge(&mut app, (1, "alice"), (2, "bob"));
+        handle(&mut app, 1, ClientMessage::LayoutRandom);
+        handle(&mut app, 2, ClientMessage::LayoutRandom);
+
+        // the session is taken over while its peer is still online
+        let messages = handle(&mut app, 3, ClientMessage::Resume(token));
+        assert_eq!(sent_to(&messages, 1), vec!["close"]);
+        assert_eq!(sent_to(&messages, 3), vec!["login_restored"]);
+        assert_eq!(
+            payload(&messages, 3, "login_restored")[1..3],
+            ["game", "bob"]
+        );
+        assert_eq!(sent_to(&messages, 2), vec!["opponent_ready"]);
+
+        // closing the old peer leaves the session and its game alone
+        assert!(app.handle_offline(&1).is_empty());
+        let messages = handle(&mut app, 1, ClientMessage::Resign);
+        assert_eq!(sent_to(&messages, 1), vec!["illegal_state"]);
+
+        let messages = handle(&mut app, 3, ClientMessage::Resign);
+        assert_eq!(sent_to(&messages, 3)[0], "game_over");
+        assert_eq!(sent_to(&messages, 2)[0], "game_over");
+    }
+
+    #[test]
+    fn test_resume_takeover_layouting() {
+        let mut app = app(10);
+        let token = resume_token(&login(&mut app, 1, "alice"), 1, "login_ok");
+        login(&mut app, 2, "bob");
+        challenge(&mut app, (1, "alice"), (2, "bob"));
+
+        // the game which has not started yet is left
+        let messages = handle(&mut app, 3, ClientMessage::Resume(token));
+        assert_eq!(sent_to(&messages, 1), vec!["close"]);
+        assert!(sent_to(&messages, 2).contains(&"opponent_left"));
+        assert_eq!(payload(&messages, 3, "login_restored")[1..], ["lobby"]);
+        assert!(app.sessions_games.is_empty());
+    }
+
+    #[test]
+    fn test_spectator_fog_of_war() {
+        let mut app = app(10);
+        login(&mut app, 1, "alice");
+        login(&mut app, 2, "bob");
+        login(&mut app, 3, "carol");
+        challenge(&mut app, (1, "alice"), (2, "bob"));
+        handle(
+            &mut app,
+            1,
+            ClientMessage::Layout(layout(&Rules::default())),
+        );
+        handle(
+            &mut app,
+            2,
+            ClientMessage::Layout(layout(&Rules::default())),
+        );
+
+        let game = app.games.values().next().unwrap();
+        let (first_player, second_player) = game.players();
+        let on_turn = game.on_turn();
+        let on_turn_peer = *app.sessions_peers.get(&on_turn).unwrap();
+        let target = Position::new(0, 0).unwrap();
+        handle(&mut app, on_turn_peer, ClientMessage::Shoot(target));
+
+        let messages = handle(&mut app, 3, ClientMessage::Spectate(nickname("alice")));
+        let board_view = |player_id| {
+            let hits = if player_id == on_turn {
+                vec![]
+            } else {
+                vec![target]
+            };
+            BoardView::new(
+                Hits::new(hits),
+                Hits::new(vec![]),
+                ShipsPlacements::new(HashMap::new()),
+            )
+        };
+        let game = app.games.values().next().unwrap();
+        let state = SpectateState {
+            first_player: nickname(app.sessions_nicknames.get(&first_player).unwrap()),
+            second_player: nickname(app.sessions_nicknames.get(&second_player).unwrap()),
+            rules: Rules::default(),
+            on_turn: nickname(app.sessions_nicknames.get(&game.on_turn()).unwrap()),
+            first_board: board_view(first_player),
+            second_board: board_view(second_player),
+        };
+        assert_eq!(
+            messages,
+            vec![(3, ServerMessage::SpectateOk(state).serialize())]
+        );
+
+        // the spectator learns only the shot positions and their results
+        let on_turn = game.on_turn();
+        let on_turn_peer = *app.sessions_peers.get(&on_turn).unwrap();
+        let target = Position::new(0, 1).unwrap();
+        let messages = handle(&mut app, on_turn_peer, ClientMessage::Shoot(target));
+        let shooter = app.sessions_nicknames.get(&on_turn).unwrap();
+        assert_eq!(
+            messages
+                .iter()
+                .find(|(peer_id, _)| *peer_id == 3)
+                .unwrap()
+                .1,
+            format!("spectator_shot:{};0;1;hit", shooter)
+        );
+        assert!(sent_to(&messages, 3)
+            .iter()
+            .all(|name| *name == "spectator_shot" || *name == "spectator_turn"));
+    }
+
+    #[test]
+    fn test_spectator_offline() {
+        let mut app = app(10);
+        login(&mut app, 1, "alice");
+        login(&mut app, 2, "bob");
+        login(&mut app, 3, "carol");
+        challenge(&mut app, (1, "alice"), (2, "bob"));
+        handle(&mut app, 3, ClientMessage::Spectate(nickname("alice")));
+
+        let spectator_id = *app.peers_sessions.get(&3).unwrap();
+        let game_id = app.spectators.game(&spectator_id).unwrap();
+
+        app.handle_offline(&3);
+        assert!(app.spectators.game(&spectator_id).is_none());
+        assert!(app.spectators.of_game(&game_id).is_empty());
+
+        // the game goes on without notifying the former spectator
+        handle(&mut app, 1, ClientMessage::LayoutRandom);
+        let messages = handle(&mut app, 2, ClientMessage::LayoutRandom);
+        assert!(messages
+            .iter()
+            .all(|(_, message)| !message.starts_with("spectator")));
+    }
+
+    /// Create a private room of the player and get its code.
+    fn room_create(app: &mut App, peer_id: usize) -> RoomCode {
+        let messages = handle(app, peer_id, ClientMessage::RoomCreate);
+        let code = payload(&messages, peer_id, "room_create_ok")[0];
+
+        RoomCode::new(String::from(code)).unwrap()
+    }
+
+    /// Let the first player win the started game by the resignation of the second one.
+    fn resign(app: &mut App, peer_id: usize, other_id: usize) {
+        handle(app, peer_id, ClientMessage::LayoutRandom);
+        handle(app, other_id, ClientMessage::LayoutRandom);
+        let messages = handle(app, other_id, ClientMessage::Resign);
+        assert_eq!(sent_to(&messages, peer_id)[0], "game_over");
+    }
+
+    #[test]
+    fn test_room() {
+        let mut app = app(10);
+        login(&mut app, 1, "alice");
+        login(&mut app, 2, "bob");
+        login(&mut app, 3, "carol");
+
+        let code = room_create(&mut app, 1);
+        let messages = handle(&mut app, 1, ClientMessage::RoomCreate);
+        assert_eq!(sent_to(&messages, 1), vec!["illegal_state"]);
+        let messages = handle(&mut app, 2, ClientMessage::RoomJoin(RoomCode::random()));
+        assert_eq!(sent_to(&messages, 2), vec!["room_join_fail"]);
+
+        let messages = handle(&mut app, 2, ClientMessage::RoomJoin(code.clone()));
+        assert_eq!(sent_to(&messages, 1), vec!["opponent_joined"]);
+        assert_eq!(sent_to(&messages, 2), vec!["join_game_ok"]);
+
+        // the room is closed once joined
+        let messages = handle(&mut app, 3, ClientMessage::RoomJoin(code));
+        assert_eq!(sent_to(&messages, 3), vec!["room_join_fail"]);
+
+        // and once its host leaves it
+        let code = room_create(&mut app, 3);
+        let messages = handle(&mut app, 3, ClientMessage::LeaveGame);
+        assert_eq!(sent_to(&messages, 3), vec!["leave_game_ok"]);
+        login(&mut app, 4, "dave");
+        let messages = handle(&mut app, 4, ClientMessage::RoomJoin(code));
+        assert_eq!(sent_to(&messages, 4), vec!["room_join_fail"]);
+    }
+
+    #[test]
+    fn test_room_host_offline() {
+        let mut app = app(10);
+        login(&mut app, 1, "alice");
+        login(&mut app, 2, "bob");
+
+        let code = room_create(&mut app, 1);
+        app.handle_offline(&1);
+
+        let messages = handle(&mut app, 2, ClientMessage::RoomJoin(code));
+        assert_eq!(sent_to(&messages, 2), vec!["room_join_fail"]);
+        assert!(app.rooms.is_empty());
+        assert!(app.sessions_rooms.is_empty());
+    }
+
+    #[test]
+    fn test_rematch() {
+        let mut app = app(10);
+        login(&mut app, 1, "alice");
+        login(&mut app, 2, "bob");
+        challenge(&mut app, (1, "alice"), (2, "bob"));
+        resign(&mut app, 1, 2);
+
+        let messages = handle(&mut app, 2, ClientMessage::RematchAccept);
+        assert_eq!(sent_to(&messages, 2), vec!["illegal_state"]);
+
+        let messages = handle(&mut app, 1, ClientMessage::RematchOffer);
+        assert_eq!(sent_to(&messages, 1), vec!["rematch_offer_ok"]);
+        assert_eq!(sent_to(&messages, 2), vec!["opponent_rematch_offer"]);
+        let messages = handle(&mut app, 2, ClientMessage::RematchOffer);
+        assert_eq!(sent_to(&messages, 2), vec!["illegal_state"]);
+
+        let messages = handle(&mut app, 2, ClientMessage::RematchAccept);
+        assert_eq!(sent_to(&messages, 1), vec!["rematch_started"]);
+        assert_eq!(sent_to(&messages, 2), vec!["rematch_started"]);
+        resign(&mut app, 2, 1);
+
+        // the declined rematch ends the series
+        handle(&mut app, 2, ClientMessage::RematchOffer);
+        let messages = handle(&mut app, 1, ClientMessage::RematchDecline);
+        assert_eq!(sent_to(&messages, 1), vec!["rematch_decline_ok"]);
+        assert_eq!(sent_to(&messages, 2), vec!["opponent_rematch_decline"]);
+        let messages = handle(&mut app, 1, ClientMessage::RematchAccept);
+        assert_eq!(sent_to(&messages, 1), vec!["illegal_state"]);
+        assert!(app.series.is_empty());
+    }
+
+    #[test]
+    fn test_rematch_opponent_offline() {
+        let mut app = app(10);
+        let token = resume_token(&login(&mut app, 1, "alice"), 1, "login_ok");
+        login(&mut app, 2, "bob");
+        challenge(&mut app, (1, "alice"), (2, "bob"));
+        resign(&mut app, 1, 2);
+
+        // no rematch can be offered to the dropped opponent
+        app.handle_offline(&1);
+        let messages = handle(&mut app, 2, ClientMessage::RematchOffer);
+        assert_eq!(sent_to(&messages, 2), vec!["illegal_state"]);
+
+        // until the session is resumed
+        handle(&mut app, 3, ClientMessage::Resume(token));
+        let messages = handle(&mut app, 2, ClientMessage::RematchOffer);
+        assert_eq!(sent_to(&messages, 3), vec!["opponent_rematch_offer"]);
+
+        // the logged out opponent declines the offered rematch
+        let messages = handle(&mut app, 3, ClientMessage::LogOut);
+        assert!(sent_to(&messages, 2).contains(&"opponent_rematch_decline"));
+        let messages = handle(&mut app, 2, ClientMessage::RematchAccept);
+        assert_eq!(sent_to(&messages, 2), vec!["illegal_state"]);
+    }
+}
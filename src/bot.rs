@@ -0,0 +1,332 @@
+//! A server-side bot opponent.
+
+use crate::game::ShootResult;
+use crate::types::{Difficulty, Layout, Nickname, Orientation, Position, Rules};
+use rand::seq::SliceRandom;
+
+/// A knowledge of the bot about one cell of the opponents board.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Cell {
+    Unknown,
+    Miss,
+    Hit,
+    Sunk,
+}
+
+/// A bot playing the game instead of a player.
+///
+/// The bot only knows the results of its own shots,
+/// it never looks at the opponents layout.
+#[derive(Debug, Clone)]
+pub struct Bot {
+    difficulty: Difficulty,
+    rules: Rules,
+    /// Knowledge about the opponents board indexed by rows and columns.
+    cells: Vec<Vec<Cell>>,
+    /// Lengths of the opponents ships which are not sunk yet.
+    remaining: Vec<u8>,
+}
+
+impl Bot {
+    /// Create a new bot of the given difficulty playing a game with the given rules.
+    pub fn new(difficulty: Difficulty, rules: Rules) -> Self {
+        let fleet = rules.fleet();
+        let remaining = fleet
+            .ships()
+            .iter()
+            .map(|ship| fleet.ship_length(ship).unwrap())
+            .collect();
+        let cells = vec![
+            vec![Cell::Unknown; rules.board_size().cols() as usize];
+            rules.board_size().rows() as usize
+        ];
+
+        Bot {
+            difficulty,
+            rules,
+            cells,
+            remaining,
+        }
+    }
+
+    /// Get the nickname under which the bot plays.
+    pub fn nickname(&self) -> Nickname {
+        let nickname = match self.difficulty {
+            Difficulty::Easy => "EasyBot",
+            Difficulty::Medium => "MediumBot",
+            Difficulty::Hard => "HardBot",
+        };
+
+        Nickname::new(String::from(nickname)).unwrap()
+    }
+
+    /// Get the bot difficulty.
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Choose a layout of the bots fleet.
+    pub fn layout(&self) -> Option<Layout> {
+        Layout::random(&self.rules)
+    }
+
+    /// Choose a position of the next shot.
+    pub fn next_shot(&self) -> Position {
+        let candidates = match self.difficulty {
+            Difficulty::Easy => self.unknown(),
+            Difficulty::Medium => {
+                let targets = self.targets();
+
+                if targets.is_empty() {
+                    self.hunt()
+                } else {
+                    targets
+                }
+            }
+            Difficulty::Hard => self.most_probable(),
+        };
+
+        let &(row, col) = candidates
+            .choose(&mut rand::thread_rng())
+            .expect("no cell left to shoot at");
+
+        Position::new(row as u8, col as u8).unwrap()
+    }
+
+    /// Remember the result of the shot.
+    pub fn record(&mut self, position: Position, result: &ShootResult) {
+        let row = position.row() as usize;
+        let col = position.col() as usize;
+
+        match result {
+            ShootResult::Missed => self.cells[row][col] = Cell::Miss,
+            ShootResult::Hit => self.cells[row][col] = Cell::Hit,
+            ShootResult::Sunk(ship, placement) => {
+                let length = self.rules.fleet().ship_length(ship).unwrap();
+
+                if let Some(i) = self.remaining.iter().position(|l| *l == length) {
+                    self.remaining.remove(i);
+                }
+
+                let (inc_r, inc_c) = match placement.orientation() {
+                    Orientation::East => (0, 1),
+                    Orientation::North => (-1, 0),
+                    Orientation::West => (0, -1),
+                    Orientation::South => (1, 0),
+                };
+
+                let mut row = placement.position().row() as i32;
+                let mut col = placement.position().col() as i32;
+
+                for _ in 0..length {
+                    self.cells[row as usize][col as usize] = Cell::Sunk;
+
+                    // no other ship can be in the surroundings of a sunk ship
+                    for (off_r, off_c) in self.rules.spacing().neighbours() {
+                        if let Some((r, c)) = self.cell(row + off_r, col + off_c) {
+                            if self.cells[r][c] == Cell::Unknown {
+                                self.cells[r][c] = Cell::Miss;
+                            }
+                        }
+                    }
+
+                    row += inc_r;
+                    col += inc_c;
+                }
+            }
+        }
+    }
+
+    /// Get the board indices of the cell if it is on the board.
+    fn cell(&self, row: i32, col: i32) -> Option<(usize, usize)> {
+        let rows = self.rules.board_size().rows() as i32;
+        let cols = self.rules.board_size().cols() as i32;
+
+        if row < 0 || row >= rows || col < 0 || col >= cols {
+            None
+        } else {
+            Some((row as usize, col as usize))
+        }
+    }
+
+    /// Get all cells which were not shot at yet.
+    fn unknown(&self) -> Vec<(usize, usize)> {
+        let mut unknown = Vec::new();
+
+        for (row, cells) in self.cells.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                if *cell == Cell::Unknown {
+                    unknown.push((row, col));
+                }
+            }
+        }
+
+        unknown
+    }
+
+    /// Get unknown cells in a checkerboard pattern, which is enough to find
+    /// all ships not shorter than two cells, or all unknown cells if there is no such cell.
+    fn hunt(&self) -> Vec<(usize, usize)> {
+        let unknown = self.unknown();
+
+        if self.remaining.iter().any(|length| *length < 2) {
+            return unknown;
+        }
+
+        let checkerboard = unknown
+            .iter()
+            .cloned()
+            .filter(|(row, col)| (row + col) % 2 == 0)
+            .collect::<Vec<_>>();
+
+        if checkerboard.is_empty() {
+            unknown
+        } else {
+            checkerboard
+        }
+    }
+
+    /// Get unknown cells next to hits of not yet sunk ships.
+    /// Cells in line with two adjacent hits are preferred.
+    fn targets(&self) -> Vec<(usize, usize)> {
+        let mut targets = Vec::new();
+        let mut best = 0;
+
+        for (row, cells) in self.cells.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                if *cell != Cell::Hit {
+                    continue;
+                }
+
+                for &(inc_r, inc_c) in &[(-1, 0), (0, 1), (1, 0), (0, -1)] {
+                    let target = match self.cell(row as i32 + inc_r, col as i32 + inc_c) {
+                        Some((r, c)) if self.cells[r][c] == Cell::Unknown => (r, c),
+                        _ => continue,
+                    };
+
+                    let in_line = match self.cell(row as i32 - inc_r, col as i32 - inc_c) {
+                        Some((r, c)) => self.cells[r][c] == Cell::Hit,
+                        None => false,
+                    };
+                    let score = if in_line { 2 } else { 1 };
+
+                    if score > best {
+                        best = score;
+                        targets.clear();
+                    }
+
+                    if score == best {
+                        targets.push(target);
+                    }
+                }
+            }
+        }
+
+        targets
+    }
+
+    /// Get unknown cells covered by the most possible placements of the remaining ships.
+    /// Placements covering hits of not yet sunk ships are weighted much more.
+    fn most_probable(&self) -> Vec<(usize, usize)> {
+        let rows = self.cells.len();
+        let cols = self.cells[0].len();
+        let mut density = vec![vec![0u32; cols]; rows];
+
+        for &length in &self.remaining {
+            for row in 0..rows as i32 {
+                for col in 0..cols as i32 {
+                    for &(inc_r, inc_c) in &[(0, 1), (1, 0)] {
+                        let mut covered = Vec::with_capacity(length as usize);
+                        let mut hits = 0;
+
+                        for i in 0..length as i32 {
+                            match self.cell(row + i * inc_r, col + i * inc_c) {
+                                Some((r, c)) => match self.cells[r][c] {
+                                    Cell::Unknown => covered.push((r, c)),
+                                    Cell::Hit => hits += 1,
+                                    _ => break,
+                                },
+                                None => break,
+                            }
+                        }
+
+                        if covered.len() + hits != length as usize {
+                            // the ship can't be placed here
+                            continue;
+                        }
+
+                        let weight = 1 + 20 * hits as u32;
+
+                        for (r, c) in covered {
+                            density[r][c] += weight;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut probable = Vec::new();
+        let mut best = 0;
+
+        for (row, cells) in density.iter().enumerate() {
+            for (col, weight) in cells.iter().enumerate() {
+                if *weight > best {
+                    best = *weight;
+                    probable.clear();
+                }
+
+                if *weight == best && best > 0 {
+                    probable.push((row, col));
+                }
+            }
+        }
+
+        if probable.is_empty() {
+            self.unknown()
+        } else {
+            probable
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bot::Bot;
+    use crate::game::Game;
+    use crate::types::{Difficulty, GameMode, Rules};
+
+    /// Let two bots play a game to the end.
+    fn play(first: Difficulty, second: Difficulty) {
+        let rules = Rules::default();
+        let mut bots = [
+            Bot::new(first, rules.clone()),
+            Bot::new(second, rules.clone()),
+        ];
+        let mut shot = [Vec::new(), Vec::new()];
+
+        let mut game = Game::new(0, 1, rules.clone(), GameMode::Casual);
+        game.set_layout(0, bots[0].layout().unwrap()).unwrap();
+        game.set_layout(1, bots[1].layout().unwrap()).unwrap();
+
+        while game.winner().is_none() {
+            let player = game.on_turn();
+            let position = bots[player].next_shot();
+
+            assert!(
+                !shot[player].contains(&position),
+                "the bot shoots at the same cell twice"
+            );
+            shot[player].push(position);
+
+            let result = game.shoot(player, position).unwrap();
+            bots[player].record(position, &result);
+        }
+    }
+
+    #[test]
+    fn test_bots_play() {
+        play(Difficulty::Easy, Difficulty::Medium);
+        play(Difficulty::Medium, Difficulty::Hard);
+        play(Difficulty::Hard, Difficulty::Easy);
+    }
+}
@@ -0,0 +1,144 @@
+//! Direct challenges between players.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Pending challenges, every player challenges at most one other player at a time.
+#[derive(Debug, Default)]
+pub struct Challenges {
+    /// Challenged-player-id-and-time-of-challenge indexed by challenger ids.
+    challenges: HashMap<usize, (usize, Instant)>,
+}
+
+impl Challenges {
+    /// Time after which an unanswered challenge expires.
+    pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Check whether the player has challenged another player.
+    pub fn contains(&self, challenger: &usize) -> bool {
+        self.challenges.contains_key(challenger)
+    }
+
+    /// Check whether the player is challenged by the challenger.
+    pub fn challenged_by(&self, challenged: &usize, challenger: &usize) -> bool {
+        match self.challenges.get(challenger) {
+            Some((id, _)) => id == challenged,
+            None => false,
+        }
+    }
+
+    /// Record the challenge of the challenger to the challenged player made at the given time.
+    pub fn add(&mut self, challenger: usize, challenged: usize, now: Instant) {
+        self.challenges.insert(challenger, (challenged, now));
+    }
+
+    /// Remove the challenge of the challenger.
+    /// Returns the challenged player.
+    pub fn remove(&mut self, challenger: &usize) -> Option<usize> {
+        self.challenges
+            .remove(challenger)
+            .map(|(challenged, _)| challenged)
+    }
+
+    /// Remove the challenge of the player and all challenges to him.
+    /// Returns the other players of the removed challenges.
+    pub fn remove_player(&mut self, player: &usize) -> Vec<usize> {
+        let mut others = self
+            .challenges
+            .iter()
+            .filter_map(|(challenger, (challenged, _))| {
+                if challenged == player {
+                    Some(*challenger)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for challenger in &others {
+            self.challenges.remove(challenger);
+        }
+
+        if let Some(challenged) = self.remove(player) {
+            others.push(challenged);
+        }
+
+        others
+    }
+
+    /// Remove the challenges which were not answered in time.
+    /// Returns the challengers and the challenged players of the removed challenges.
+    pub fn expire(&mut self, now: Instant) -> Vec<(usize, usize)> {
+        let expired = self
+            .challenges
+            .iter()
+            .filter_map(|(challenger, (challenged, challenged_at))| {
+                if now.duration_since(*challenged_at) >= Self::TIMEOUT {
+                    Some((*challenger, *challenged))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for (challenger, _) in &expired {
+            self.challenges.remove(challenger);
+        }
+
+        expired
+    }
+
+    /// Remove all challenges.
+    pub fn clear(&mut self) {
+        self.challenges.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::challenges::Challenges;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_challenges() {
+        let now = Instant::now();
+
+        let mut challenges = Challenges::default();
+        challenges.add(1, 2, now);
+        challenges.add(3, 2, now);
+        challenges.add(2, 4, now);
+
+        assert!(challenges.contains(&1));
+        assert!(!challenges.contains(&4));
+        assert!(challenges.challenged_by(&2, &1));
+        assert!(!challenges.challenged_by(&1, &2));
+
+        assert_eq!(challenges.remove(&3), Some(2));
+        assert_eq!(challenges.remove(&3), None);
+
+        let mut others = challenges.remove_player(&2);
+        others.sort_unstable();
+        assert_eq!(others, vec![1, 4]);
+        assert!(!challenges.contains(&1));
+        assert!(!challenges.contains(&2));
+    }
+
+    #[test]
+    fn test_expire() {
+        let now = Instant::now();
+
+        let mut challenges = Challenges::default();
+        challenges.add(1, 2, now);
+        challenges.add(3, 4, now + Duration::from_secs(10));
+
+        assert!(challenges.expire(now + Duration::from_secs(29)).is_empty());
+        assert_eq!(challenges.expire(now + Challenges::TIMEOUT), vec![(1, 2)]);
+        assert!(!challenges.contains(&1));
+        assert!(challenges.contains(&3));
+
+        assert_eq!(
+            challenges.expire(now + Duration::from_secs(40)),
+            vec![(3, 4)]
+        );
+    }
+}
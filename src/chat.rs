@@ -0,0 +1,144 @@
+//! Filtering and rate limiting of chat messages.
+
+use crate::store;
+use crate::types::ChatMessage;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A filter applied to every chat message before it is delivered.
+pub trait ChatFilter {
+    /// Filter the message sent by the player.
+    /// Returns the message to be delivered, possibly altered, or None if it must not be delivered.
+    fn filter(&self, sender: &str, message: ChatMessage) -> Option<ChatMessage>;
+}
+
+/// A chat filter which masks blocked words with asterisks.
+#[derive(Debug, Default)]
+pub struct BlockedWords {
+    /// Lowercase blocked words.
+    words: Vec<String>,
+}
+
+impl BlockedWords {
+    /// Create a filter of the blocked words.
+    pub fn new(words: Vec<String>) -> Self {
+        BlockedWords {
+            words: words.iter().map(|word| word.to_lowercase()).collect(),
+        }
+    }
+
+    /// Load the blocked words from the file with one word per line,
+    /// nothing is blocked if the file does not exist.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let words = store::read_lines(path)?
+            .iter()
+            .map(|line| String::from(line.trim()))
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        Ok(BlockedWords::new(words))
+    }
+}
+
+impl ChatFilter for BlockedWords {
+    fn filter(&self, _sender: &str, message: ChatMessage) -> Option<ChatMessage> {
+        if self.words.is_empty() {
+            return Some(message);
+        }
+
+        let filtered = message
+            .get()
+            .split(' ')
+            .map(|word| {
+                let bare = word
+                    .trim_matches(|c: char| !c.is_alphanumeric())
+                    .to_lowercase();
+
+                if self.words.contains(&bare) {
+                    "*".repeat(word.chars().count())
+                } else {
+                    String::from(word)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        ChatMessage::new(filtered).ok()
+    }
+}
+
+/// Limits the number of chat messages each player can send in a time window.
+#[derive(Debug, Default)]
+pub struct ChatLimiter {
+    /// Times of the recently sent messages indexed by player ids.
+    sent: HashMap<usize, VecDeque<Instant>>,
+}
+
+impl ChatLimiter {
+    /// Maximal number of messages sent in the window.
+    pub const MAX_MESSAGES: usize = 5;
+    /// The time window of the limit.
+    pub const WINDOW: Duration = Duration::from_secs(10);
+
+    /// Check whether the player can send a message now, the message is counted if so.
+    pub fn allow(&mut self, player: usize, now: Instant) -> bool {
+        let sent = self.sent.entry(player).or_default();
+
+        while let Some(oldest) = sent.front() {
+            if now.duration_since(*oldest) >= Self::WINDOW {
+                sent.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if sent.len() >= Self::MAX_MESSAGES {
+            false
+        } else {
+            sent.push_back(now);
+            true
+        }
+    }
+
+    /// Forget the messages sent by the player.
+    pub fn remove(&mut self, player: &usize) {
+        self.sent.remove(player);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chat::{BlockedWords, ChatFilter, ChatLimiter};
+    use crate::types::ChatMessage;
+    use std::time::Instant;
+
+    #[test]
+    fn test_blocked_words() {
+        let filter = BlockedWords::new(vec![String::from("Darn")]);
+        let message = ChatMessage::new(String::from("darn, missed again; DARN")).unwrap();
+
+        assert_eq!(
+            filter.filter("alice", message).unwrap().get(),
+            "***** missed again; ****"
+        );
+    }
+
+    #[test]
+    fn test_chat_limiter() {
+        let mut limiter = ChatLimiter::default();
+        let now = Instant::now();
+
+        for _ in 0..ChatLimiter::MAX_MESSAGES {
+            assert!(limiter.allow(1, now));
+        }
+        assert!(!limiter.allow(1, now));
+        assert!(limiter.allow(2, now));
+
+        assert!(limiter.allow(1, now + ChatLimiter::WINDOW));
+
+        limiter.remove(&1);
+        assert!(!limiter.sent.contains_key(&1));
+    }
+}
@@ -1,14 +1,21 @@
 use crate::types::{
-    Hits, Layout, Orientation, Placement, Position, ShipKind, ShipsPlacements, Who,
+    BoardSize, BoardView, GameMode, Hits, Layout, LayoutError, Orientation, Placement, Position,
+    Rules, ShipId, ShipsPlacements, ShotsPerTurn, TurnRule, Who,
 };
+use rand::seq::SliceRandom;
 use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant, SystemTime};
 
 /// An error indicating that player did something illegal with the game.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum GameError {
     AlreadyHasLayout,
-    InvalidLayout,
+    InvalidLayout(LayoutError),
     NotOnTurn,
+    OutOfBoard,
+    InvalidSalvo,
 }
 
 /// A state of the one board cell.
@@ -17,33 +24,93 @@ pub enum BoardCell {
     Empty,
     Miss,
     Hit,
-    Ship(ShipKind),
+    Ship(ShipId),
 }
 
-/// Ship of particular kind and health.
+/// A board of cells of the particular size.
+#[derive(Debug, Clone)]
+pub struct Board {
+    size: BoardSize,
+    cells: Vec<BoardCell>,
+}
+
+impl Board {
+    /// Create a new board of the given size with all cells empty.
+    pub fn new(size: BoardSize) -> Self {
+        Board {
+            size,
+            cells: vec![BoardCell::Empty; size.rows() as usize * size.cols() as usize],
+        }
+    }
+
+    /// Get the size of the board.
+    pub fn size(&self) -> BoardSize {
+        self.size
+    }
+
+    /// Get the state of the cell at the position.
+    pub fn get(&self, position: &Position) -> BoardCell {
+        self.cells[self.index(position)]
+    }
+
+    /// Set the state of the cell at the position.
+    pub fn set(&mut self, position: &Position, cell: BoardCell) {
+        let index = self.index(position);
+        self.cells[index] = cell;
+    }
+
+    /// Get all positions of the board with their cells, row by row.
+    pub fn cells(&self) -> impl Iterator<Item = (Position, BoardCell)> + '_ {
+        let cols = self.size.cols();
+
+        self.cells.iter().enumerate().map(move |(i, cell)| {
+            let row = (i / cols as usize) as u8;
+            let col = (i % cols as usize) as u8;
+            (Position::new(row, col).unwrap(), *cell)
+        })
+    }
+
+    /// Get the index of the position in the cells vector.
+    fn index(&self, position: &Position) -> usize {
+        if !self.size.contains(position) {
+            panic!("position {} is not on the board {}", position, self.size);
+        }
+
+        position.row() as usize * self.size.cols() as usize + position.col() as usize
+    }
+}
+
+/// Ship with particular id and health.
 #[derive(Debug)]
 pub struct Ship {
-    kind: ShipKind,
+    id: ShipId,
     health: u8,
 }
 
 /// The result of shooting.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ShootResult {
     Missed,
     Hit,
-    Sunk(ShipKind, Placement),
+    Sunk(ShipId, Placement),
 }
 
-impl Ship {
-    /// Create a new ship of the given kind.
-    /// Sets the ships health to the correct value according to the kind.
-    pub fn new(kind: ShipKind) -> Self {
-        Ship {
-            kind,
-            health: kind.cells(),
+impl Display for ShootResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ShootResult::Missed => write!(f, "missed"),
+            ShootResult::Hit => write!(f, "hit"),
+            ShootResult::Sunk(ship, placement) => write!(f, "sunk {} {}", ship, placement),
         }
     }
+}
+
+impl Ship {
+    /// Create a new ship with the given id.
+    /// Sets the ships health to the length of the ship.
+    pub fn new(id: ShipId, length: u8) -> Self {
+        Ship { id, health: length }
+    }
 
     /// Decrease the ships health by one if not already zero.
     pub fn hit(&mut self) {
@@ -52,9 +119,9 @@ impl Ship {
         }
     }
 
-    /// Get the kind of the ship
-    pub fn kind(&self) -> ShipKind {
-        self.kind
+    /// Get the id of the ship
+    pub fn id(&self) -> ShipId {
+        self.id
     }
 
     /// Check whether is the ship sunk (health == 0).
@@ -63,35 +130,105 @@ impl Ship {
     }
 }
 
+/// An action of a player recorded in the history of a game.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GameAction {
+    Layout(Layout),
+    Shot(Position, ShootResult),
+    Pass,
+}
+
 /// A game of two players.
 pub struct Game {
     first_player: usize,
     second_player: usize,
     first_layout: Option<Layout>,
     second_layout: Option<Layout>,
-    first_board: [[BoardCell; 10]; 10],
-    second_board: [[BoardCell; 10]; 10],
-    first_ships: HashMap<ShipKind, Ship>,
-    second_ships: HashMap<ShipKind, Ship>,
+    rules: Rules,
+    mode: GameMode,
+    first_board: Board,
+    second_board: Board,
+    first_ships: HashMap<ShipId, Ship>,
+    second_ships: HashMap<ShipId, Ship>,
     on_turn: usize,
+    shots_left: u8,
+    turn_hit: bool,
+    turn: usize,
+    turn_started: Instant,
     winner: Option<usize>,
+    created: Instant,
+    created_at: SystemTime,
+    /// Actions of the players with their time since the creation of the game.
+    history: Vec<(Duration, usize, GameAction)>,
 }
 
 impl Game {
-    /// Create a new game with the two players.
-    pub fn new(first_player: usize, second_player: usize) -> Self {
+    /// Create a new game with the two players played by the given rules.
+    pub fn new(first_player: usize, second_player: usize, rules: Rules, mode: GameMode) -> Self {
+        let shots_left = match rules.shots_per_turn() {
+            ShotsPerTurn::Fixed(shots) => shots,
+            ShotsPerTurn::SurvivingShips => rules.fleet().ships().len() as u8,
+        };
+
         Game {
             first_player,
             second_player,
             first_layout: None,
             second_layout: None,
-            first_board: [[BoardCell::Empty; 10]; 10],
-            second_board: [[BoardCell::Empty; 10]; 10],
+            first_board: Board::new(rules.board_size()),
+            second_board: Board::new(rules.board_size()),
+            rules,
+            mode,
             first_ships: HashMap::new(),
             second_ships: HashMap::new(),
             on_turn: first_player,
+            shots_left,
+            turn_hit: false,
+            turn: 0,
+            turn_started: Instant::now(),
             winner: None,
+            created: Instant::now(),
+            created_at: SystemTime::now(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Create a game restored from a journal, created at the given time.
+    /// The actions of the players are restored by [replay](Game::replay).
+    pub fn restore(
+        first_player: usize,
+        second_player: usize,
+        rules: Rules,
+        mode: GameMode,
+        created_at: SystemTime,
+    ) -> Self {
+        let mut game = Game::new(first_player, second_player, rules, mode);
+        let elapsed = created_at.elapsed().unwrap_or_default();
+
+        game.created = Instant::now().checked_sub(elapsed).unwrap_or(game.created);
+        game.created_at = created_at;
+
+        game
+    }
+
+    /// Take the action of the player again, as it was taken at the time since the creation of the game.
+    pub fn replay(
+        &mut self,
+        time: Duration,
+        player: usize,
+        action: GameAction,
+    ) -> Result<(), GameError> {
+        match action {
+            GameAction::Layout(layout) => self.set_layout(player, layout).map(|_| ())?,
+            GameAction::Shot(position, _) => self.shoot(player, position).map(|_| ())?,
+            GameAction::Pass => self.pass(player)?,
+        }
+
+        if let Some((action_time, _, _)) = self.history.last_mut() {
+            *action_time = time;
         }
+
+        Ok(())
     }
 
     /// Set the ships layout for the player.
@@ -114,25 +251,28 @@ impl Game {
             return Err(GameError::AlreadyHasLayout);
         }
 
-        if !layout.is_valid() {
-            return Err(GameError::InvalidLayout);
+        if let Err(error) = layout.validate(&self.rules) {
+            return Err(GameError::InvalidLayout(error));
         }
 
+        self.history.push((
+            self.created.elapsed(),
+            player,
+            GameAction::Layout(layout.clone()),
+        ));
+
         *l = Some(layout);
 
         // prepare fleet
-        s.insert(
-            ShipKind::AircraftCarrier,
-            Ship::new(ShipKind::AircraftCarrier),
-        );
-        s.insert(ShipKind::Battleship, Ship::new(ShipKind::Battleship));
-        s.insert(ShipKind::Cruiser, Ship::new(ShipKind::Cruiser));
-        s.insert(ShipKind::Destroyer, Ship::new(ShipKind::Destroyer));
-        s.insert(ShipKind::PatrolBoat, Ship::new(ShipKind::PatrolBoat));
+        for class in self.rules.fleet().classes() {
+            for id in class.ships() {
+                s.insert(id, Ship::new(id, class.length()));
+            }
+        }
 
         // mark ships on board
-        for (kind, placement) in l.as_ref().unwrap().placements().placements() {
-            let cells = kind.cells();
+        for (id, placement) in l.as_ref().unwrap().placements() {
+            let cells = self.rules.fleet().ship_length(id).unwrap();
             let mut row: i32 = placement.position().row() as i32;
             let mut col: i32 = placement.position().col() as i32;
 
@@ -145,13 +285,21 @@ impl Game {
 
             // mark ships cells
             for _ in 0..cells {
-                b[row as usize][col as usize] = BoardCell::Ship(*kind);
+                b.set(
+                    &Position::new(row as u8, col as u8).unwrap(),
+                    BoardCell::Ship(*id),
+                );
 
                 row += inc_r;
                 col += inc_c;
             }
         }
 
+        if self.playing() {
+            // the clock of the first turn starts when the game starts
+            self.turn_started = Instant::now();
+        }
+
         Ok(self.playing())
     }
 
@@ -160,11 +308,84 @@ impl Game {
         self.first_layout.is_some() && self.second_layout.is_some()
     }
 
+    /// Get the rules of the game.
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Get whether the game is rated or casual.
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Get the player who started the game.
+    pub fn starting_player(&self) -> usize {
+        self.first_player
+    }
+
+    /// Get the player on turn.
+    pub fn on_turn(&self) -> usize {
+        self.on_turn
+    }
+
+    /// Get the number of the current turn.
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    /// Get the number of turns the player has had including the current one.
+    pub fn player_turns(&self, player: usize) -> usize {
+        if player == self.first_player {
+            self.turn / 2 + 1
+        } else {
+            self.turn.div_ceil(2)
+        }
+    }
+
+    /// Get the time left to the player on turn if the game is played with a shot clock.
+    pub fn remaining_time(&self) -> Option<Duration> {
+        match self.rules.shot_clock() {
+            Some(shot_clock) if self.playing() && self.winner.is_none() => Some(
+                shot_clock
+                    .limit()
+                    .checked_sub(self.turn_started.elapsed())
+                    .unwrap_or_default(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Get the number of shots left in the current turn to the player on turn.
+    pub fn shots_left(&self) -> u8 {
+        self.shots_left
+    }
+
     /// Get the game winner if the game has ended.
     pub fn winner(&self) -> Option<usize> {
         self.winner
     }
 
+    /// Get the time when the game was created.
+    pub fn created_at(&self) -> SystemTime {
+        self.created_at
+    }
+
+    /// Get the time elapsed since the game was created.
+    pub fn elapsed(&self) -> Duration {
+        self.created.elapsed()
+    }
+
+    /// Get the actions of the players in the order they were taken,
+    /// with their time since the creation of the game.
+    pub fn history(&self) -> &[(Duration, usize, GameAction)] {
+        &self.history
+    }
+
+    /// Get the first and the second player of the game.
+    pub fn players(&self) -> (usize, usize) {
+        (self.first_player, self.second_player)
+    }
+
     /// Get the other player in the game.
     pub fn other_player(&self, player: &usize) -> usize {
         match player {
@@ -200,52 +421,35 @@ impl Game {
             return Err(GameError::NotOnTurn);
         }
 
+        if !opponent_board.size().contains(&position) {
+            return Err(GameError::OutOfBoard);
+        }
+
         // cell is already hit
-        if let BoardCell::Hit = opponent_board[position.row() as usize][position.col() as usize] {
+        if let BoardCell::Hit = opponent_board.get(&position) {
             return Ok(ShootResult::Hit);
         }
 
         let mut result = ShootResult::Missed;
-        self.on_turn = opponent;
 
         // check if any ship is hit
-        'outer: for r in 0..10 {
-            for c in 0..10 {
-                if let BoardCell::Ship(kind) = opponent_board[r as usize][c as usize] {
-                    if position.row() == r && position.col() == c {
-                        // ship is hit
-
-                        self.on_turn = player;
-
-                        let ship = opponent_fleet.get_mut(&kind).unwrap();
-                        ship.hit();
-
-                        if ship.is_sunk() {
-                            result = ShootResult::Sunk(
-                                kind,
-                                opponent_layout
-                                    .placements()
-                                    .placements()
-                                    .get(&kind)
-                                    .unwrap()
-                                    .clone(),
-                            )
-                        } else {
-                            result = ShootResult::Hit;
-                        }
-
-                        break 'outer;
-                    }
-                }
+        if let BoardCell::Ship(id) = opponent_board.get(&position) {
+            // ship is hit
+
+            let ship = opponent_fleet.get_mut(&id).unwrap();
+            ship.hit();
+
+            if ship.is_sunk() {
+                result = ShootResult::Sunk(id, opponent_layout.placement(&id).unwrap())
+            } else {
+                result = ShootResult::Hit;
             }
         }
 
         match result {
-            ShootResult::Missed => {
-                opponent_board[position.row() as usize][position.col() as usize] = BoardCell::Miss
-            }
+            ShootResult::Missed => opponent_board.set(&position, BoardCell::Miss),
             ShootResult::Hit | ShootResult::Sunk(_, _) => {
-                opponent_board[position.row() as usize][position.col() as usize] = BoardCell::Hit
+                opponent_board.set(&position, BoardCell::Hit)
             }
         }
 
@@ -257,9 +461,164 @@ impl Game {
             }
         }
 
+        if result != ShootResult::Missed {
+            self.turn_hit = true;
+        }
+
+        self.history.push((
+            self.created.elapsed(),
+            player,
+            GameAction::Shot(position, result),
+        ));
+
+        self.shots_left -= 1;
+
+        if self.shots_left == 0 {
+            // no shots left - the turn ends
+            if self.turn_hit && self.rules.turn_rule() == TurnRule::HitKeepsTurn {
+                self.start_turn(player);
+            } else {
+                self.start_turn(opponent);
+            }
+        }
+
         Ok(result)
     }
 
+    /// Pass the rest of the turn to the opponent.
+    pub fn pass(&mut self, player: usize) -> Result<(), GameError> {
+        if self.winner.is_some() {
+            panic!("game is over");
+        }
+
+        if player != self.on_turn {
+            return Err(GameError::NotOnTurn);
+        }
+
+        self.history
+            .push((self.created.elapsed(), player, GameAction::Pass));
+        self.start_turn(self.other_player(&player));
+
+        Ok(())
+    }
+
+    /// Give up the game, the opponent wins.
+    pub fn forfeit(&mut self, player: usize) {
+        if self.winner.is_some() {
+            panic!("game is over");
+        }
+
+        self.winner = Some(self.other_player(&player));
+    }
+
+    /// Check whether the player already shot at the position on the opponents board.
+    pub fn already_shot(&self, player: usize, position: &Position) -> bool {
+        let opponent_board = match player {
+            id if id == self.first_player => &self.second_board,
+            id if id == self.second_player => &self.first_board,
+            _ => panic!("player {} is not in this game", player),
+        };
+
+        opponent_board.size().contains(position)
+            && match opponent_board.get(position) {
+                BoardCell::Miss | BoardCell::Hit => true,
+                BoardCell::Empty | BoardCell::Ship(_) => false,
+            }
+    }
+
+    /// Choose a random position on the opponents board which was not shot at yet.
+    pub fn random_target(&self, player: usize) -> Position {
+        let opponent_board = match player {
+            id if id == self.first_player => &self.second_board,
+            id if id == self.second_player => &self.first_board,
+            _ => panic!("player {} is not in this game", player),
+        };
+
+        let targets = opponent_board
+            .cells()
+            .filter_map(|(position, cell)| match cell {
+                BoardCell::Empty | BoardCell::Ship(_) => Some(position),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        *targets
+            .choose(&mut rand::thread_rng())
+            .expect("no position left to shoot at")
+    }
+
+    /// Start a new turn of the player.
+    fn start_turn(&mut self, player: usize) {
+        self.on_turn = player;
+        self.shots_left = self.shots_of(player);
+        self.turn_hit = false;
+        self.turn += 1;
+        self.turn_started = Instant::now();
+    }
+
+    /// Fire a salvo of shots at the positions and get the results of the fired shots.
+    /// The salvo must not have more shots than the player has left in the turn
+    /// and must not shoot at any position twice or at a position which was already shot at.
+    /// Shooting stops when the game is over.
+    pub fn shoot_salvo(
+        &mut self,
+        player: usize,
+        positions: &[Position],
+    ) -> Result<Vec<(Position, ShootResult)>, GameError> {
+        if self.winner.is_some() {
+            panic!("game is over");
+        }
+
+        if player != self.on_turn {
+            return Err(GameError::NotOnTurn);
+        }
+
+        if positions.is_empty() || positions.len() > self.shots_left as usize {
+            return Err(GameError::InvalidSalvo);
+        }
+
+        if !positions
+            .iter()
+            .all(|p| self.rules.board_size().contains(p))
+        {
+            return Err(GameError::OutOfBoard);
+        }
+
+        for (i, position) in positions.iter().enumerate() {
+            if self.already_shot(player, position) || positions[..i].contains(position) {
+                return Err(GameError::InvalidSalvo);
+            }
+        }
+
+        let mut results = Vec::with_capacity(positions.len());
+
+        for position in positions {
+            if self.winner.is_some() || self.on_turn != player {
+                break;
+            }
+
+            results.push((*position, self.shoot(player, *position)?));
+        }
+
+        Ok(results)
+    }
+
+    /// Get the number of shots the player fires in his next turn.
+    fn shots_of(&self, player: usize) -> u8 {
+        match self.rules.shots_per_turn() {
+            ShotsPerTurn::Fixed(shots) => shots,
+            ShotsPerTurn::SurvivingShips => {
+                let ships = match player {
+                    id if id == self.first_player => &self.first_ships,
+                    id if id == self.second_player => &self.second_ships,
+                    _ => panic!("player {} is not in this game", player),
+                };
+
+                ships.values().filter(|ship| !ship.is_sunk()).count() as u8
+            }
+        }
+    }
+
     /// Get the state of game for a concrete player.
     pub fn state(&self, player: usize) -> (Who, Hits, Hits, Layout, Hits, Hits, ShipsPlacements) {
         let (board, layout, opponent_board, opponent_layout, opponent_ships) = match player {
@@ -303,49 +662,433 @@ impl Game {
         )
     }
 
-    /// Serialize all board cells which are hit into the Hits structure.
-    pub fn serialize_hits(board: &[[BoardCell; 10]; 10]) -> Hits {
-        let mut hits = Vec::new();
-
-        for r in 0..10 {
-            for c in 0..10 {
-                if let BoardCell::Hit = board[r as usize][c as usize] {
-                    hits.push(Position::new(r, c).unwrap());
-                }
+    /// Get the view of the board of a concrete player which can be shown to anyone,
+    /// the positions of ships which are not sunk yet are never revealed.
+    pub fn board_view(&self, player: usize) -> BoardView {
+        let (board, layout, ships) = match player {
+            id if id == self.first_player => {
+                (&self.first_board, &self.first_layout, &self.first_ships)
             }
-        }
+            id if id == self.second_player => {
+                (&self.second_board, &self.second_layout, &self.second_ships)
+            }
+            _ => panic!("player {} is not in this game", player),
+        };
+
+        let sunk_ships = match layout {
+            Some(layout) => Self::serialize_sunk(layout, ships),
+            None => ShipsPlacements::new(HashMap::new()),
+        };
+
+        BoardView::new(
+            Self::serialize_hits(board),
+            Self::serialize_misses(board),
+            sunk_ships,
+        )
+    }
+
+    /// Serialize all board cells which are hit into the Hits structure.
+    pub fn serialize_hits(board: &Board) -> Hits {
+        let hits = board
+            .cells()
+            .filter(|(_, cell)| *cell == BoardCell::Hit)
+            .map(|(position, _)| position)
+            .collect();
 
         Hits::new(hits)
     }
 
     /// Serialize all board cells which are missed into the Hits structure.
-    pub fn serialize_misses(board: &[[BoardCell; 10]; 10]) -> Hits {
-        let mut hits = Vec::new();
-
-        for r in 0..10 {
-            for c in 0..10 {
-                if let BoardCell::Miss = board[r as usize][c as usize] {
-                    hits.push(Position::new(r, c).unwrap());
-                }
-            }
-        }
+    pub fn serialize_misses(board: &Board) -> Hits {
+        let misses = board
+            .cells()
+            .filter(|(_, cell)| *cell == BoardCell::Miss)
+            .map(|(position, _)| position)
+            .collect();
 
-        Hits::new(hits)
+        Hits::new(misses)
     }
 
     /// Serialize all ships which are sunk into a ShipsPlacements structure.
-    pub fn serialize_sunk(layout: &Layout, ships: &HashMap<ShipKind, Ship>) -> ShipsPlacements {
+    pub fn serialize_sunk(layout: &Layout, ships: &HashMap<ShipId, Ship>) -> ShipsPlacements {
         let mut placements = HashMap::new();
 
-        for (kind, ship) in ships {
+        for (id, ship) in ships {
             if ship.is_sunk() {
-                placements.insert(
-                    *kind,
-                    layout.placements().placements().get(&kind).unwrap().clone(),
-                );
+                placements.insert(*id, layout.placement(id).unwrap());
             }
         }
 
         ShipsPlacements::new(placements)
     }
 }
+
+/// A series of games played by the same two players.
+/// Keeps the score and swaps the starting player in every rematch.
+#[derive(Debug, Clone)]
+pub struct Series {
+    first_player: usize,
+    second_player: usize,
+    first_wins: u16,
+    second_wins: u16,
+    last_starting: usize,
+    offered_by: Option<usize>,
+    rules: Rules,
+    mode: GameMode,
+}
+
+impl Series {
+    /// Create a new series of the two players playing games of the given rules and mode
+    /// with no games played.
+    pub fn new(first_player: usize, second_player: usize, rules: Rules, mode: GameMode) -> Self {
+        Series {
+            first_player,
+            second_player,
+            first_wins: 0,
+            second_wins: 0,
+            last_starting: first_player,
+            offered_by: None,
+            rules,
+            mode,
+        }
+    }
+
+    /// Record the result of the finished game of the series.
+    pub fn record(&mut self, game: &Game) {
+        match game.winner() {
+            Some(id) if id == self.first_player => self.first_wins += 1,
+            Some(id) if id == self.second_player => self.second_wins += 1,
+            _ => panic!("game is not finished or is not in this series"),
+        }
+
+        self.last_starting = game.starting_player();
+        self.offered_by = None;
+    }
+
+    /// Get the other player in the series.
+    pub fn other_player(&self, player: &usize) -> usize {
+        match player {
+            id if *id == self.first_player => self.second_player,
+            id if *id == self.second_player => self.first_player,
+            _ => panic!("player {} is not in this series", player),
+        }
+    }
+
+    /// Get the number of wins of the player and of his opponent.
+    pub fn score(&self, player: &usize) -> (u16, u16) {
+        match player {
+            id if *id == self.first_player => (self.first_wins, self.second_wins),
+            id if *id == self.second_player => (self.second_wins, self.first_wins),
+            _ => panic!("player {} is not in this series", player),
+        }
+    }
+
+    /// Get the player who offered a rematch if there is a pending offer.
+    pub fn offered_by(&self) -> Option<usize> {
+        self.offered_by
+    }
+
+    /// Offer a rematch to the other player.
+    pub fn offer(&mut self, player: usize) {
+        self.other_player(&player);
+        self.offered_by = Some(player);
+    }
+
+    /// Start a rematch, the player who did not start the last game starts.
+    pub fn rematch(&mut self) -> Game {
+        self.offered_by = None;
+
+        let starting = self.other_player(&self.last_starting);
+        Game::new(starting, self.last_starting, self.rules.clone(), self.mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Game, GameAction, GameError, Series, ShootResult};
+    use crate::types::{
+        BoardSize, Fleet, GameMode, Layout, Orientation, Placement, Position, Rules, ShotsPerTurn,
+        Spacing, TurnRule,
+    };
+
+    /// Create a layout of the fleet with ships placed on even rows.
+    fn layout(fleet: &Fleet) -> Layout {
+        let mut placements = Vec::new();
+
+        for (i, ship) in fleet.ships().into_iter().enumerate() {
+            let position = Position::new(i as u8 * 2, 0).unwrap();
+            placements.push((ship, Placement::new(position, Orientation::East)));
+        }
+
+        Layout::new(placements).unwrap()
+    }
+
+    /// Create a game with both layouts set.
+    fn game(shots_per_turn: ShotsPerTurn, turn_rule: TurnRule) -> Game {
+        game_of(Fleet::default(), shots_per_turn, turn_rule)
+    }
+
+    /// Create a game of the fleet with both layouts set.
+    fn game_of(fleet: Fleet, shots_per_turn: ShotsPerTurn, turn_rule: TurnRule) -> Game {
+        let rules = Rules::new(
+            BoardSize::default(),
+            fleet,
+            shots_per_turn,
+            turn_rule,
+            Spacing::default(),
+            None,
+        )
+        .unwrap();
+
+        let mut game = Game::new(1, 2, rules.clone(), GameMode::Casual);
+        game.set_layout(1, layout(rules.fleet())).unwrap();
+        game.set_layout(2, layout(rules.fleet())).unwrap();
+        game
+    }
+
+    #[test]
+    fn test_hit_keeps_turn() {
+        let mut game = game(ShotsPerTurn::Fixed(1), TurnRule::HitKeepsTurn);
+
+        let hit = game.shoot(1, Position::new(0, 0).unwrap());
+        assert_eq!(hit, Ok(ShootResult::Hit));
+        assert_eq!(game.on_turn(), 1);
+
+        let miss = game.shoot(1, Position::new(1, 0).unwrap());
+        assert_eq!(miss, Ok(ShootResult::Missed));
+        assert_eq!(game.on_turn(), 2);
+    }
+
+    #[test]
+    fn test_already_shot() {
+        let mut game = game(ShotsPerTurn::Fixed(1), TurnRule::HitKeepsTurn);
+
+        assert!(!game.already_shot(1, &Position::new(0, 0).unwrap()));
+        game.shoot(1, Position::new(0, 0).unwrap()).unwrap();
+        assert!(game.already_shot(1, &Position::new(0, 0).unwrap()));
+        assert!(!game.already_shot(2, &Position::new(0, 0).unwrap()));
+
+        // a hit cell is hit again and the turn is kept
+        assert_eq!(
+            game.shoot(1, Position::new(0, 0).unwrap()),
+            Ok(ShootResult::Hit)
+        );
+        assert_eq!(game.on_turn(), 1);
+
+        game.shoot(1, Position::new(1, 0).unwrap()).unwrap();
+        game.shoot(2, Position::new(1, 0).unwrap()).unwrap();
+
+        // a missed cell is missed again
+        assert_eq!(
+            game.shoot(1, Position::new(1, 0).unwrap()),
+            Ok(ShootResult::Missed)
+        );
+        assert_eq!(game.on_turn(), 2);
+    }
+
+    #[test]
+    fn test_always_alternate() {
+        let mut game = game(ShotsPerTurn::Fixed(1), TurnRule::AlwaysAlternate);
+
+        let hit = game.shoot(1, Position::new(0, 0).unwrap());
+        assert_eq!(hit, Ok(ShootResult::Hit));
+        assert_eq!(game.on_turn(), 2);
+        assert_eq!(
+            game.shoot(1, Position::new(0, 1).unwrap()),
+            Err(GameError::NotOnTurn)
+        );
+    }
+
+    #[test]
+    fn test_salvo() {
+        let mut game = game(ShotsPerTurn::Fixed(3), TurnRule::AlwaysAlternate);
+
+        let positions = [Position::new(1, 0).unwrap(), Position::new(1, 1).unwrap()];
+        let results = game.shoot_salvo(1, &positions).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(game.on_turn(), 1);
+        assert_eq!(game.shots_left(), 1);
+
+        let positions = [Position::new(1, 2).unwrap(), Position::new(1, 3).unwrap()];
+        assert_eq!(
+            game.shoot_salvo(1, &positions),
+            Err(GameError::InvalidSalvo)
+        );
+
+        game.shoot(1, Position::new(1, 2).unwrap()).unwrap();
+        assert_eq!(game.on_turn(), 2);
+        assert_eq!(game.shots_left(), 3);
+    }
+
+    #[test]
+    fn test_salvo_fixed() {
+        let mut game = game(ShotsPerTurn::Fixed(3), TurnRule::HitKeepsTurn);
+        assert_eq!(game.shots_left(), 3);
+
+        let positions = [
+            Position::new(1, 0).unwrap(),
+            Position::new(1, 1).unwrap(),
+            Position::new(1, 2).unwrap(),
+        ];
+        let results = game.shoot_salvo(1, &positions).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|(_, r)| *r == ShootResult::Missed));
+        assert_eq!(game.on_turn(), 2);
+        assert_eq!(game.shots_left(), 3);
+
+        let positions = [Position::new(3, 0).unwrap(); 4];
+        assert_eq!(
+            game.shoot_salvo(2, &positions[..]),
+            Err(GameError::InvalidSalvo)
+        );
+        assert_eq!(game.shoot_salvo(2, &[]), Err(GameError::InvalidSalvo));
+    }
+
+    #[test]
+    fn test_salvo_surviving_ships() {
+        let mut game = game(ShotsPerTurn::SurvivingShips, TurnRule::AlwaysAlternate);
+        assert_eq!(game.shots_left(), 5);
+
+        // sink the patrol boat and the destroyer of the second player
+        let positions = [
+            Position::new(8, 0).unwrap(),
+            Position::new(6, 0).unwrap(),
+            Position::new(6, 1).unwrap(),
+            Position::new(9, 0).unwrap(),
+            Position::new(9, 1).unwrap(),
+        ];
+        let results = game.shoot_salvo(1, &positions).unwrap();
+        assert!(matches!(results[0].1, ShootResult::Sunk(_, _)));
+        assert_eq!(results[1].1, ShootResult::Hit);
+        assert!(matches!(results[2].1, ShootResult::Sunk(_, _)));
+        assert_eq!(game.on_turn(), 2);
+        assert_eq!(game.shots_left(), 3);
+
+        let positions = [Position::new(9, 0).unwrap()];
+        game.shoot_salvo(2, &positions).unwrap();
+        assert_eq!(game.shots_left(), 2);
+    }
+
+    #[test]
+    fn test_salvo_partial() {
+        let mut game = game(ShotsPerTurn::Fixed(4), TurnRule::AlwaysAlternate);
+
+        let positions = [Position::new(1, 0).unwrap()];
+        game.shoot_salvo(1, &positions).unwrap();
+        assert_eq!(game.shots_left(), 3);
+
+        let positions = [Position::new(1, 1).unwrap(), Position::new(1, 2).unwrap()];
+        game.shoot_salvo(1, &positions).unwrap();
+        assert_eq!(game.on_turn(), 1);
+        assert_eq!(game.shots_left(), 1);
+
+        // already shot at in the previous salvo
+        let positions = [Position::new(1, 0).unwrap()];
+        assert_eq!(
+            game.shoot_salvo(1, &positions),
+            Err(GameError::InvalidSalvo)
+        );
+
+        // repeated in the same salvo
+        let mut game = game_of(
+            Fleet::default(),
+            ShotsPerTurn::Fixed(2),
+            TurnRule::AlwaysAlternate,
+        );
+        let positions = [Position::new(0, 0).unwrap(), Position::new(0, 0).unwrap()];
+        assert_eq!(
+            game.shoot_salvo(1, &positions),
+            Err(GameError::InvalidSalvo)
+        );
+        assert_eq!(game.shots_left(), 2);
+    }
+
+    #[test]
+    fn test_salvo_game_over() {
+        let fleet = "P:PatrolBoat:1:2".parse().unwrap();
+        let mut game = game_of(fleet, ShotsPerTurn::Fixed(4), TurnRule::AlwaysAlternate);
+
+        let positions = [
+            Position::new(0, 0).unwrap(),
+            Position::new(2, 0).unwrap(),
+            Position::new(1, 0).unwrap(),
+        ];
+        let results = game.shoot_salvo(1, &positions).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(game.winner(), Some(1));
+    }
+
+    #[test]
+    fn test_pass_and_forfeit() {
+        let mut game = game(ShotsPerTurn::Fixed(2), TurnRule::HitKeepsTurn);
+
+        game.shoot(1, game.random_target(1)).unwrap();
+        assert_eq!(game.pass(2), Err(GameError::NotOnTurn));
+        assert_eq!(game.pass(1), Ok(()));
+        assert_eq!(game.on_turn(), 2);
+        assert_eq!(game.shots_left(), 2);
+        assert_eq!(game.turn(), 1);
+        assert_eq!(game.player_turns(1), 1);
+        assert_eq!(game.player_turns(2), 1);
+
+        let actions = game
+            .history()
+            .iter()
+            .map(|(_, player, action)| (*player, action.clone()))
+            .collect::<Vec<_>>();
+        assert!(matches!(actions[0], (1, GameAction::Layout(_))));
+        assert!(matches!(actions[1], (2, GameAction::Layout(_))));
+        assert!(matches!(actions[2], (1, GameAction::Shot(_, _))));
+        assert_eq!(actions[3], (1, GameAction::Pass));
+
+        game.forfeit(2);
+        assert_eq!(game.winner(), Some(1));
+    }
+
+    #[test]
+    fn test_resign() {
+        // the player on turn resigns
+        let mut game = game(ShotsPerTurn::Fixed(1), TurnRule::HitKeepsTurn);
+        game.shoot(1, Position::new(0, 0).unwrap()).unwrap();
+        assert_eq!(game.on_turn(), 1);
+        game.forfeit(1);
+        assert_eq!(game.winner(), Some(2));
+
+        // the player waiting for his turn resigns
+        let mut game = game_of(
+            Fleet::default(),
+            ShotsPerTurn::Fixed(1),
+            TurnRule::HitKeepsTurn,
+        );
+        game.shoot(1, Position::new(1, 0).unwrap()).unwrap();
+        assert_eq!(game.on_turn(), 2);
+        game.forfeit(1);
+        assert_eq!(game.winner(), Some(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "game is over")]
+    fn test_resign_finished_game() {
+        let mut game = game(ShotsPerTurn::Fixed(1), TurnRule::HitKeepsTurn);
+        game.forfeit(2);
+        game.forfeit(1);
+    }
+
+    #[test]
+    fn test_series() {
+        let mut game = game(ShotsPerTurn::Fixed(1), TurnRule::HitKeepsTurn);
+        let mut series = Series::new(1, 2, game.rules().clone(), game.mode());
+
+        game.forfeit(2);
+        series.record(&game);
+        assert_eq!(series.score(&1), (1, 0));
+        assert_eq!(series.score(&2), (0, 1));
+
+        series.offer(2);
+        assert_eq!(series.offered_by(), Some(2));
+
+        let rematch = series.rematch();
+        assert_eq!(rematch.starting_player(), 2);
+        assert_eq!(series.offered_by(), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! Histories of finished games exported as replays.
+
+use crate::game::ShootResult;
+use crate::store;
+use crate::types::{GameMode, GameOverReason, Layout, Nickname, Position, Rules};
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// An event in the history of a game.
+/// Times of the events following the start are measured since the creation of the game.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GameEvent {
+    /// The game of the two players was created.
+    Started(SystemTime, Nickname, Nickname, Rules, GameMode),
+    /// The player placed the fleet.
+    Layout(Duration, Nickname, Layout),
+    /// The player shot at the position.
+    Shot(Duration, Nickname, Position, ShootResult),
+    /// The player passed the rest of the turn.
+    Pass(Duration, Nickname),
+    /// The player won the game.
+    Won(Duration, Nickname, GameOverReason),
+    /// The player left the game before it was decided.
+    Abandoned(Duration, Nickname),
+}
+
+/// Replays of finished games indexed by game ids.
+///
+/// A replay is a list of lines, the first one is [FORMAT](History::FORMAT)
+/// and every other one is a serialized [GameEvent](GameEvent).
+/// The most recent replays are kept in memory, if the history has a directory,
+/// every replay is also stored in it as a file `<game id>.replay`.
+#[derive(Debug, Default)]
+pub struct History {
+    dir: Option<PathBuf>,
+    recent: VecDeque<(usize, Vec<String>)>,
+}
+
+impl History {
+    /// Format and version of the replays.
+    pub const FORMAT: &'static str = "battleships_replay;1";
+    /// Maximal number of replays kept in memory.
+    pub const RECENT_GAMES: usize = 100;
+
+    /// Open the history in the directory, which is created if it does not exist.
+    pub fn open(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        Ok(History {
+            dir: Some(dir),
+            recent: VecDeque::new(),
+        })
+    }
+
+    /// Get the path of the replay file of the game.
+    fn path(dir: &Path, game_id: usize) -> PathBuf {
+        dir.join(format!("{:0>16X}.replay", game_id))
+    }
+
+    /// Record the events of the finished game.
+    pub fn record(&mut self, game_id: usize, events: &[GameEvent]) -> io::Result<()> {
+        let mut lines = vec![String::from(Self::FORMAT)];
+        lines.extend(events.iter().map(|event| event.serialize()));
+
+        if self.recent.len() >= Self::RECENT_GAMES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((game_id, lines.clone()));
+
+        match self.dir {
+            Some(ref dir) => store::write_lines(&Self::path(dir, game_id), lines.into_iter()),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the replay of the finished game, None if the game is not recorded.
+    pub fn replay(&self, game_id: usize) -> io::Result<Option<Vec<String>>> {
+        if let Some((_, lines)) = self.recent.iter().find(|(id, _)| *id == game_id) {
+            return Ok(Some(lines.clone()));
+        }
+
+        let dir = match self.dir {
+            Some(ref dir) => dir,
+            None => return Ok(None),
+        };
+
+        let lines = store::read_lines(&Self::path(dir, game_id))?;
+
+        if lines.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(lines))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{GameEvent, History};
+    use crate::types::{GameOverReason, Nickname};
+    use std::time::Duration;
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+        let alice = Nickname::new(String::from("alice")).unwrap();
+        let events = vec![
+            GameEvent::Pass(Duration::from_millis(1500), alice.clone()),
+            GameEvent::Won(Duration::from_millis(2000), alice, GameOverReason::Resigned),
+        ];
+
+        history.record(1, &events).unwrap();
+
+        assert_eq!(
+            history.replay(1).unwrap().unwrap(),
+            vec![
+                String::from(History::FORMAT),
+                String::from("pass;1500;alice"),
+                String::from("won;2000;alice;resigned"),
+            ]
+        );
+        assert!(history.replay(2).unwrap().is_none());
+
+        for id in 2..History::RECENT_GAMES + 2 {
+            history.record(id, &events).unwrap();
+        }
+        assert!(history.replay(1).unwrap().is_none());
+        assert!(history.replay(2).unwrap().is_some());
+    }
+}
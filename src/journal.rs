@@ -0,0 +1,379 @@
+//! Crash-safe persistence of the sessions and the running games.
+
+use crate::game::{GameAction, ShootResult};
+use crate::store;
+use crate::types::{Difficulty, GameMode, Layout, Placement, Position, Rules, ShipId, TokenHash};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::iter;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A change of the sessions or the running games.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Record {
+    /// A session of the player with the nickname and the hash of the resume token was created.
+    Session(usize, String, TokenHash),
+    /// A session of the bot of the difficulty was created.
+    Bot(usize, Difficulty),
+    /// The session was removed.
+    SessionRemoved(usize),
+    /// A game of the two players was created at the time.
+    Game(usize, SystemTime, usize, usize, Rules, GameMode),
+    /// The player took the action in the game, at the time since the creation of the game.
+    Action(usize, Duration, usize, GameAction),
+    /// The game was removed.
+    GameRemoved(usize),
+}
+
+impl Record {
+    /// Format the record as one line of the journal.
+    fn to_line(&self) -> String {
+        match self {
+            Record::Session(player_id, nickname, token) => {
+                format!("session;{};{};{}", player_id, nickname, token)
+            }
+            Record::Bot(bot_id, difficulty) => format!("bot;{};{}", bot_id, difficulty),
+            Record::SessionRemoved(player_id) => format!("session_removed;{}", player_id),
+            Record::Game(game_id, created_at, first_player, second_player, rules, mode) => {
+                format!(
+                    "game;{};{};{};{};{};{};{};{};{};{};{}",
+                    game_id,
+                    created_at
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_millis(),
+                    first_player,
+                    second_player,
+                    rules.board_size(),
+                    rules.fleet(),
+                    rules.shots_per_turn(),
+                    rules.turn_rule(),
+                    rules.spacing(),
+                    rules
+                        .shot_clock()
+                        .map_or(String::from("none"), |shot_clock| shot_clock.to_string()),
+                    mode
+                )
+            }
+            Record::Action(game_id, time, player_id, action) => {
+                let action = match action {
+                    GameAction::Layout(layout) => format!(
+                        "layout;{}",
+                        layout
+                            .placements()
+                            .iter()
+                            .map(|(ship, placement)| Self::placement_to_string(ship, placement))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ),
+                    GameAction::Shot(position, result) => {
+                        let result = match result {
+                            ShootResult::Missed => String::from("missed"),
+                            ShootResult::Hit => String::from("hit"),
+                            ShootResult::Sunk(ship, placement) => {
+                                format!("sunk:{}", Self::placement_to_string(ship, placement))
+                            }
+                        };
+
+                        format!("shot;{};{};{}", position.row(), position.col(), result)
+                    }
+                    GameAction::Pass => String::from("pass"),
+                };
+
+                format!(
+                    "action;{};{};{};{}",
+                    game_id,
+                    time.as_millis(),
+                    player_id,
+                    action
+                )
+            }
+            Record::GameRemoved(game_id) => format!("game_removed;{}", game_id),
+        }
+    }
+
+    /// Parse one line of the journal.
+    fn parse_line(line: &str) -> Option<Record> {
+        let parts = line.split(';').collect::<Vec<_>>();
+
+        let record = match (parts[0], parts.len()) {
+            ("session", 4) => Record::Session(
+                parts[1].parse().ok()?,
+                String::from(parts[2]),
+                TokenHash::new(String::from(parts[3])).ok()?,
+            ),
+            ("bot", 3) => Record::Bot(parts[1].parse().ok()?, parts[2].parse().ok()?),
+            ("session_removed", 2) => Record::SessionRemoved(parts[1].parse().ok()?),
+            ("game", 12) => {
+                let shot_clock = match parts[10] {
+                    "none" => None,
+                    shot_clock => Some(shot_clock.parse().ok()?),
+                };
+                let rules = Rules::new(
+                    parts[5].parse().ok()?,
+                    parts[6].parse().ok()?,
+                    parts[7].parse().ok()?,
+                    parts[8].parse().ok()?,
+                    parts[9].parse().ok()?,
+                    shot_clock,
+                )
+                .ok()?;
+
+                Record::Game(
+                    parts[1].parse().ok()?,
+                    UNIX_EPOCH + Duration::from_millis(parts[2].parse().ok()?),
+                    parts[3].parse().ok()?,
+                    parts[4].parse().ok()?,
+                    rules,
+                    parts[11].parse().ok()?,
+                )
+            }
+            ("action", len) if len >= 5 => {
+                let action = match (parts[4], len) {
+                    ("layout", 6) => {
+                        let placements = parts[5]
+                            .split(',')
+                            .map(Self::parse_placement)
+                            .collect::<Option<Vec<_>>>()?;
+
+                        GameAction::Layout(Layout::new(placements).ok()?)
+                    }
+                    ("shot", 8) => {
+                        let position =
+                            Position::new(parts[5].parse().ok()?, parts[6].parse().ok()?).ok()?;
+                        let result = match parts[7] {
+                            "missed" => ShootResult::Missed,
+                            "hit" => ShootResult::Hit,
+                            sunk => {
+                                let (ship, placement) =
+                                    Self::parse_placement(sunk.strip_prefix("sunk:")?)?;
+                                ShootResult::Sunk(ship, placement)
+                            }
+                        };
+
+                        GameAction::Shot(position, result)
+                    }
+                    ("pass", 5) => GameAction::Pass,
+                    _ => return None,
+                };
+
+                Record::Action(
+                    parts[1].parse().ok()?,
+                    Duration::from_millis(parts[2].parse().ok()?),
+                    parts[3].parse().ok()?,
+                    action,
+                )
+            }
+            ("game_removed", 2) => Record::GameRemoved(parts[1].parse().ok()?),
+            _ => return None,
+        };
+
+        Some(record)
+    }
+
+    /// Format the placement of the ship as `<ship>:<row>:<col>:<orientation>`.
+    fn placement_to_string(ship: &ShipId, placement: &Placement) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            ship,
+            placement.position().row(),
+            placement.position().col(),
+            placement.orientation()
+        )
+    }
+
+    /// Parse the placement of the ship from the `<ship>:<row>:<col>:<orientation>` format.
+    fn parse_placement(string: &str) -> Option<(ShipId, Placement)> {
+        let parts = string.split(':').collect::<Vec<_>>();
+
+        if parts.len() != 4 {
+            return None;
+        }
+
+        let position = Position::new(parts[1].parse().ok()?, parts[2].parse().ok()?).ok()?;
+
+        Some((
+            parts[0].parse().ok()?,
+            Placement::new(position, parts[3].parse().ok()?),
+        ))
+    }
+}
+
+/// Sessions and running games persisted in a directory as a snapshot of the whole state
+/// and a journal of the records appended since the snapshot was taken.
+///
+/// Both files start with the generation of the snapshot. A journal of an older generation
+/// is already contained in the snapshot, so a crash while taking a snapshot loses nothing.
+/// Records are appended only after the first snapshot is taken.
+#[derive(Debug, Default)]
+pub struct Journal {
+    dir: Option<PathBuf>,
+    file: Option<File>,
+    generation: u64,
+    /// Number of records appended since the snapshot was taken.
+    appended: usize,
+}
+
+impl Journal {
+    /// Number of appended records after which a new snapshot should be taken.
+    pub const SNAPSHOT_INTERVAL: usize = 1000;
+
+    /// Open the journal in the directory, which is created if it does not exist.
+    /// Returns the journal and the records of the persisted state.
+    pub fn open(dir: PathBuf) -> io::Result<(Self, Vec<Record>)> {
+        fs::create_dir_all(&dir)?;
+
+        let snapshot = store::read_lines(&dir.join("snapshot"))?;
+        let journal = store::read_lines(&dir.join("journal"))?;
+
+        let generation = match snapshot.first() {
+            Some(line) => line.parse().map_err(|_| store::invalid_record(line))?,
+            None => 0,
+        };
+
+        let mut records = Vec::new();
+
+        for line in snapshot.iter().skip(1) {
+            records.push(Record::parse_line(line).ok_or_else(|| store::invalid_record(line))?);
+        }
+
+        if journal.first() == Some(&generation.to_string()) {
+            for (i, line) in journal.iter().enumerate().skip(1) {
+                match Record::parse_line(line) {
+                    Some(record) => records.push(record),
+                    // the last record may be torn by a crash
+                    None if i == journal.len() - 1 => {}
+                    None => return Err(store::invalid_record(line)),
+                }
+            }
+        }
+
+        let journal = Journal {
+            dir: Some(dir),
+            file: None,
+            generation,
+            appended: 0,
+        };
+
+        Ok((journal, records))
+    }
+
+    /// Append the records to the journal, they are on the disk once this returns.
+    pub fn append(&mut self, records: &[Record]) -> io::Result<()> {
+        let file = match self.file {
+            Some(ref mut file) => file,
+            None => return Ok(()),
+        };
+
+        let mut content = String::new();
+
+        for record in records {
+            content.push_str(&record.to_line());
+            content.push('\n');
+        }
+
+        file.write_all(content.as_bytes())?;
+        file.sync_data()?;
+
+        self.appended += records.len();
+
+        Ok(())
+    }
+
+    /// Check whether enough records were appended to take a new snapshot.
+    pub fn needs_snapshot(&self) -> bool {
+        self.appended >= Self::SNAPSHOT_INTERVAL
+    }
+
+    /// Replace the snapshot by the records of the whole state and start an empty journal.
+    pub fn snapshot(&mut self, records: &[Record]) -> io::Result<()> {
+        let dir = match self.dir {
+            Some(ref dir) => dir,
+            None => return Ok(()),
+        };
+
+        let generation = self.generation + 1;
+        let lines = iter::once(generation.to_string()).chain(records.iter().map(Record::to_line));
+        store::write_lines(&dir.join("snapshot"), lines)?;
+
+        // the journal is truncated only once the snapshot is on the disk
+        let path = dir.join("journal");
+        let mut file = File::create(&path)?;
+        writeln!(file, "{}", generation)?;
+        file.sync_all()?;
+        store::sync_dir(&path)?;
+
+        self.file = Some(file);
+        self.generation = generation;
+        self.appended = 0;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{GameAction, ShootResult};
+    use crate::journal::Record;
+    use crate::types::{
+        Difficulty, GameMode, Layout, Orientation, Placement, Position, ResumeToken, Rules, ShipId,
+    };
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_lines() {
+        let placement = Placement::new(Position::new(2, 3).unwrap(), Orientation::South);
+        let ship = "D1".parse::<ShipId>().unwrap();
+        let records = vec![
+            Record::Session(1, String::from("alice"), ResumeToken::random().hash()),
+            Record::Bot(2, Difficulty::Hard),
+            Record::Game(
+                3,
+                UNIX_EPOCH + Duration::from_millis(1_600_000_000_123),
+                1,
+                2,
+                Rules::default(),
+                GameMode::Rated,
+            ),
+            Record::Action(
+                3,
+                Duration::from_millis(1500),
+                1,
+                GameAction::Layout(Layout::new(vec![(ship, placement)]).unwrap()),
+            ),
+            Record::Action(
+                3,
+                Duration::from_millis(2500),
+                2,
+                GameAction::Shot(Position::new(2, 3).unwrap(), ShootResult::Missed),
+            ),
+            Record::Action(
+                3,
+                Duration::from_millis(3500),
+                1,
+                GameAction::Shot(
+                    Position::new(2, 3).unwrap(),
+                    ShootResult::Sunk(ship, placement),
+                ),
+            ),
+            Record::Action(3, Duration::from_millis(4500), 2, GameAction::Pass),
+            Record::GameRemoved(3),
+            Record::SessionRemoved(1),
+        ];
+
+        for record in records {
+            assert_eq!(Record::parse_line(&record.to_line()), Some(record));
+        }
+
+        assert!(Record::parse_line("action;3;1500;1;shot;2;3").is_none());
+        assert!(
+            Record::parse_line("session;alice;alice;0123456789abcdef0123456789abcdef").is_none()
+        );
+        assert!(Record::parse_line("session;1;alice").is_none());
+        // the resume token itself is not accepted instead of its hash
+        assert!(Record::parse_line("session;1;alice;0123456789abcdef0123456789abcdef").is_none());
+    }
+}
@@ -1,16 +1,36 @@
+pub mod accounts;
 pub mod app;
+pub mod bot;
+pub mod challenges;
+pub mod chat;
 pub mod game;
+pub mod history;
+pub mod journal;
 pub mod net;
+pub mod presence;
 pub mod proto;
+pub mod queue;
+pub mod rating;
+pub mod spectators;
+pub mod stats;
+pub mod store;
 pub mod types;
 
+use crate::accounts::Accounts;
 use crate::app::App;
-use crate::net::{PeerErrorKind, PollEvent, Poller, Server};
+use crate::chat::BlockedWords;
+use crate::history::History;
+use crate::journal::Journal;
+use crate::net::{load_tls_config, PeerErrorKind, PollEvent, Poller, Server};
 use crate::proto::ServerMessage;
+use crate::rating::Ratings;
+use crate::stats::Stats;
+use crate::types::Rules;
 use log::{debug, error, info, warn};
 use std::collections::HashSet;
 use std::io;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -22,6 +42,11 @@ pub struct Config {
     max_players: usize,
     peer_timeout: Duration,
     session_timeout: Duration,
+    rules: Rules,
+    data_dir: Option<PathBuf>,
+    require_auth: bool,
+    tls: Option<(PathBuf, PathBuf)>,
+    websocket_address: Option<SocketAddr>,
 }
 
 impl Config {
@@ -44,6 +69,33 @@ impl Config {
     pub fn session_timeout(&self) -> &Duration {
         &self.session_timeout
     }
+
+    /// Get the rules of games.
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Get the directory where the persistent data are stored, if any.
+    pub fn data_dir(&self) -> Option<&Path> {
+        self.data_dir.as_deref()
+    }
+
+    /// Check whether the registered players must log in with their passwords.
+    pub fn require_auth(&self) -> bool {
+        self.require_auth
+    }
+
+    /// Get the paths of the TLS certificate chain and private key, if the peers use TLS.
+    pub fn tls(&self) -> Option<(&Path, &Path)> {
+        self.tls
+            .as_ref()
+            .map(|(cert, key)| (cert.as_path(), key.as_path()))
+    }
+
+    /// Get the address on which the server listens for WebSocket clients, if any.
+    pub fn websocket_address(&self) -> Option<&SocketAddr> {
+        self.websocket_address.as_ref()
+    }
 }
 
 impl Config {
@@ -54,8 +106,44 @@ impl Config {
             max_players,
             peer_timeout,
             session_timeout: Duration::from_secs(300),
+            rules: Rules::default(),
+            data_dir: None,
+            require_auth: false,
+            tls: None,
+            websocket_address: None,
         }
     }
+
+    /// Set the rules of games.
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Set the directory where the persistent data are stored.
+    pub fn with_data_dir(mut self, data_dir: PathBuf) -> Self {
+        self.data_dir = Some(data_dir);
+        self
+    }
+
+    /// Set whether the registered players must log in with their passwords.
+    pub fn with_require_auth(mut self, require_auth: bool) -> Self {
+        self.require_auth = require_auth;
+        self
+    }
+
+    /// Set the paths of the PEM files with the TLS certificate chain and private key,
+    /// the peers must connect over TLS.
+    pub fn with_tls(mut self, cert: PathBuf, key: PathBuf) -> Self {
+        self.tls = Some((cert, key));
+        self
+    }
+
+    /// Set the address on which the server listens for WebSocket clients.
+    pub fn with_websocket_address(mut self, websocket_address: SocketAddr) -> Self {
+        self.websocket_address = Some(websocket_address);
+        self
+    }
 }
 
 impl Default for Config {
@@ -65,6 +153,11 @@ impl Default for Config {
             max_players: 128,
             peer_timeout: Duration::from_secs(10),
             session_timeout: Duration::from_secs(60),
+            rules: Rules::default(),
+            data_dir: None,
+            require_auth: false,
+            tls: None,
+            websocket_address: None,
         }
     }
 }
@@ -87,11 +180,54 @@ pub enum Command {
 /// If the peer is inactive for a longer period than is configured, the peer is disconnected.
 pub fn run_game_server(config: Config, shutdown: Arc<AtomicBool>) -> io::Result<()> {
     let mut server = Server::new(config.address().clone())?;
-    let mut app = App::new(config.max_players(), config.session_timeout().clone());
+    if let Some(websocket_address) = config.websocket_address() {
+        server = server.with_websocket(*websocket_address)?;
+    }
+    if let Some((cert, key)) = config.tls() {
+        server = server.with_tls(load_tls_config(cert, key)?);
+    }
+    let ratings = match config.data_dir() {
+        Some(data_dir) => Ratings::load(data_dir.join("ratings"))?,
+        None => Ratings::default(),
+    };
+    let stats = match config.data_dir() {
+        Some(data_dir) => Stats::load(data_dir.join("stats"))?,
+        None => Stats::default(),
+    };
+    let history = match config.data_dir() {
+        Some(data_dir) => History::open(data_dir.join("history"))?,
+        None => History::default(),
+    };
+    let accounts = match config.data_dir() {
+        Some(data_dir) => Accounts::load(data_dir.join("accounts"))?,
+        None => Accounts::default(),
+    }
+    .with_required(config.require_auth());
+    let (journal, records) = match config.data_dir() {
+        Some(data_dir) => Journal::open(data_dir.join("journal"))?,
+        None => (Journal::default(), Vec::new()),
+    };
+    let blocked_words = match config.data_dir() {
+        Some(data_dir) => BlockedWords::load(&data_dir.join("blocked_words"))?,
+        None => BlockedWords::default(),
+    };
+    let mut app = App::new(
+        config.max_players(),
+        config.session_timeout().clone(),
+        config.rules().clone(),
+        ratings,
+        stats,
+        history,
+        Box::new(blocked_words),
+    )
+    .with_accounts(accounts);
+    app.restore(journal, records)?;
     let mut poller = Poller::new(128)?;
 
-    // register servers listener for polling
-    poller.register_listener(server.listener(), 0)?;
+    // register servers listeners for polling
+    for (id, listener) in server.listeners() {
+        poller.register_listener(listener, *id)?;
+    }
 
     let peer_timeout = config.peer_timeout;
 
@@ -105,7 +241,37 @@ pub fn run_game_server(config: Config, shutdown: Arc<AtomicBool>) -> io::Result<
     let mut end = false;
 
     info!("starting the server on address: {}", config.address());
+    match config.websocket_address() {
+        Some(websocket_address) => info!("WebSocket address: {}", websocket_address),
+        None => info!("WebSocket: off"),
+    }
     info!("maximum number of players: {}", config.max_players());
+    info!("board size: {}", config.rules().board_size());
+    info!("fleet: {}", config.rules().fleet());
+    info!("shots per turn: {}", config.rules().shots_per_turn());
+    info!("turn rule: {}", config.rules().turn_rule());
+    info!("ships spacing: {}", config.rules().spacing());
+    match config.rules().shot_clock() {
+        Some(shot_clock) => info!(
+            "shot clock: {} seconds, then {}",
+            shot_clock.seconds(),
+            shot_clock.expiry()
+        ),
+        None => info!("shot clock: off"),
+    }
+    match config.data_dir() {
+        Some(data_dir) => info!("data directory: {}", data_dir.display()),
+        None => info!("data directory: none - nothing is persisted"),
+    }
+    match config.tls() {
+        Some((cert, _)) => info!("TLS: on, certificate: {}", cert.display()),
+        None => info!("TLS: off"),
+    }
+    if config.require_auth() {
+        info!("authentication: required for registered players");
+    } else {
+        info!("authentication: optional");
+    }
     info!(
         "sessions timeout: {} seconds",
         config.session_timeout().as_secs()
@@ -121,8 +287,8 @@ pub fn run_game_server(config: Config, shutdown: Arc<AtomicBool>) -> io::Result<
 
         for event in events.drain(..) {
             match event {
-                PollEvent::Accept(_) => {
-                    let peer = server.listener().accept_peer()?;
+                PollEvent::Accept(listener_id) => {
+                    let peer = server.listener(&listener_id).unwrap().accept_peer()?;
                     let address = peer.address().clone();
 
                     let id = server.add_peer(peer);
@@ -139,6 +305,14 @@ pub fn run_game_server(config: Config, shutdown: Arc<AtomicBool>) -> io::Result<
                                 debug!("incoming message from {:0>16X}: {}", id, message);
                                 incoming_messages.push((id, message));
                             }
+
+                            if peer.is_closed() {
+                                debug!("connection {:0>16X} closed", id);
+                                closed_peers.insert(id);
+                            } else if peer.wants_write() {
+                                // the TLS handshake may have to be answered
+                                reregister_peers.insert(id);
+                            }
                         }
                         Err(error) => {
                             match error.kind() {
@@ -148,6 +322,9 @@ pub fn run_game_server(config: Config, shutdown: Arc<AtomicBool>) -> io::Result<
                                 PeerErrorKind::Deserialization(error) => {
                                     error!("error in message stream: {}", error);
                                 }
+                                PeerErrorKind::WebSocket(error) => {
+                                    error!("error in WebSocket stream: {}", error);
+                                }
                             }
                             closed_peers.insert(id);
                         }
@@ -189,6 +366,12 @@ pub fn run_game_server(config: Config, shutdown: Arc<AtomicBool>) -> io::Result<
             }
         }
 
+        // Handle incoming messages, the last ones of closed peers included
+        for (id, message) in incoming_messages.drain(..) {
+            let mut result = app.handle_message(&id, message);
+            commands.extend(result.drain(..));
+        }
+
         // Handle closed peers
         for id in closed_peers.drain() {
             let peer = server.remove_peer(&id).unwrap();
@@ -198,15 +381,24 @@ pub fn run_game_server(config: Config, shutdown: Arc<AtomicBool>) -> io::Result<
             commands.extend(result.drain(..));
         }
 
-        // Handle incoming messages
-        for (id, message) in incoming_messages.drain(..) {
-            let mut result = app.handle_message(&id, message);
-            commands.extend(result.drain(..));
-        }
+        // Handle shot clocks
+        commands.extend(app.handle_shot_clocks());
+
+        // Match waiting players
+        commands.extend(app.handle_matchmaking());
+
+        // Expire unanswered challenges
+        commands.extend(app.handle_challenges());
 
         // Do a cleanup.
         commands.extend(app.handle_cleanup());
 
+        // Announce changes of the players statuses
+        commands.extend(app.handle_presence());
+
+        // Persist the changes of the sessions and games
+        app.handle_persistence();
+
         // If shutdown - handle shutdown
         end = shutdown.load(Ordering::SeqCst);
         if end {
@@ -229,9 +421,11 @@ pub fn run_game_server(config: Config, shutdown: Arc<AtomicBool>) -> io::Result<
                 Command::Close(id) => {
                     // force close on peer
 
-                    let peer = server.remove_peer(&id).unwrap();
-                    peer.close();
-                    poller.deregister_peer(&peer, &id)?;
+                    // the peer may have been closed already
+                    if let Some(peer) = server.remove_peer(&id) {
+                        peer.close();
+                        poller.deregister_peer(&peer, &id)?;
+                    }
                 }
             }
         }
@@ -1,9 +1,11 @@
+use bssrv::types::{BoardSize, Fleet, Rules, ShotClock, ShotsPerTurn, Spacing, TurnRule};
 use bssrv::{run_game_server, Config};
 use clap::{App, Arg};
 use log::error;
 use log::LevelFilter;
 use simplelog::{TermLogger, TerminalMode};
 use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -34,6 +36,15 @@ fn main() {
                 .validator(validate_port)
                 .default_value("10000"),
         )
+        .arg(
+            Arg::with_name("websocket_port")
+                .short("w")
+                .long("websocket_port")
+                .value_name("PORT")
+                .help("Sets a port on which the server listens for WebSocket clients, no WebSocket clients are accepted if not set.")
+                .takes_value(true)
+                .validator(validate_port),
+        )
         .arg(
             Arg::with_name("players")
                 .short("m")
@@ -54,6 +65,98 @@ fn main() {
                 .validator(validate_duration)
                 .default_value("5"),
         )
+        .arg(
+            Arg::with_name("board_size")
+                .short("b")
+                .long("board")
+                .value_name("ROWSxCOLS")
+                .help("Sets a size of the game board, each dimension between 6 - 26.")
+                .takes_value(true)
+                .validator(validate_board_size)
+                .default_value("10x10"),
+        )
+        .arg(
+            Arg::with_name("fleet")
+                .short("f")
+                .long("fleet")
+                .value_name("FLEET")
+                .help("Sets a fleet as comma separated ship classes <code>:<name>:<length>:<count>.")
+                .takes_value(true)
+                .validator(validate_fleet)
+                .default_value("A:AircraftCarrier:5:1,B:Battleship:4:1,C:Cruiser:3:1,D:Destroyer:2:1,P:PatrolBoat:1:1"),
+        )
+        .arg(
+            Arg::with_name("shots")
+                .short("s")
+                .long("shots")
+                .value_name("SHOTS")
+                .help("Sets a number of shots per turn or 'surviving' for as many shots as surviving ships.")
+                .takes_value(true)
+                .validator(validate_shots)
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("turn_rule")
+                .short("r")
+                .long("turn_rule")
+                .value_name("TURN_RULE")
+                .possible_values(&["hit_keeps_turn", "alternate"])
+                .help("Sets whether a hit keeps the turn or the players always alternate.")
+                .takes_value(true)
+                .default_value("hit_keeps_turn"),
+        )
+        .arg(
+            Arg::with_name("spacing")
+                .short("g")
+                .long("spacing")
+                .value_name("SPACING")
+                .possible_values(&["none", "orthogonal", "diagonal"])
+                .help("Sets whether ships may touch each other by sides or even by corners.")
+                .takes_value(true)
+                .default_value("orthogonal"),
+        )
+        .arg(
+            Arg::with_name("shot_clock")
+                .short("c")
+                .long("shot_clock")
+                .value_name("SECONDS:EXPIRY")
+                .help("Sets a time limit of one turn and what happens when it expires: random_shot, pass or forfeit.")
+                .takes_value(true)
+                .validator(validate_shot_clock),
+        )
+        .arg(
+            Arg::with_name("data_dir")
+                .short("d")
+                .long("data_dir")
+                .value_name("DIRECTORY")
+                .help("Sets a directory where player accounts, ratings, statistics, game replays and running games are persisted and blocked chat words are read from, nothing is persisted if not set.")
+                .takes_value(true)
+                .validator(validate_data_dir),
+        )
+        .arg(
+            Arg::with_name("require_auth")
+                .short("a")
+                .long("require_auth")
+                .help("Requires registered players to log in with their passwords, players without an account can still log in as guests."),
+        )
+        .arg(
+            Arg::with_name("tls_cert")
+                .long("tls_cert")
+                .value_name("FILE")
+                .help("Sets a PEM file with the TLS certificate chain, the clients must connect over TLS if set.")
+                .takes_value(true)
+                .requires("tls_key")
+                .validator(validate_file),
+        )
+        .arg(
+            Arg::with_name("tls_key")
+                .long("tls_key")
+                .value_name("FILE")
+                .help("Sets a PEM file with the private key of the TLS certificate.")
+                .takes_value(true)
+                .requires("tls_cert")
+                .validator(validate_file),
+        )
         .arg(
             Arg::with_name("log_level")
                 .short("l")
@@ -64,14 +167,23 @@ fn main() {
         )
         .get_matches();
 
-
-
     // get commandline arguments
     let log_level = matches.value_of("log_level").unwrap();
     let ip = matches.value_of("ip").unwrap();
     let port = matches.value_of("port").unwrap();
+    let websocket_port = matches.value_of("websocket_port");
     let players = matches.value_of("players").unwrap();
     let peer_timeout = matches.value_of("peer_timeout").unwrap();
+    let board_size = matches.value_of("board_size").unwrap();
+    let fleet = matches.value_of("fleet").unwrap();
+    let shots = matches.value_of("shots").unwrap();
+    let turn_rule = matches.value_of("turn_rule").unwrap();
+    let spacing = matches.value_of("spacing").unwrap();
+    let shot_clock = matches.value_of("shot_clock");
+    let data_dir = matches.value_of("data_dir");
+    let require_auth = matches.is_present("require_auth");
+    let tls_cert = matches.value_of("tls_cert");
+    let tls_key = matches.value_of("tls_key");
 
     // setup logging
     let log_level = match log_level {
@@ -102,7 +214,46 @@ fn main() {
     let address = SocketAddr::new(ip.parse().unwrap(), port.parse().unwrap());
     let max_players = players.parse().unwrap();
     let peer_timeout = Duration::from_secs(peer_timeout.parse().unwrap());
-    let config = Config::new(address, max_players, peer_timeout);
+    let board_size = BoardSize::from_str(board_size).unwrap();
+    let fleet = Fleet::from_str(fleet).unwrap();
+    let shots_per_turn = ShotsPerTurn::from_str(shots).unwrap();
+    let turn_rule = TurnRule::from_str(turn_rule).unwrap();
+    let spacing = Spacing::from_str(spacing).unwrap();
+    let shot_clock = shot_clock.map(|shot_clock| ShotClock::from_str(shot_clock).unwrap());
+
+    let rules = match Rules::new(
+        board_size,
+        fleet,
+        shots_per_turn,
+        turn_rule,
+        spacing,
+        shot_clock,
+    ) {
+        Ok(rules) => rules,
+        Err(error) => {
+            error!("Invalid game rules: {}", error);
+            return;
+        }
+    };
+
+    let mut config = Config::new(address, max_players, peer_timeout)
+        .with_rules(rules)
+        .with_require_auth(require_auth);
+
+    if let Some(websocket_port) = websocket_port {
+        config = config.with_websocket_address(SocketAddr::new(
+            ip.parse().unwrap(),
+            websocket_port.parse().unwrap(),
+        ));
+    }
+
+    if let Some(data_dir) = data_dir {
+        config = config.with_data_dir(PathBuf::from(data_dir));
+    }
+
+    if let (Some(tls_cert), Some(tls_key)) = (tls_cert, tls_key) {
+        config = config.with_tls(PathBuf::from(tls_cert), PathBuf::from(tls_key));
+    }
 
     match run_game_server(config, shutdown) {
         Ok(_) => {}
@@ -151,3 +302,61 @@ fn validate_duration(v: String) -> Result<(), String> {
         Err(error) => Err(error.to_string()),
     }
 }
+
+/// Validate the board size.
+fn validate_board_size(v: String) -> Result<(), String> {
+    let board_size = BoardSize::from_str(&v);
+
+    match board_size {
+        Ok(_) => Ok(()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Validate the fleet.
+fn validate_fleet(v: String) -> Result<(), String> {
+    let fleet = Fleet::from_str(&v);
+
+    match fleet {
+        Ok(_) => Ok(()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Validate the number of shots per turn.
+fn validate_shots(v: String) -> Result<(), String> {
+    let shots = ShotsPerTurn::from_str(&v);
+
+    match shots {
+        Ok(_) => Ok(()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Validate the shot clock.
+fn validate_shot_clock(v: String) -> Result<(), String> {
+    let shot_clock = ShotClock::from_str(&v);
+
+    match shot_clock {
+        Ok(_) => Ok(()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Validate the data directory.
+fn validate_data_dir(v: String) -> Result<(), String> {
+    if Path::new(&v).is_dir() {
+        Ok(())
+    } else {
+        Err(String::from("Data directory does not exist."))
+    }
+}
+
+/// Validate the file.
+fn validate_file(v: String) -> Result<(), String> {
+    if Path::new(&v).is_file() {
+        Ok(())
+    } else {
+        Err(String::from("File does not exist."))
+    }
+}
@@ -1,12 +1,19 @@
 use crate::net::peer::Peer;
+use crate::net::stream::Stream;
 use mio::net::TcpListener;
 use mio::{Poll, PollOpt, Ready, Token};
+use rustls::ServerConfig;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 pub struct Listener {
     address: SocketAddr,
     listener: TcpListener,
+    /// Config of TLS which encrypts the streams of accepted peers, if any.
+    tls: Option<Arc<ServerConfig>>,
+    /// Whether the accepted peers are WebSocket clients.
+    websocket: bool,
 }
 
 impl Listener {
@@ -15,9 +22,23 @@ impl Listener {
         Ok(Listener {
             address,
             listener: TcpListener::bind(&address)?,
+            tls: None,
+            websocket: false,
         })
     }
 
+    /// Make the listener accept WebSocket clients.
+    pub fn with_websocket(mut self) -> Self {
+        self.websocket = true;
+        self
+    }
+
+    /// Set the TLS config, the streams of accepted peers are encrypted by TLS.
+    pub fn with_tls(mut self, tls: Arc<ServerConfig>) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
     /// Get the address on which this listener listens.
     pub fn address(&self) -> &SocketAddr {
         &self.address
@@ -36,6 +57,18 @@ impl Listener {
     /// Accepts a new waiting peer.
     pub fn accept_peer(&self) -> io::Result<Peer> {
         let (stream, address) = self.listener.accept()?;
-        Ok(Peer::new(stream, address))
+
+        let stream = match self.tls {
+            Some(ref tls) => Stream::tls(stream, tls.clone())?,
+            None => Stream::Plain(stream),
+        };
+
+        let peer = Peer::new(stream, address);
+
+        if self.websocket {
+            Ok(peer.with_websocket())
+        } else {
+            Ok(peer)
+        }
     }
 }
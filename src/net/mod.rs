@@ -2,6 +2,8 @@ mod listener;
 mod peer;
 mod poller;
 mod server;
+mod stream;
+mod websocket;
 
 pub use listener::Listener;
 pub use peer::Peer;
@@ -10,3 +12,7 @@ pub use peer::PeerErrorKind;
 pub use poller::PollEvent;
 pub use poller::Poller;
 pub use server::Server;
+pub use stream::load_tls_config;
+pub use stream::Stream;
+pub use websocket::WebSocket;
+pub use websocket::WebSocketError;
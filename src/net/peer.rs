@@ -1,5 +1,6 @@
+use crate::net::stream::Stream;
+use crate::net::websocket::{Received, WebSocket, WebSocketError};
 use crate::proto::{ClientMessage, DeserializationError, Deserializer, Serializer, ServerMessage};
-use mio::net::TcpStream;
 use mio::{Poll, PollOpt, Ready, Token};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
@@ -10,25 +11,38 @@ use std::{fmt, io};
 
 /// A network remote point with associated stream, address, serializer and deserializer.
 pub struct Peer {
-    stream: TcpStream,
+    stream: Stream,
     address: SocketAddr,
+    /// WebSocket carrying the messages if the peer is a WebSocket client.
+    websocket: Option<WebSocket>,
     deserializer: Deserializer,
     serializer: Serializer,
     last_active: Instant,
+    /// Whether the client has closed the WebSocket.
+    closed: bool,
 }
 
 impl Peer {
     /// Create new peer.
-    pub fn new(stream: TcpStream, address: SocketAddr) -> Self {
+    pub fn new(stream: Stream, address: SocketAddr) -> Self {
         Peer {
             stream,
             address,
+            websocket: None,
             deserializer: Deserializer::new(),
             serializer: Serializer::new(),
             last_active: Instant::now(),
+            closed: false,
         }
     }
 
+    /// Make the peer a WebSocket client, the messages are carried in WebSocket frames
+    /// once the upgrade handshake is done.
+    pub fn with_websocket(mut self) -> Self {
+        self.websocket = Some(WebSocket::new());
+        self
+    }
+
     /// Get the peers remote address.
     pub fn address(&self) -> &SocketAddr {
         &self.address
@@ -39,30 +53,53 @@ impl Peer {
         self.last_active
     }
 
+    /// Check whether the client has closed the WebSocket, so the peer has to be closed
+    /// once the messages received before are handled.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
     /// Register the peer for polling.
     pub fn register(&self, poll: &Poll, token: Token) -> io::Result<()> {
-        poll.register(&self.stream, token, Ready::readable(), PollOpt::edge())
+        poll.register(self.stream.tcp(), token, Ready::readable(), PollOpt::edge())
     }
 
     /// Reregister the peer for polling.
     pub fn reregister(&self, poll: &Poll, token: Token) -> io::Result<()> {
         let mut ready = Ready::readable();
 
-        if self.serializer.has_bytes() {
+        if self.wants_write() {
             ready = ready | Ready::writable();
         }
 
-        poll.reregister(&self.stream, token, ready, PollOpt::edge())
+        poll.reregister(self.stream.tcp(), token, ready, PollOpt::edge())
     }
 
     /// Deregister the the peer from polling.
     pub fn deregister(&self, poll: &Poll) -> Result<(), io::Error> {
-        poll.deregister(&self.stream)
+        poll.deregister(self.stream.tcp())
+    }
+
+    /// Check whether any bytes wait to be written to the peer.
+    pub fn wants_write(&self) -> bool {
+        self.serializer.has_bytes()
+            || matches!(self.websocket, Some(ref websocket) if websocket.has_output())
+            || self.stream.wants_write()
     }
 
     /// Deserialize message into bytes and prepare them to stream write operation.
     pub fn add_message(&mut self, message: &ServerMessage) {
         self.serializer.serialize(&message);
+
+        if let Some(ref mut websocket) = self.websocket {
+            // alive ok answers a ping by its pong, other messages are sent in text frames
+            if !matches!(message, ServerMessage::AliveOk) || !websocket.pong() {
+                websocket.send(self.serializer.bytes());
+            }
+
+            let len = self.serializer.bytes().len();
+            self.serializer.clear(len);
+        }
     }
 
     /// Read as much data as possible at the moment from peer and build messages from it.
@@ -71,8 +108,10 @@ impl Peer {
 
         // buffer for incoming bytes
         let mut buffer = [0; 1024];
+        // pings of a WebSocket client are mapped to alive messages
+        let mut pings = 0;
 
-        loop {
+        while !self.closed {
             // read available bytes into the buffer
             let n = self.stream.read(&mut buffer);
 
@@ -83,7 +122,34 @@ impl Peer {
                 }
                 Ok(n) => {
                     // some are bytes available
-                    self.deserializer.deserialize(&buffer[0..n])?;
+                    let websocket = match self.websocket {
+                        Some(ref mut websocket) => websocket,
+                        None => {
+                            self.deserializer.deserialize(&buffer[0..n])?;
+                            continue;
+                        }
+                    };
+
+                    let received = match websocket.receive(&buffer[0..n]) {
+                        Ok(received) => received,
+                        Err(error) => {
+                            // try to send the refusal or the close frame before closing
+                            let _ = self.stream.write(websocket.output());
+                            return Err(error.into());
+                        }
+                    };
+
+                    for received in received {
+                        match received {
+                            Received::Data(bytes) => self.deserializer.deserialize(&bytes)?,
+                            Received::Ping => pings += 1,
+                            Received::Close => {
+                                // the messages received before the close are still handled
+                                let _ = self.stream.write(websocket.output());
+                                self.closed = true;
+                            }
+                        }
+                    }
                 }
                 Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => {
                     // no more available data
@@ -100,12 +166,19 @@ impl Peer {
             }
         }
 
-        Ok(self.deserializer.take_messages())
+        // the pings come first, so their alive oks are the first answered by pongs
+        Ok((0..pings)
+            .map(|_| ClientMessage::Alive)
+            .chain(self.deserializer.take_messages())
+            .collect())
     }
 
     /// Write as many buffered bytes as possible at the moment.
     pub fn do_write(&mut self) -> Result<(), PeerError> {
-        let to_write = self.serializer.bytes();
+        let to_write = match self.websocket {
+            Some(ref websocket) => websocket.output(),
+            None => self.serializer.bytes(),
+        };
 
         let bytes_written;
 
@@ -137,14 +210,17 @@ impl Peer {
             }
         }
 
-        self.serializer.clear(bytes_written);
+        match self.websocket {
+            Some(ref mut websocket) => websocket.clear(bytes_written),
+            None => self.serializer.clear(bytes_written),
+        }
 
         Ok(())
     }
 
     /// Close the peers stream.
     pub fn close(&self) {
-        let _ = self.stream.shutdown(Shutdown::Both);
+        let _ = self.stream.tcp().shutdown(Shutdown::Both);
     }
 }
 
@@ -155,6 +231,7 @@ impl Peer {
 pub enum PeerErrorKind {
     Closed,
     Deserialization(DeserializationError),
+    WebSocket(WebSocketError),
 }
 
 impl Display for PeerErrorKind {
@@ -162,6 +239,7 @@ impl Display for PeerErrorKind {
         match self {
             PeerErrorKind::Closed => write!(f, "Stream is closed."),
             PeerErrorKind::Deserialization(error) => write!(f, "Deserialization failed: {}", error),
+            PeerErrorKind::WebSocket(error) => write!(f, "WebSocket failed: {}", error),
         }
     }
 }
@@ -203,4 +281,10 @@ impl From<DeserializationError> for PeerError {
     }
 }
 
+impl From<WebSocketError> for PeerError {
+    fn from(error: WebSocketError) -> Self {
+        PeerErrorKind::WebSocket(error).into()
+    }
+}
+
 impl Error for PeerError {}
@@ -1,31 +1,58 @@
 use crate::net::listener::Listener;
 use crate::net::peer::Peer;
 use rand::Rng;
+use rustls::ServerConfig;
 use std::collections::hash_map;
 use std::collections::HashMap;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 pub struct Server {
-    listener: Listener,
-    listener_id: usize,
+    listeners: HashMap<usize, Listener>,
     peers: HashMap<usize, Peer>,
 }
 
 impl Server {
+    /// Id of the listener of the TCP clients.
+    pub const LISTENER_ID: usize = 0;
+    /// Id of the listener of the WebSocket clients.
+    pub const WEBSOCKET_LISTENER_ID: usize = 1;
+
     pub fn new(address: SocketAddr) -> io::Result<Self> {
+        let mut listeners = HashMap::new();
+        listeners.insert(Self::LISTENER_ID, Listener::new(address)?);
+
         Ok(Server {
-            listener: Listener::new(address)?,
-            listener_id: 0,
+            listeners,
             peers: HashMap::new(),
         })
     }
 
+    /// Add a listener of the WebSocket clients on the address.
+    pub fn with_websocket(mut self, address: SocketAddr) -> io::Result<Self> {
+        self.listeners.insert(
+            Self::WEBSOCKET_LISTENER_ID,
+            Listener::new(address)?.with_websocket(),
+        );
+        Ok(self)
+    }
+
+    /// Set the TLS config of all listeners, the streams of accepted peers are encrypted by TLS.
+    pub fn with_tls(mut self, tls: Arc<ServerConfig>) -> Self {
+        self.listeners = self
+            .listeners
+            .drain()
+            .map(|(id, listener)| (id, listener.with_tls(tls.clone())))
+            .collect();
+        self
+    }
+
     /// Get unique id for a new peer.
     fn unique_id(&self) -> usize {
         loop {
             let id = rand::thread_rng().gen();
-            if id != self.listener_id && !self.peers.contains_key(&id) {
+            if !self.listeners.contains_key(&id) && !self.peers.contains_key(&id) {
                 break id;
             }
         }
@@ -41,12 +68,12 @@ impl Server {
         self.peers.remove(id)
     }
 
-    pub fn listener(&self) -> &Listener {
-        &self.listener
+    pub fn listener(&self, id: &usize) -> Option<&Listener> {
+        self.listeners.get(id)
     }
 
-    pub fn listener_mut(&mut self) -> &mut Listener {
-        &mut self.listener
+    pub fn listeners(&self) -> hash_map::Iter<'_, usize, Listener> {
+        self.listeners.iter()
     }
 
     pub fn peer(&self, id: &usize) -> Option<&Peer> {
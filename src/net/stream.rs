@@ -0,0 +1,265 @@
+use mio::net::TcpStream;
+use rustls::{Certificate, PrivateKey, ServerConfig, ServerConnection};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A stream of a peer, either plain or encrypted by TLS.
+pub enum Stream {
+    Plain(TcpStream),
+    Tls(TcpStream, Box<ServerConnection>),
+}
+
+impl Stream {
+    /// Create a new stream encrypted by TLS with the server config.
+    pub fn tls(stream: TcpStream, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let connection = ServerConnection::new(config)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        Ok(Stream::Tls(stream, Box::new(connection)))
+    }
+
+    /// Get the underlying TCP stream.
+    pub fn tcp(&self) -> &TcpStream {
+        match self {
+            Stream::Plain(stream) => stream,
+            Stream::Tls(stream, _) => stream,
+        }
+    }
+
+    /// Check whether encrypted bytes are waiting to be written into the TCP stream.
+    pub fn wants_write(&self) -> bool {
+        match self {
+            Stream::Plain(_) => false,
+            Stream::Tls(_, connection) => connection.wants_write(),
+        }
+    }
+
+    /// Write as many encrypted bytes as possible at the moment into the TCP stream.
+    fn write_tls(stream: &mut TcpStream, connection: &mut ServerConnection) -> io::Result<()> {
+        while connection.wants_write() {
+            match connection.write_tls(stream) {
+                Ok(_) => {}
+                Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (stream, connection) = match self {
+            Stream::Plain(stream) => return stream.read(buf),
+            Stream::Tls(stream, connection) => (stream, connection),
+        };
+
+        loop {
+            // plaintext of the already received records
+            match connection.reader().read(buf) {
+                Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+
+            if connection.read_tls(stream)? == 0 {
+                return Ok(0);
+            }
+
+            let processed = connection.process_new_packets();
+
+            // answer the handshake or send the alert of the failure
+            Self::write_tls(stream, connection)?;
+
+            if let Err(error) = processed {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            Stream::Tls(stream, connection) => {
+                // encrypted bytes which can't be written now wait in the connection
+                let n = connection.writer().write(buf)?;
+                Self::write_tls(stream, connection)?;
+                Ok(n)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            Stream::Tls(stream, connection) => Self::write_tls(stream, connection),
+        }
+    }
+}
+
+/// Load the TLS server config from the PEM files with the certificate chain and the private key.
+pub fn load_tls_config(cert_path: &Path, key_path: &Path) -> io::Result<Arc<ServerConfig>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert_path)?))?;
+
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no certificate found in {}", cert_path.display()),
+        ));
+    }
+
+    let key = rustls_pemfile::read_all(&mut BufReader::new(File::open(key_path)?))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key) | rustls_pemfile::Item::PKCS8Key(key) => Some(key),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no private key found in {}", key_path.display()),
+            )
+        })?;
+
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            certs.into_iter().map(Certificate).collect(),
+            PrivateKey(key),
+        )
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    Ok(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::net::stream::Stream;
+    use mio::net::TcpStream;
+    use rustls::{
+        Certificate, ClientConfig, ClientConnection, PrivateKey, RootCertStore, ServerConfig,
+        ServerName,
+    };
+    use std::convert::TryFrom;
+    use std::io;
+    use std::io::{Read, Write};
+    use std::net;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Move the encrypted bytes between the client and the server until the server stream
+    /// reads the expected plaintext.
+    fn pump_to_server(
+        client: &mut ClientConnection,
+        client_tcp: &mut net::TcpStream,
+        server: &mut Stream,
+        expected: &[u8],
+    ) {
+        let mut received = Vec::new();
+        let mut buf = [0; 64];
+
+        for _ in 0..1000 {
+            pump_client(client, client_tcp);
+
+            match server.read(&mut buf) {
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+                Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => {}
+                Err(error) => panic!("server read failed: {}", error),
+            }
+
+            if received == expected {
+                return;
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        panic!("server received {:?}", received);
+    }
+
+    /// Write the pending encrypted bytes of the client and process the received ones.
+    fn pump_client(client: &mut ClientConnection, client_tcp: &mut net::TcpStream) {
+        while client.wants_write() {
+            client.write_tls(client_tcp).unwrap();
+        }
+
+        match client.read_tls(client_tcp) {
+            Ok(_) => {
+                client.process_new_packets().unwrap();
+            }
+            Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => {}
+            Err(error) => panic!("client read failed: {}", error),
+        }
+    }
+
+    #[test]
+    fn test_tls() {
+        let cert = rcgen::generate_simple_self_signed(vec![String::from("localhost")]).unwrap();
+        let cert_der = Certificate(cert.serialize_der().unwrap());
+        let key_der = PrivateKey(cert.serialize_private_key_der());
+
+        let server_config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert_der.clone()], key_der)
+            .unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(&cert_der).unwrap();
+        let client_config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client_tcp = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client_tcp.set_nonblocking(true).unwrap();
+        let (server_tcp, _) = listener.accept().unwrap();
+
+        let mut server = Stream::tls(
+            TcpStream::from_stream(server_tcp).unwrap(),
+            Arc::new(server_config),
+        )
+        .unwrap();
+        let mut client = ClientConnection::new(
+            Arc::new(client_config),
+            ServerName::try_from("localhost").unwrap(),
+        )
+        .unwrap();
+
+        // the plaintext waits in the client until the handshake is done
+        client.writer().write_all(b"ping").unwrap();
+        pump_to_server(&mut client, &mut client_tcp, &mut server, b"ping");
+        assert!(!client.is_handshaking());
+
+        server.write_all(b"pong").unwrap();
+        assert!(!server.wants_write());
+
+        let mut received = Vec::new();
+        for _ in 0..1000 {
+            pump_client(&mut client, &mut client_tcp);
+
+            let mut buf = [0; 64];
+            match client.reader().read(&mut buf) {
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+                Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => {}
+                Err(error) => panic!("client read failed: {}", error),
+            }
+
+            if received == b"pong" {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received, b"pong");
+    }
+}
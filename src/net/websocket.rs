@@ -0,0 +1,428 @@
+use sha1::{Digest, Sha1};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Something received from a WebSocket peer.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Received {
+    /// Bytes of the text protocol carried in a text or binary frame.
+    Data(Vec<u8>),
+    /// A ping, which is answered by a pong once [pong](WebSocket::pong) is called.
+    Ping,
+    /// The peer closed the connection, the close frame is answered.
+    Close,
+}
+
+/// The server side of a WebSocket connection, from the upgrade handshake to the closing.
+///
+/// Frames are decoded from the received bytes and encoded into the output bytes,
+/// which are written to the peer's stream by the caller.
+/// Text and binary frames carry the bytes of the text protocol as they are,
+/// so messages still end with the message end character.
+#[derive(Default)]
+pub struct WebSocket {
+    /// Whether the upgrade handshake is done.
+    open: bool,
+    /// Received bytes which do not form a whole request or frame yet.
+    input: Vec<u8>,
+    /// Encoded bytes to be written to the peer.
+    output: Vec<u8>,
+    /// Whether a fragmented text or binary message is being received.
+    fragmented: bool,
+    /// Payloads of the pings waiting for their pongs.
+    pings: VecDeque<Vec<u8>>,
+}
+
+impl WebSocket {
+    /// The GUID appended to the key of the handshake.
+    const GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+    /// Maximal length of the handshake request.
+    const MAX_HANDSHAKE_LENGTH: usize = 8192;
+    /// Maximal length of a frame payload.
+    const MAX_PAYLOAD_LENGTH: usize = 65536;
+
+    const OPCODE_CONTINUATION: u8 = 0x0;
+    const OPCODE_TEXT: u8 = 0x1;
+    const OPCODE_BINARY: u8 = 0x2;
+    const OPCODE_CLOSE: u8 = 0x8;
+    const OPCODE_PING: u8 = 0x9;
+    const OPCODE_PONG: u8 = 0xA;
+
+    /// Status code of the close frame of a protocol error.
+    const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+    /// Status code of the close frame of a too long frame.
+    const CLOSE_TOO_BIG: u16 = 1009;
+
+    /// Create a new WebSocket waiting for the upgrade handshake.
+    pub fn new() -> Self {
+        WebSocket {
+            open: false,
+            input: Vec::new(),
+            output: Vec::new(),
+            fragmented: false,
+            pings: VecDeque::new(),
+        }
+    }
+
+    /// Process the received bytes, the handshake is answered once the whole request is received.
+    /// On an error, the output contains the refusal of the handshake or the close frame.
+    pub fn receive(&mut self, bytes: &[u8]) -> Result<Vec<Received>, WebSocketError> {
+        self.input.extend_from_slice(bytes);
+
+        let mut received = Vec::new();
+
+        if !self.open {
+            let end = match self.input.windows(4).position(|w| w == b"\r\n\r\n") {
+                Some(position) => position + 4,
+                None if self.input.len() > Self::MAX_HANDSHAKE_LENGTH => {
+                    self.output
+                        .extend_from_slice(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+                    return Err(WebSocketError::Handshake);
+                }
+                None => return Ok(received),
+            };
+
+            let request = String::from_utf8_lossy(&self.input[..end]).into_owned();
+            self.input.drain(..end);
+
+            match Self::accept_key(&request) {
+                Some(accept_key) => {
+                    self.output.extend_from_slice(
+                        format!(
+                            "HTTP/1.1 101 Switching Protocols\r\n\
+                             Upgrade: websocket\r\n\
+                             Connection: Upgrade\r\n\
+                             Sec-WebSocket-Accept: {}\r\n\r\n",
+                            accept_key
+                        )
+                        .as_bytes(),
+                    );
+                    self.open = true;
+                }
+                None => {
+                    self.output
+                        .extend_from_slice(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+                    return Err(WebSocketError::Handshake);
+                }
+            }
+        }
+
+        while let Some((fin, opcode, payload)) = self.decode_frame()? {
+            match opcode {
+                Self::OPCODE_CONTINUATION if self.fragmented => {
+                    self.fragmented = !fin;
+                    received.push(Received::Data(payload));
+                }
+                Self::OPCODE_TEXT | Self::OPCODE_BINARY if !self.fragmented => {
+                    self.fragmented = !fin;
+                    received.push(Received::Data(payload));
+                }
+                Self::OPCODE_CLOSE => {
+                    // echo the status code, a payload too short for any is answered by an empty one
+                    let status = if payload.len() >= 2 {
+                        &payload[..2]
+                    } else {
+                        &[]
+                    };
+                    self.send_frame(Self::OPCODE_CLOSE, status);
+                    received.push(Received::Close);
+                    break;
+                }
+                Self::OPCODE_PING => {
+                    self.pings.push_back(payload);
+                    received.push(Received::Ping);
+                }
+                Self::OPCODE_PONG => {
+                    // unsolicited pongs are ignored
+                }
+                _ => return Err(self.fail(WebSocketError::Protocol)),
+            }
+        }
+
+        Ok(received)
+    }
+
+    /// Encode the bytes of the text protocol into a text frame.
+    pub fn send(&mut self, bytes: &[u8]) {
+        self.send_frame(Self::OPCODE_TEXT, bytes);
+    }
+
+    /// Answer the oldest unanswered ping by a pong.
+    /// Returns false if there is no such ping.
+    pub fn pong(&mut self) -> bool {
+        match self.pings.pop_front() {
+            Some(payload) => {
+                self.send_frame(Self::OPCODE_PONG, &payload);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check if encoded bytes are waiting to be written to the peer.
+    pub fn has_output(&self) -> bool {
+        !self.output.is_empty()
+    }
+
+    /// Get all encoded bytes waiting to be written to the peer.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Discard first `count` bytes of the output.
+    pub fn clear(&mut self, count: usize) {
+        let count = count.min(self.output.len());
+        self.output.drain(..count);
+    }
+
+    /// Get the accept key of the upgrade request, None if the request is not a valid upgrade.
+    fn accept_key(request: &str) -> Option<String> {
+        let mut lines = request.split("\r\n");
+
+        if !lines.next()?.starts_with("GET ") {
+            return None;
+        }
+
+        let mut upgrade = false;
+        let mut connection = false;
+        let mut version = false;
+        let mut key = None;
+
+        for line in lines {
+            let (name, value) = match line.find(':') {
+                Some(i) => (line[..i].trim().to_lowercase(), line[i + 1..].trim()),
+                None => continue,
+            };
+
+            match name.as_str() {
+                "upgrade" => upgrade = value.eq_ignore_ascii_case("websocket"),
+                "connection" => {
+                    connection = value
+                        .split(',')
+                        .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+                }
+                "sec-websocket-version" => version = value == "13",
+                "sec-websocket-key" => key = Some(value),
+                _ => {}
+            }
+        }
+
+        if !upgrade || !connection || !version {
+            return None;
+        }
+
+        let mut hasher = Sha1::new();
+        hasher.update(key?.as_bytes());
+        hasher.update(Self::GUID.as_bytes());
+
+        Some(base64::encode(hasher.finalize()))
+    }
+
+    /// Decode the next whole frame of the input into its fin flag, opcode and unmasked payload.
+    fn decode_frame(&mut self) -> Result<Option<(bool, u8, Vec<u8>)>, WebSocketError> {
+        let input = &self.input;
+
+        if input.len() < 2 {
+            return Ok(None);
+        }
+
+        let fin = input[0] & 0x80 != 0;
+        let opcode = input[0] & 0x0F;
+
+        // no extensions are negotiated and the frames of clients must be masked
+        if input[0] & 0x70 != 0 || input[1] & 0x80 == 0 {
+            return Err(self.fail(WebSocketError::Protocol));
+        }
+
+        let (length, offset) = match input[1] & 0x7F {
+            126 if input.len() < 4 => return Ok(None),
+            126 => (u16::from_be_bytes([input[2], input[3]]) as u64, 4),
+            127 if input.len() < 10 => return Ok(None),
+            127 => (u64::from_be_bytes(input[2..10].try_into().unwrap()), 10),
+            length => (length as u64, 2),
+        };
+
+        // control frames must not be fragmented nor long
+        if opcode >= Self::OPCODE_CLOSE && (!fin || length > 125) {
+            return Err(self.fail(WebSocketError::Protocol));
+        }
+
+        if length > Self::MAX_PAYLOAD_LENGTH as u64 {
+            return Err(self.fail(WebSocketError::TooLong));
+        }
+
+        let length = length as usize;
+
+        if input.len() < offset + 4 + length {
+            return Ok(None);
+        }
+
+        let mask = &input[offset..offset + 4];
+        let payload = input[offset + 4..offset + 4 + length]
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4])
+            .collect();
+
+        self.input.drain(..offset + 4 + length);
+
+        Ok(Some((fin, opcode, payload)))
+    }
+
+    /// Encode an unmasked frame with the opcode and the payload.
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) {
+        self.output.push(0x80 | opcode);
+
+        match payload.len() {
+            length if length < 126 => self.output.push(length as u8),
+            length if length <= u16::MAX as usize => {
+                self.output.push(126);
+                self.output
+                    .extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                self.output.push(127);
+                self.output
+                    .extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+
+        self.output.extend_from_slice(payload);
+    }
+
+    /// Encode the close frame of the error.
+    fn fail(&mut self, error: WebSocketError) -> WebSocketError {
+        let status = match error {
+            WebSocketError::TooLong => Self::CLOSE_TOO_BIG,
+            _ => Self::CLOSE_PROTOCOL_ERROR,
+        };
+        self.send_frame(Self::OPCODE_CLOSE, &status.to_be_bytes());
+
+        error
+    }
+}
+
+// ---ERRORS---
+
+/// An error indicating that the peer violated the WebSocket protocol.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WebSocketError {
+    Handshake,
+    Protocol,
+    TooLong,
+}
+
+impl Display for WebSocketError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            WebSocketError::Handshake => write!(f, "Invalid WebSocket upgrade request."),
+            WebSocketError::Protocol => write!(f, "Invalid WebSocket frame."),
+            WebSocketError::TooLong => write!(f, "WebSocket frame is too long."),
+        }
+    }
+}
+
+impl Error for WebSocketError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::net::websocket::{Received, WebSocket, WebSocketError};
+
+    /// Encode a masked frame as a client does.
+    fn client_frame(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut frame = vec![first_byte, 0x80 | payload.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    fn open_websocket() -> WebSocket {
+        let mut websocket = WebSocket::new();
+        websocket
+            .receive(
+                b"GET /chat HTTP/1.1\r\n\
+                  Host: server.example.com\r\n\
+                  Upgrade: websocket\r\n\
+                  Connection: keep-alive, Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                  Sec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .unwrap();
+        websocket
+    }
+
+    #[test]
+    fn test_handshake() {
+        let websocket = open_websocket();
+        let response = String::from_utf8(websocket.output().to_vec()).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        let mut websocket = WebSocket::new();
+        assert_eq!(
+            websocket.receive(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            Err(WebSocketError::Handshake)
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut websocket = open_websocket();
+        websocket.clear(websocket.output().len());
+
+        let mut bytes = client_frame(0x01, b"login:al");
+        bytes.extend(client_frame(0x80, b"ice\n"));
+        bytes.extend(client_frame(0x89, b"hi"));
+        let (first, second) = bytes.split_at(5);
+
+        assert_eq!(websocket.receive(first), Ok(vec![]));
+        assert_eq!(
+            websocket.receive(second),
+            Ok(vec![
+                Received::Data(b"login:al".to_vec()),
+                Received::Data(b"ice\n".to_vec()),
+                Received::Ping,
+            ])
+        );
+
+        assert!(websocket.pong());
+        assert!(!websocket.pong());
+        websocket.send(b"login_ok\n");
+        assert_eq!(websocket.output(), b"\x8a\x02hi\x81\x09login_ok\n");
+
+        assert_eq!(
+            websocket.receive(&[0x81, 0x01, b'x']),
+            Err(WebSocketError::Protocol)
+        );
+    }
+
+    #[test]
+    fn test_close() {
+        let mut websocket = open_websocket();
+        websocket.clear(websocket.output().len());
+
+        let mut bytes = client_frame(0x81, b"logout\n");
+        bytes.extend(client_frame(0x88, b"\x03\xe8bye"));
+        bytes.extend(client_frame(0x81, b"ignored\n"));
+
+        assert_eq!(
+            websocket.receive(&bytes),
+            Ok(vec![Received::Data(b"logout\n".to_vec()), Received::Close])
+        );
+        assert_eq!(websocket.output(), b"\x88\x02\x03\xe8");
+
+        let mut websocket = open_websocket();
+        websocket.clear(websocket.output().len());
+
+        assert_eq!(
+            websocket.receive(&client_frame(0x88, b"\x03")),
+            Ok(vec![Received::Close])
+        );
+        assert_eq!(websocket.output(), b"\x88\x00");
+    }
+}
@@ -0,0 +1,76 @@
+//! Statuses of players announced to the other players.
+
+use crate::types::PlayerStatus;
+use std::collections::HashMap;
+
+/// Last announced statuses of logged players.
+#[derive(Debug, Default)]
+pub struct Presence {
+    /// Last announced statuses indexed by nicknames.
+    statuses: HashMap<String, PlayerStatus>,
+}
+
+impl Presence {
+    /// Compare the current statuses of the logged players with the last announced ones
+    /// and remember the current statuses as announced.
+    /// Returns the changes ordered by nicknames, None for the players who logged out.
+    pub fn update(
+        &mut self,
+        current: HashMap<String, PlayerStatus>,
+    ) -> Vec<(String, Option<PlayerStatus>)> {
+        let mut changes = Vec::new();
+
+        for (nickname, status) in &current {
+            if self.statuses.get(nickname) != Some(status) {
+                changes.push((nickname.clone(), Some(*status)));
+            }
+        }
+
+        for nickname in self.statuses.keys() {
+            if !current.contains_key(nickname) {
+                changes.push((nickname.clone(), None));
+            }
+        }
+
+        changes.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        self.statuses = current;
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presence::Presence;
+    use crate::types::PlayerStatus;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_update() {
+        let mut presence = Presence::default();
+
+        let mut current = HashMap::new();
+        current.insert(String::from("alice"), PlayerStatus::Lobby);
+        current.insert(String::from("bob"), PlayerStatus::Lobby);
+
+        assert_eq!(
+            presence.update(current.clone()),
+            vec![
+                (String::from("alice"), Some(PlayerStatus::Lobby)),
+                (String::from("bob"), Some(PlayerStatus::Lobby)),
+            ]
+        );
+        assert!(presence.update(current.clone()).is_empty());
+
+        current.insert(String::from("alice"), PlayerStatus::Game);
+        current.remove("bob");
+
+        assert_eq!(
+            presence.update(current),
+            vec![
+                (String::from("alice"), Some(PlayerStatus::Game)),
+                (String::from("bob"), None),
+            ]
+        );
+    }
+}
@@ -191,6 +191,11 @@ impl Payload {
         self.items.push_back(int.to_string());
     }
 
+    /// Check whether there are no more items in the payload.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
     /// Take next item from the front of the payload.
     fn take_item(&mut self) -> Result<String, DeserializationError> {
         if let Some(item) = self.items.pop_front() {
@@ -4,8 +4,10 @@ use crate::proto::codec::{
     find, unescape, Payload, ESCAPE, MAX_MESSAGE_LENGTH, MESSAGE_END, PAYLOAD_START,
 };
 use crate::proto::ClientMessage;
-use crate::types::{Layout, Nickname, Orientation, Placement, Position, ShipKind, ShipsPlacements};
-use std::collections::HashMap;
+use crate::types::{
+    BoardSize, ChatChannel, ChatMessage, Difficulty, GameMode, Layout, Nickname, Orientation,
+    Password, Placement, Position, QueuePreference, ResumeToken, RoomCode, ShipId,
+};
 use std::error::Error;
 use std::fmt;
 use std::fmt::{Display, Formatter};
@@ -79,7 +81,8 @@ impl Deserializer {
         let mut byte_offset = 0;
 
         loop {
-            let separator_pos = find(&self.string_buffer[byte_offset..], MESSAGE_END, ESCAPE);
+            let separator_pos = find(&self.string_buffer[byte_offset..], MESSAGE_END, ESCAPE)
+                .map(|pos| byte_offset + pos);
 
             match separator_pos {
                 None => {
@@ -152,17 +155,92 @@ impl ClientMessage {
             "alive" => Ok(ClientMessage::Alive),
             "login" => {
                 let nickname = Nickname::deserialize(&mut payload)?;
-                Ok(ClientMessage::Login(nickname))
+
+                if payload.is_empty() {
+                    Ok(ClientMessage::Login(nickname, None))
+                } else {
+                    let password = Password::deserialize(&mut payload)?;
+                    Ok(ClientMessage::Login(nickname, Some(password)))
+                }
+            }
+            "resume" => {
+                let token = ResumeToken::deserialize(&mut payload)?;
+                Ok(ClientMessage::Resume(token))
+            }
+            "register" => {
+                let password = Password::deserialize(&mut payload)?;
+                Ok(ClientMessage::Register(password))
+            }
+            "join_game" => {
+                let preference = QueuePreference::deserialize(&mut payload)?;
+                Ok(ClientMessage::JoinGame(preference))
+            }
+            "join_game_bot" => {
+                let difficulty = Difficulty::deserialize(&mut payload)?;
+                Ok(ClientMessage::JoinGameBot(difficulty))
+            }
+            "room_create" => Ok(ClientMessage::RoomCreate),
+            "room_join" => {
+                let code = RoomCode::deserialize(&mut payload)?;
+                Ok(ClientMessage::RoomJoin(code))
+            }
+            "challenge" => {
+                let nickname = Nickname::deserialize(&mut payload)?;
+                Ok(ClientMessage::Challenge(nickname))
+            }
+            "challenge_accept" => {
+                let nickname = Nickname::deserialize(&mut payload)?;
+                Ok(ClientMessage::ChallengeAccept(nickname))
+            }
+            "challenge_decline" => {
+                let nickname = Nickname::deserialize(&mut payload)?;
+                Ok(ClientMessage::ChallengeDecline(nickname))
             }
-            "join_game" => Ok(ClientMessage::JoinGame),
             "layout" => {
                 let layout = Layout::deserialize(&mut payload)?;
                 Ok(ClientMessage::Layout(layout))
             }
+            "layout_random" => Ok(ClientMessage::LayoutRandom),
             "shoot" => {
                 let position = Position::deserialize(&mut payload)?;
                 Ok(ClientMessage::Shoot(position))
             }
+            "shoot_salvo" => {
+                let positions = Vec::<Position>::deserialize(&mut payload)?;
+                Ok(ClientMessage::ShootSalvo(positions))
+            }
+            "resign" => Ok(ClientMessage::Resign),
+            "rating" => {
+                if payload.is_empty() {
+                    Ok(ClientMessage::Rating(None))
+                } else {
+                    let nickname = Nickname::deserialize(&mut payload)?;
+                    Ok(ClientMessage::Rating(Some(nickname)))
+                }
+            }
+            "leaderboard" => {
+                let count = payload.take_u8()?;
+                Ok(ClientMessage::Leaderboard(count))
+            }
+            "list_games" => Ok(ClientMessage::ListGames),
+            "spectate" => {
+                let nickname = Nickname::deserialize(&mut payload)?;
+                Ok(ClientMessage::Spectate(nickname))
+            }
+            "spectate_leave" => Ok(ClientMessage::SpectateLeave),
+            "chat" => {
+                let channel = ChatChannel::deserialize(&mut payload)?;
+                let message = ChatMessage::deserialize(&mut payload)?;
+                Ok(ClientMessage::Chat(channel, message))
+            }
+            "list_players" => Ok(ClientMessage::ListPlayers),
+            "history" => {
+                let game_id = usize::from_str_radix(&payload.take_string()?, 16)?;
+                Ok(ClientMessage::History(game_id))
+            }
+            "rematch_offer" => Ok(ClientMessage::RematchOffer),
+            "rematch_accept" => Ok(ClientMessage::RematchAccept),
+            "rematch_decline" => Ok(ClientMessage::RematchDecline),
             "leave_game" => Ok(ClientMessage::LeaveGame),
             "logout" => Ok(ClientMessage::LogOut),
             _ => Err(DeserializationError::new(
@@ -201,6 +279,121 @@ impl DeserializeFromPayload for Nickname {
     }
 }
 
+impl DeserializeFromPayload for Password {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let password = payload.take_string();
+
+        if let Err(error) = password {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::Password,
+                error.into(),
+            )
+            .into());
+        }
+
+        match Password::new(password.unwrap()) {
+            Ok(password) => Ok(password),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::Password,
+                error.into(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl DeserializeFromPayload for ChatMessage {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let text = payload.take_string();
+
+        if let Err(error) = text {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::ChatMessage,
+                error.into(),
+            )
+            .into());
+        }
+
+        match ChatMessage::new(text.unwrap()) {
+            Ok(message) => Ok(message),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::ChatMessage,
+                error.into(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl DeserializeFromPayload for ChatChannel {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let string = payload.take_string();
+
+        if let Err(error) = string {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::ChatChannel,
+                error.into(),
+            )
+            .into());
+        }
+
+        match string.unwrap().parse::<ChatChannel>() {
+            Ok(channel) => Ok(channel),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::ChatChannel,
+                error.into(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl DeserializeFromPayload for RoomCode {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let code = payload.take_string();
+
+        if let Err(error) = code {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::RoomCode,
+                error.into(),
+            )
+            .into());
+        }
+
+        match RoomCode::new(code.unwrap()) {
+            Ok(code) => Ok(code),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::RoomCode,
+                error.into(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl DeserializeFromPayload for ResumeToken {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let token = payload.take_string();
+
+        if let Err(error) = token {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::ResumeToken,
+                error.into(),
+            )
+            .into());
+        }
+
+        match ResumeToken::new(token.unwrap()) {
+            Ok(token) => Ok(token),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::ResumeToken,
+                error.into(),
+            )
+            .into()),
+        }
+    }
+}
+
 impl DeserializeFromPayload for Position {
     fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
         let row = payload.take_u8();
@@ -233,6 +426,112 @@ impl DeserializeFromPayload for Position {
     }
 }
 
+impl DeserializeFromPayload for BoardSize {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let rows = payload.take_u8();
+        let cols = payload.take_u8();
+
+        if let Err(error) = rows {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::BoardSize,
+                error.into(),
+            )
+            .into());
+        }
+
+        if let Err(error) = cols {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::BoardSize,
+                error.into(),
+            )
+            .into());
+        }
+
+        match BoardSize::new(rows.unwrap(), cols.unwrap()) {
+            Ok(board_size) => Ok(board_size),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::BoardSize,
+                error.into(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl DeserializeFromPayload for QueuePreference {
+    /// An empty payload means the default preference,
+    /// the board size may be omitted after the game mode.
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        if payload.is_empty() {
+            return Ok(QueuePreference::default());
+        }
+
+        let mode = GameMode::deserialize(payload)?;
+
+        if payload.is_empty() {
+            return Ok(QueuePreference::new(mode, None));
+        }
+
+        let board_size = BoardSize::deserialize(payload)?;
+        Ok(QueuePreference::new(mode, Some(board_size)))
+    }
+}
+
+impl DeserializeFromPayload for GameMode {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let string = payload.take_string();
+
+        if let Err(error) = string {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::GameMode,
+                error.into(),
+            )
+            .into());
+        }
+
+        match string.unwrap().parse::<GameMode>() {
+            Ok(mode) => Ok(mode),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::GameMode,
+                error.into(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl<T: DeserializeFromPayload> DeserializeFromPayload for Vec<T> {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let size = payload.take_u8();
+
+        if let Err(error) = size {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::List,
+                error.into(),
+            )
+            .into());
+        }
+
+        let size = size.unwrap();
+        let mut items = Vec::with_capacity(size as usize);
+
+        for _ in 0..size {
+            match T::deserialize(payload) {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    return Err(StructDeserializationError::new(
+                        StructDeserializeErrorKind::List,
+                        error.into(),
+                    )
+                    .into())
+                }
+            }
+        }
+
+        Ok(items)
+    }
+}
+
 impl DeserializeFromPayload for Orientation {
     fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
         let string = payload.take_string();
@@ -287,49 +586,27 @@ impl DeserializeFromPayload for Placement {
 }
 
 impl DeserializeFromPayload for Layout {
-    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
-        let placements = ShipsPlacements::deserialize(payload);
-
-        if let Err(error) = placements {
-            return Err(StructDeserializationError::new(
-                StructDeserializeErrorKind::Layout,
-                error.into(),
-            )
-            .into());
-        }
-
-        match Layout::new(placements.unwrap()) {
-            Ok(layout) => Ok(layout),
-            Err(error) => Err(StructDeserializationError::new(
-                StructDeserializeErrorKind::Layout,
-                error.into(),
-            )
-            .into()),
-        }
-    }
-}
-
-impl DeserializeFromPayload for ShipsPlacements {
     fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
         let size = payload.take_u8();
 
         if let Err(error) = size {
             return Err(StructDeserializationError::new(
-                StructDeserializeErrorKind::ShipsPlacements,
+                StructDeserializeErrorKind::Layout,
                 error.into(),
             )
             .into());
         }
 
-        let mut placements = HashMap::with_capacity(5);
+        let size = size.unwrap();
+        let mut placements = Vec::with_capacity(size as usize);
 
-        for _ in 0..(size.unwrap()) {
-            let kind = ShipKind::deserialize(payload);
+        for _ in 0..size {
+            let ship = ShipId::deserialize(payload);
             let placement = Placement::deserialize(payload);
 
-            if let Err(error) = kind {
+            if let Err(error) = ship {
                 return Err(StructDeserializationError::new(
-                    StructDeserializeErrorKind::ShipsPlacements,
+                    StructDeserializeErrorKind::Layout,
                     error.into(),
                 )
                 .into());
@@ -337,42 +614,66 @@ impl DeserializeFromPayload for ShipsPlacements {
 
             if let Err(error) = placement {
                 return Err(StructDeserializationError::new(
-                    StructDeserializeErrorKind::ShipsPlacements,
+                    StructDeserializeErrorKind::Layout,
                     error.into(),
                 )
                 .into());
             }
 
-            placements.insert(kind.unwrap(), placement.unwrap());
+            placements.push((ship.unwrap(), placement.unwrap()));
         }
 
-        Ok(ShipsPlacements::new(placements))
+        match Layout::new(placements) {
+            Ok(layout) => Ok(layout),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::Layout,
+                error.into(),
+            )
+            .into()),
+        }
     }
 }
 
-impl DeserializeFromPayload for ShipKind {
+impl DeserializeFromPayload for ShipId {
     fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
         let string = payload.take_string();
 
         if let Err(error) = string {
             return Err(StructDeserializationError::new(
-                StructDeserializeErrorKind::ShipKind,
+                StructDeserializeErrorKind::ShipId,
                 error.into(),
             )
             .into());
         }
 
-        match string.unwrap().as_str() {
-            "A" => Ok(ShipKind::AircraftCarrier),
-            "B" => Ok(ShipKind::Battleship),
-            "C" => Ok(ShipKind::Cruiser),
-            "D" => Ok(ShipKind::Destroyer),
-            "P" => Ok(ShipKind::PatrolBoat),
-            _ => Err(StructDeserializationError::new(
-                StructDeserializeErrorKind::ShipKind,
-                Box::new(DeserializationError::new(
-                    DeserializationErrorKind::InvalidEnumValue,
-                )),
+        match string.unwrap().parse::<ShipId>() {
+            Ok(ship) => Ok(ship),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::ShipId,
+                error.into(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl DeserializeFromPayload for Difficulty {
+    fn deserialize(payload: &mut Payload) -> Result<Self, DeserializationError> {
+        let string = payload.take_string();
+
+        if let Err(error) = string {
+            return Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::Difficulty,
+                error.into(),
+            )
+            .into());
+        }
+
+        match string.unwrap().parse::<Difficulty>() {
+            Ok(difficulty) => Ok(difficulty),
+            Err(error) => Err(StructDeserializationError::new(
+                StructDeserializeErrorKind::Difficulty,
+                error.into(),
             )
             .into()),
         }
@@ -457,12 +758,20 @@ impl Error for DeserializationError {}
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum StructDeserializeErrorKind {
     Nickname,
-    ShipKind,
+    RoomCode,
+    ShipId,
     Position,
     Orientation,
     Placement,
     Layout,
-    ShipsPlacements,
+    List,
+    Difficulty,
+    BoardSize,
+    GameMode,
+    ChatChannel,
+    ChatMessage,
+    Password,
+    ResumeToken,
 }
 
 impl Display for StructDeserializeErrorKind {
@@ -471,7 +780,10 @@ impl Display for StructDeserializeErrorKind {
             StructDeserializeErrorKind::Nickname => {
                 write!(f, "Nickname can't be properly deserialized")
             }
-            StructDeserializeErrorKind::ShipKind => {
+            StructDeserializeErrorKind::RoomCode => {
+                write!(f, "RoomCode can't be properly deserialized")
+            }
+            StructDeserializeErrorKind::ShipId => {
                 write!(f, "ShipId can't be properly deserialized")
             }
             StructDeserializeErrorKind::Position => {
@@ -483,12 +795,31 @@ impl Display for StructDeserializeErrorKind {
             StructDeserializeErrorKind::Placement => {
                 write!(f, "Placement can't be properly deserialized")
             }
-            StructDeserializeErrorKind::ShipsPlacements => {
-                write!(f, "ShipsPlacements can't be properly deserialized")
-            }
             StructDeserializeErrorKind::Layout => {
                 write!(f, "Layout can't be properly deserialized")
             }
+            StructDeserializeErrorKind::List => write!(f, "List can't be properly deserialized"),
+            StructDeserializeErrorKind::Difficulty => {
+                write!(f, "Difficulty can't be properly deserialized")
+            }
+            StructDeserializeErrorKind::BoardSize => {
+                write!(f, "BoardSize can't be properly deserialized")
+            }
+            StructDeserializeErrorKind::GameMode => {
+                write!(f, "GameMode can't be properly deserialized")
+            }
+            StructDeserializeErrorKind::ChatChannel => {
+                write!(f, "ChatChannel can't be properly deserialized")
+            }
+            StructDeserializeErrorKind::ChatMessage => {
+                write!(f, "ChatMessage can't be properly deserialized")
+            }
+            StructDeserializeErrorKind::Password => {
+                write!(f, "Password can't be properly deserialized")
+            }
+            StructDeserializeErrorKind::ResumeToken => {
+                write!(f, "ResumeToken can't be properly deserialized")
+            }
         }
     }
 }
@@ -525,3 +856,28 @@ impl Display for StructDeserializationError {
 }
 
 impl Error for StructDeserializationError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::proto::deserialize::Deserializer;
+    use crate::proto::ClientMessage;
+
+    #[test]
+    fn test_deserialize_stream() {
+        let mut deserializer = Deserializer::new();
+
+        deserializer
+            .deserialize(b"alive\nchat:lobby;a\\;b: c\nshoo")
+            .unwrap();
+        deserializer.deserialize(b"t:1;2\n").unwrap();
+
+        let messages = deserializer.take_messages();
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(messages[0], ClientMessage::Alive));
+        match &messages[1] {
+            ClientMessage::Chat(_, message) => assert_eq!(message.get(), "a;b: c"),
+            _ => panic!("chat message expected"),
+        }
+        assert!(matches!(messages[2], ClientMessage::Shoot(_)));
+    }
+}
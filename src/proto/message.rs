@@ -1,7 +1,13 @@
 //! Battleships protocol message types,
 //! And payload container.
 
-use crate::types::{Layout, Nickname, Placement, Position, RestoreState, ShipKind, Who};
+use crate::game::ShootResult;
+use crate::stats::PlayerStats;
+use crate::types::{
+    ChatChannel, ChatMessage, Difficulty, GameOverReason, Hits, Layout, LayoutError, Nickname,
+    Password, Placement, PlayerStatus, Position, QueuePreference, RestoreState, ResumeToken,
+    RoomCode, Rules, ShipId, SpectateState, TurnExpiry, Who,
+};
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
@@ -9,10 +15,32 @@ use std::fmt::{Display, Formatter};
 #[derive(Debug, Clone)]
 pub enum ClientMessage {
     Alive,
-    Login(Nickname),
-    JoinGame,
+    Login(Nickname, Option<Password>),
+    Resume(ResumeToken),
+    Register(Password),
+    JoinGame(QueuePreference),
+    JoinGameBot(Difficulty),
+    RoomCreate,
+    RoomJoin(RoomCode),
+    Challenge(Nickname),
+    ChallengeAccept(Nickname),
+    ChallengeDecline(Nickname),
     Layout(Layout),
+    LayoutRandom,
     Shoot(Position),
+    ShootSalvo(Vec<Position>),
+    Resign,
+    Rating(Option<Nickname>),
+    Leaderboard(u8),
+    ListGames,
+    Spectate(Nickname),
+    SpectateLeave,
+    Chat(ChatChannel, ChatMessage),
+    ListPlayers,
+    History(usize),
+    RematchOffer,
+    RematchAccept,
+    RematchDecline,
     LeaveGame,
     LogOut,
 }
@@ -21,10 +49,48 @@ impl Display for ClientMessage {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
             ClientMessage::Alive => write!(f, "[alive]"),
-            ClientMessage::Login(nickname) => write!(f, "[login: {}]", nickname),
-            ClientMessage::JoinGame => write!(f, "[join game]"),
+            ClientMessage::Login(nickname, None) => write!(f, "[login: {}]", nickname),
+            ClientMessage::Login(nickname, Some(password)) => {
+                write!(f, "[login: {}, {}]", nickname, password)
+            }
+            ClientMessage::Resume(_) => write!(f, "[resume]"),
+            ClientMessage::Register(password) => write!(f, "[register: {}]", password),
+            ClientMessage::JoinGame(preference) => write!(f, "[join game: {}]", preference),
+            ClientMessage::JoinGameBot(difficulty) => write!(f, "[join game bot: {}]", difficulty),
+            ClientMessage::RoomCreate => write!(f, "[room create]"),
+            ClientMessage::RoomJoin(code) => write!(f, "[room join: {}]", code),
+            ClientMessage::Challenge(nickname) => write!(f, "[challenge: {}]", nickname),
+            ClientMessage::ChallengeAccept(nickname) => {
+                write!(f, "[challenge accept: {}]", nickname)
+            }
+            ClientMessage::ChallengeDecline(nickname) => {
+                write!(f, "[challenge decline: {}]", nickname)
+            }
             ClientMessage::Layout(layout) => write!(f, "[layout: {}]", layout),
+            ClientMessage::LayoutRandom => write!(f, "[layout random]"),
             ClientMessage::Shoot(position) => write!(f, "[shoot: {}]", position),
+            ClientMessage::ShootSalvo(positions) => write!(
+                f,
+                "[shoot salvo: {}]",
+                positions
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ClientMessage::Resign => write!(f, "[resign]"),
+            ClientMessage::Rating(None) => write!(f, "[rating]"),
+            ClientMessage::Rating(Some(nickname)) => write!(f, "[rating: {}]", nickname),
+            ClientMessage::Leaderboard(count) => write!(f, "[leaderboard: {}]", count),
+            ClientMessage::ListGames => write!(f, "[list games]"),
+            ClientMessage::Spectate(nickname) => write!(f, "[spectate: {}]", nickname),
+            ClientMessage::SpectateLeave => write!(f, "[spectate leave]"),
+            ClientMessage::Chat(channel, message) => write!(f, "[chat: {}, {}]", channel, message),
+            ClientMessage::ListPlayers => write!(f, "[list players]"),
+            ClientMessage::History(game_id) => write!(f, "[history: {:0>16X}]", game_id),
+            ClientMessage::RematchOffer => write!(f, "[rematch offer]"),
+            ClientMessage::RematchAccept => write!(f, "[rematch accept]"),
+            ClientMessage::RematchDecline => write!(f, "[rematch decline]"),
             ClientMessage::LeaveGame => write!(f, "[leave game]"),
             ClientMessage::LogOut => write!(f, "[logout]"),
         }
@@ -35,27 +101,84 @@ impl Display for ClientMessage {
 pub enum ServerMessage {
     IllegalState,
     AliveOk,
-    LoginOk,
-    LoginRestored(RestoreState),
+    /// The token by which the session can be restored once the player is offline.
+    LoginOk(ResumeToken),
+    /// The new token of the restored session, the previous one can't be used anymore.
+    LoginRestored(ResumeToken, RestoreState),
     LoginFull,
     LoginTaken,
-    JoinGameWait,
-    JoinGameOk(Nickname),
+    /// The nickname is registered and the password is missing or wrong.
+    LoginUnauthorized,
+    /// The resume token does not belong to any session.
+    ResumeFail,
+    RegisterOk,
+    /// The nickname is already registered.
+    RegisterFail,
+    /// Position in the queue and estimated wait in seconds, 0 if it can't be estimated yet.
+    JoinGameWait(u16, u16),
+    JoinGameOk(Nickname, Rules),
+    RoomCreateOk(RoomCode),
+    RoomJoinFail,
+    ChallengeOk,
+    ChallengeFail,
+    ChallengeDeclineOk,
     LayoutOk,
-    LayoutFail,
+    LayoutFail(LayoutError),
+    LayoutRandomOk(Layout),
+    LayoutRandomFail,
     ShootHit,
     ShootMissed,
-    ShootSunk(ShipKind, Placement),
+    ShootSunk(ShipId, Placement),
+    ShootSalvo(Vec<(Position, ShootResult)>, u8),
     LeaveGameOk,
     LogoutOk,
     Disconnect,
-    OpponentJoined(Nickname),
+    OpponentJoined(Nickname, Rules),
     OpponentReady,
     OpponentOffline,
     OpponentLeft,
     OpponentMissed(Position),
     OpponentHit(Position),
-    GameOver(Who),
+    OpponentSalvo(Hits, Hits, u8),
+    TurnClock(Who, u16),
+    TurnExpired(Who, TurnExpiry),
+    GameOver(Who, GameOverReason),
+    SeriesScore(u16, u16),
+    RematchOfferOk,
+    RematchDeclineOk,
+    RematchStarted(Who),
+    OpponentRematchOffer,
+    OpponentRematchDecline,
+    ChallengeReceived(Nickname),
+    ChallengeDeclined(Nickname),
+    ChallengeExpired(Nickname),
+    RatingOk(Nickname, u16, u32),
+    RatingFail,
+    RatingChanged(u16, i16),
+    /// Statistics of the top players and the rank of the player starting from 1, 0 if unranked.
+    Leaderboard(Vec<(Nickname, PlayerStats)>, u32),
+    /// Pairs of players of the running games.
+    GamesList(Vec<(Nickname, Nickname)>),
+    SpectateOk(SpectateState),
+    SpectateFail,
+    SpectateLeaveOk,
+    SpectatorShot(Nickname, Position, ShootResult),
+    SpectatorTurn(Nickname),
+    SpectatorGameOver(Nickname, GameOverReason),
+    SpectatorGameLeft(Nickname),
+    ChatOk,
+    ChatFail,
+    ChatBlocked,
+    ChatRateLimited,
+    ChatReceived(ChatChannel, Nickname, ChatMessage),
+    PlayersList(Vec<(Nickname, PlayerStatus)>),
+    PlayerStatusChanged(Nickname, PlayerStatus),
+    PlayerLoggedOut(Nickname),
+    /// The id of the finished game under which its replay can be retrieved.
+    GameRecorded(usize),
+    /// Lines of the replay of the game.
+    HistoryOk(Vec<String>),
+    HistoryFail,
 }
 
 impl Display for ServerMessage {
@@ -63,31 +186,150 @@ impl Display for ServerMessage {
         match self {
             ServerMessage::IllegalState => write!(f, "[illegal state]"),
             ServerMessage::AliveOk => write!(f, "[alive ok]"),
-            ServerMessage::LoginOk => write!(f, "[login ok]"),
-            ServerMessage::LoginRestored(restore_state) => {
+            ServerMessage::LoginOk(_) => write!(f, "[login ok]"),
+            ServerMessage::LoginRestored(_, restore_state) => {
                 write!(f, "[login restored: {}]", restore_state)
             }
             ServerMessage::LoginFull => write!(f, "[login full]"),
             ServerMessage::LoginTaken => write!(f, "[login taken]"),
-            ServerMessage::JoinGameWait => write!(f, "[join game wait]"),
-            ServerMessage::JoinGameOk(opponent) => write!(f, "[join game ok: {}]", opponent),
+            ServerMessage::LoginUnauthorized => write!(f, "[login unauthorized]"),
+            ServerMessage::ResumeFail => write!(f, "[resume fail]"),
+            ServerMessage::RegisterOk => write!(f, "[register ok]"),
+            ServerMessage::RegisterFail => write!(f, "[register fail]"),
+            ServerMessage::JoinGameWait(position, wait) => {
+                write!(f, "[join game wait: {}, {}]", position, wait)
+            }
+            ServerMessage::JoinGameOk(opponent, rules) => {
+                write!(f, "[join game ok: {}, {}]", opponent, rules)
+            }
+            ServerMessage::RoomCreateOk(code) => write!(f, "[room create ok: {}]", code),
+            ServerMessage::RoomJoinFail => write!(f, "[room join fail]"),
+            ServerMessage::ChallengeOk => write!(f, "[challenge ok]"),
+            ServerMessage::ChallengeFail => write!(f, "[challenge fail]"),
+            ServerMessage::ChallengeDeclineOk => write!(f, "[challenge decline ok]"),
             ServerMessage::LayoutOk => write!(f, "[layout ok]"),
-            ServerMessage::LayoutFail => write!(f, "[layout fail]"),
+            ServerMessage::LayoutFail(error) => write!(f, "[layout fail: {}]", error),
+            ServerMessage::LayoutRandomOk(layout) => write!(f, "[layout random ok: {}]", layout),
+            ServerMessage::LayoutRandomFail => write!(f, "[layout random fail]"),
             ServerMessage::ShootHit => write!(f, "[shoot hit]"),
             ServerMessage::ShootMissed => write!(f, "[shoot missed]"),
-            ServerMessage::ShootSunk(kind, placement) => {
-                write!(f, "[shoot sunk: {}, {}]", kind, placement)
+            ServerMessage::ShootSunk(ship, placement) => {
+                write!(f, "[shoot sunk: {}, {}]", ship, placement)
             }
+            ServerMessage::ShootSalvo(results, shots_left) => write!(
+                f,
+                "[shoot salvo: {{{}}}, {}]",
+                results
+                    .iter()
+                    .map(|(position, result)| format!("{} {}", position, result))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                shots_left
+            ),
             ServerMessage::LeaveGameOk => write!(f, "[leave game ok]"),
             ServerMessage::LogoutOk => write!(f, "[logout ok]"),
             ServerMessage::Disconnect => write!(f, "[disconnect]"),
-            ServerMessage::OpponentJoined(opponent) => write!(f, "[opponent joined: {}]", opponent),
+            ServerMessage::OpponentJoined(opponent, rules) => {
+                write!(f, "[opponent joined: {}, {}]", opponent, rules)
+            }
             ServerMessage::OpponentReady => write!(f, "[opponent ready]"),
             ServerMessage::OpponentOffline => write!(f, "[opponent offline]"),
             ServerMessage::OpponentLeft => write!(f, "[opponent left]"),
             ServerMessage::OpponentMissed(position) => write!(f, "[opponent missed: {}]", position),
             ServerMessage::OpponentHit(position) => write!(f, "[opponent hit: {}]", position),
-            ServerMessage::GameOver(winner) => write!(f, "[game over: {}]", winner),
+            ServerMessage::OpponentSalvo(hits, misses, shots_left) => {
+                write!(f, "[opponent salvo: {}, {}, {}]", hits, misses, shots_left)
+            }
+            ServerMessage::TurnClock(on_turn, seconds) => {
+                write!(f, "[turn clock: {}, {}]", on_turn, seconds)
+            }
+            ServerMessage::TurnExpired(on_turn, expiry) => {
+                write!(f, "[turn expired: {}, {}]", on_turn, expiry)
+            }
+            ServerMessage::GameOver(winner, reason) => {
+                write!(f, "[game over: {}, {}]", winner, reason)
+            }
+            ServerMessage::SeriesScore(wins, opponent_wins) => {
+                write!(f, "[series score: {}, {}]", wins, opponent_wins)
+            }
+            ServerMessage::RematchOfferOk => write!(f, "[rematch offer ok]"),
+            ServerMessage::RematchDeclineOk => write!(f, "[rematch decline ok]"),
+            ServerMessage::RematchStarted(on_turn) => write!(f, "[rematch started: {}]", on_turn),
+            ServerMessage::OpponentRematchOffer => write!(f, "[opponent rematch offer]"),
+            ServerMessage::OpponentRematchDecline => write!(f, "[opponent rematch decline]"),
+            ServerMessage::ChallengeReceived(challenger) => {
+                write!(f, "[challenge received: {}]", challenger)
+            }
+            ServerMessage::ChallengeDeclined(challenged) => {
+                write!(f, "[challenge declined: {}]", challenged)
+            }
+            ServerMessage::ChallengeExpired(nickname) => {
+                write!(f, "[challenge expired: {}]", nickname)
+            }
+            ServerMessage::RatingOk(nickname, rating, games) => {
+                write!(f, "[rating ok: {}, {}, {}]", nickname, rating, games)
+            }
+            ServerMessage::RatingFail => write!(f, "[rating fail]"),
+            ServerMessage::RatingChanged(rating, change) => {
+                write!(f, "[rating changed: {}, {}]", rating, change)
+            }
+            ServerMessage::Leaderboard(players, rank) => write!(
+                f,
+                "[leaderboard: {{{}}}, {}]",
+                players
+                    .iter()
+                    .map(|(nickname, stats)| format!("{} {}", nickname, stats.wins()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                rank
+            ),
+            ServerMessage::GamesList(games) => write!(
+                f,
+                "[games list: {{{}}}]",
+                games
+                    .iter()
+                    .map(|(first, second)| format!("{} vs {}", first, second))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ServerMessage::SpectateOk(state) => write!(f, "[spectate ok: {}]", state),
+            ServerMessage::SpectateFail => write!(f, "[spectate fail]"),
+            ServerMessage::SpectateLeaveOk => write!(f, "[spectate leave ok]"),
+            ServerMessage::SpectatorShot(shooter, position, result) => {
+                write!(f, "[spectator shot: {}, {}, {}]", shooter, position, result)
+            }
+            ServerMessage::SpectatorTurn(on_turn) => write!(f, "[spectator turn: {}]", on_turn),
+            ServerMessage::SpectatorGameOver(winner, reason) => {
+                write!(f, "[spectator game over: {}, {}]", winner, reason)
+            }
+            ServerMessage::SpectatorGameLeft(player) => {
+                write!(f, "[spectator game left: {}]", player)
+            }
+            ServerMessage::ChatOk => write!(f, "[chat ok]"),
+            ServerMessage::ChatFail => write!(f, "[chat fail]"),
+            ServerMessage::ChatBlocked => write!(f, "[chat blocked]"),
+            ServerMessage::ChatRateLimited => write!(f, "[chat rate limited]"),
+            ServerMessage::ChatReceived(channel, sender, message) => {
+                write!(f, "[chat received: {}, {}, {}]", channel, sender, message)
+            }
+            ServerMessage::PlayersList(players) => write!(
+                f,
+                "[players list: {{{}}}]",
+                players
+                    .iter()
+                    .map(|(nickname, status)| format!("{} {}", nickname, status))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ServerMessage::PlayerStatusChanged(nickname, status) => {
+                write!(f, "[player status changed: {}, {}]", nickname, status)
+            }
+            ServerMessage::PlayerLoggedOut(nickname) => {
+                write!(f, "[player logged out: {}]", nickname)
+            }
+            ServerMessage::GameRecorded(game_id) => write!(f, "[game recorded: {:0>16X}]", game_id),
+            ServerMessage::HistoryOk(lines) => write!(f, "[history ok: {} lines]", lines.len()),
+            ServerMessage::HistoryFail => write!(f, "[history fail]"),
         }
     }
 }
@@ -1,10 +1,16 @@
+use crate::game::ShootResult;
+use crate::history::GameEvent;
 use crate::proto::codec::{escape, Payload, ESCAPE, MESSAGE_END, PAYLOAD_START};
 use crate::proto::ServerMessage;
+use crate::stats::PlayerStats;
 use crate::types::{
-    Hits, Layout, Nickname, Orientation, Placement, Position, RestoreState, ShipKind,
-    ShipsPlacements, Who,
+    BoardSize, BoardView, ChatChannel, ChatMessage, Fleet, GameOverReason, Hits, Layout,
+    LayoutError, Nickname, Orientation, Placement, PlayerStatus, Position, RestoreState,
+    ResumeToken, RoomCode, Rules, ShipClass, ShipId, ShipsPlacements, ShotClock, ShotsPerTurn,
+    Spacing, SpectateState, TurnExpiry, TurnRule, Who,
 };
 use std::convert::TryInto;
+use std::time::{Duration, UNIX_EPOCH};
 
 // ---Stream serialize---
 
@@ -69,11 +75,13 @@ impl ServerMessage {
             ServerMessage::AliveOk => {
                 serialized.push_str("alive_ok");
             }
-            ServerMessage::LoginOk => {
+            ServerMessage::LoginOk(token) => {
                 serialized.push_str("login_ok");
+                token.serialize(&mut payload);
             }
-            ServerMessage::LoginRestored(restore_state) => {
+            ServerMessage::LoginRestored(token, restore_state) => {
                 serialized.push_str("login_restored");
+                token.serialize(&mut payload);
                 restore_state.serialize(&mut payload);
             }
             ServerMessage::LoginFull => {
@@ -82,18 +90,57 @@ impl ServerMessage {
             ServerMessage::LoginTaken => {
                 serialized.push_str("login_taken");
             }
-            ServerMessage::JoinGameWait => {
+            ServerMessage::LoginUnauthorized => {
+                serialized.push_str("login_unauthorized");
+            }
+            ServerMessage::ResumeFail => {
+                serialized.push_str("resume_fail");
+            }
+            ServerMessage::RegisterOk => {
+                serialized.push_str("register_ok");
+            }
+            ServerMessage::RegisterFail => {
+                serialized.push_str("register_fail");
+            }
+            ServerMessage::JoinGameWait(position, wait) => {
                 serialized.push_str("join_game_wait");
+                payload.put_int(*position as i32);
+                payload.put_int(*wait as i32);
             }
-            ServerMessage::JoinGameOk(opponent) => {
+            ServerMessage::JoinGameOk(opponent, rules) => {
                 serialized.push_str("join_game_ok");
                 opponent.serialize(&mut payload);
+                rules.serialize(&mut payload);
+            }
+            ServerMessage::RoomCreateOk(code) => {
+                serialized.push_str("room_create_ok");
+                code.serialize(&mut payload);
+            }
+            ServerMessage::RoomJoinFail => {
+                serialized.push_str("room_join_fail");
+            }
+            ServerMessage::ChallengeOk => {
+                serialized.push_str("challenge_ok");
+            }
+            ServerMessage::ChallengeFail => {
+                serialized.push_str("challenge_fail");
+            }
+            ServerMessage::ChallengeDeclineOk => {
+                serialized.push_str("challenge_decline_ok");
             }
             ServerMessage::LayoutOk => {
                 serialized.push_str("layout_ok");
             }
-            ServerMessage::LayoutFail => {
+            ServerMessage::LayoutFail(error) => {
                 serialized.push_str("layout_fail");
+                error.serialize(&mut payload);
+            }
+            ServerMessage::LayoutRandomOk(layout) => {
+                serialized.push_str("layout_random_ok");
+                layout.serialize(&mut payload);
+            }
+            ServerMessage::LayoutRandomFail => {
+                serialized.push_str("layout_random_fail");
             }
             ServerMessage::ShootHit => {
                 serialized.push_str("shoot_hit");
@@ -101,11 +148,22 @@ impl ServerMessage {
             ServerMessage::ShootMissed => {
                 serialized.push_str("shoot_missed");
             }
-            ServerMessage::ShootSunk(kind, placement) => {
+            ServerMessage::ShootSunk(ship, placement) => {
                 serialized.push_str("shoot_sunk");
-                kind.serialize(&mut payload);
+                ship.serialize(&mut payload);
                 placement.serialize(&mut payload);
             }
+            ServerMessage::ShootSalvo(results, shots_left) => {
+                serialized.push_str("shoot_salvo");
+                payload.put_int(results.len().try_into().unwrap());
+
+                for (position, result) in results {
+                    position.serialize(&mut payload);
+                    result.serialize(&mut payload);
+                }
+
+                payload.put_int(*shots_left as i32);
+            }
             ServerMessage::LeaveGameOk => {
                 serialized.push_str("leave_game_ok");
             }
@@ -115,9 +173,10 @@ impl ServerMessage {
             ServerMessage::Disconnect => {
                 serialized.push_str("disconnect");
             }
-            ServerMessage::OpponentJoined(opponent) => {
+            ServerMessage::OpponentJoined(opponent, rules) => {
                 serialized.push_str("opponent_joined");
                 opponent.serialize(&mut payload);
+                rules.serialize(&mut payload);
             }
             ServerMessage::OpponentReady => {
                 serialized.push_str("opponent_ready");
@@ -136,9 +195,172 @@ impl ServerMessage {
                 serialized.push_str("opponent_hit");
                 position.serialize(&mut payload);
             }
-            ServerMessage::GameOver(winner) => {
+            ServerMessage::OpponentSalvo(hits, misses, shots_left) => {
+                serialized.push_str("opponent_salvo");
+                hits.serialize(&mut payload);
+                misses.serialize(&mut payload);
+                payload.put_int(*shots_left as i32);
+            }
+            ServerMessage::TurnClock(on_turn, seconds) => {
+                serialized.push_str("turn_clock");
+                on_turn.serialize(&mut payload);
+                payload.put_int(*seconds as i32);
+            }
+            ServerMessage::TurnExpired(on_turn, expiry) => {
+                serialized.push_str("turn_expired");
+                on_turn.serialize(&mut payload);
+                expiry.serialize(&mut payload);
+            }
+            ServerMessage::GameOver(winner, reason) => {
                 serialized.push_str("game_over");
                 winner.serialize(&mut payload);
+                reason.serialize(&mut payload);
+            }
+            ServerMessage::SeriesScore(wins, opponent_wins) => {
+                serialized.push_str("series_score");
+                payload.put_int(*wins as i32);
+                payload.put_int(*opponent_wins as i32);
+            }
+            ServerMessage::RematchOfferOk => {
+                serialized.push_str("rematch_offer_ok");
+            }
+            ServerMessage::RematchDeclineOk => {
+                serialized.push_str("rematch_decline_ok");
+            }
+            ServerMessage::RematchStarted(on_turn) => {
+                serialized.push_str("rematch_started");
+                on_turn.serialize(&mut payload);
+            }
+            ServerMessage::OpponentRematchOffer => {
+                serialized.push_str("opponent_rematch_offer");
+            }
+            ServerMessage::OpponentRematchDecline => {
+                serialized.push_str("opponent_rematch_decline");
+            }
+            ServerMessage::ChallengeReceived(challenger) => {
+                serialized.push_str("challenge_received");
+                challenger.serialize(&mut payload);
+            }
+            ServerMessage::ChallengeDeclined(challenged) => {
+                serialized.push_str("challenge_declined");
+                challenged.serialize(&mut payload);
+            }
+            ServerMessage::ChallengeExpired(nickname) => {
+                serialized.push_str("challenge_expired");
+                nickname.serialize(&mut payload);
+            }
+            ServerMessage::RatingOk(nickname, rating, games) => {
+                serialized.push_str("rating_ok");
+                nickname.serialize(&mut payload);
+                payload.put_int(*rating as i32);
+                payload.put_int(*games as i32);
+            }
+            ServerMessage::RatingFail => {
+                serialized.push_str("rating_fail");
+            }
+            ServerMessage::RatingChanged(rating, change) => {
+                serialized.push_str("rating_changed");
+                payload.put_int(*rating as i32);
+                payload.put_int(*change as i32);
+            }
+            ServerMessage::Leaderboard(players, rank) => {
+                serialized.push_str("leaderboard");
+                payload.put_int(*rank as i32);
+                payload.put_int(players.len().try_into().unwrap());
+
+                for (nickname, stats) in players {
+                    nickname.serialize(&mut payload);
+                    stats.serialize(&mut payload);
+                }
+            }
+            ServerMessage::GamesList(games) => {
+                serialized.push_str("games_list");
+                payload.put_int(games.len().try_into().unwrap());
+
+                for (first, second) in games {
+                    first.serialize(&mut payload);
+                    second.serialize(&mut payload);
+                }
+            }
+            ServerMessage::SpectateOk(state) => {
+                serialized.push_str("spectate_ok");
+                state.serialize(&mut payload);
+            }
+            ServerMessage::SpectateFail => {
+                serialized.push_str("spectate_fail");
+            }
+            ServerMessage::SpectateLeaveOk => {
+                serialized.push_str("spectate_leave_ok");
+            }
+            ServerMessage::SpectatorShot(shooter, position, result) => {
+                serialized.push_str("spectator_shot");
+                shooter.serialize(&mut payload);
+                position.serialize(&mut payload);
+                result.serialize(&mut payload);
+            }
+            ServerMessage::SpectatorTurn(on_turn) => {
+                serialized.push_str("spectator_turn");
+                on_turn.serialize(&mut payload);
+            }
+            ServerMessage::SpectatorGameOver(winner, reason) => {
+                serialized.push_str("spectator_game_over");
+                winner.serialize(&mut payload);
+                reason.serialize(&mut payload);
+            }
+            ServerMessage::SpectatorGameLeft(player) => {
+                serialized.push_str("spectator_game_left");
+                player.serialize(&mut payload);
+            }
+            ServerMessage::ChatOk => {
+                serialized.push_str("chat_ok");
+            }
+            ServerMessage::ChatFail => {
+                serialized.push_str("chat_fail");
+            }
+            ServerMessage::ChatBlocked => {
+                serialized.push_str("chat_blocked");
+            }
+            ServerMessage::ChatRateLimited => {
+                serialized.push_str("chat_rate_limited");
+            }
+            ServerMessage::ChatReceived(channel, sender, message) => {
+                serialized.push_str("chat_received");
+                channel.serialize(&mut payload);
+                sender.serialize(&mut payload);
+                message.serialize(&mut payload);
+            }
+            ServerMessage::PlayersList(players) => {
+                serialized.push_str("players_list");
+                payload.put_int(players.len().try_into().unwrap());
+
+                for (nickname, status) in players {
+                    nickname.serialize(&mut payload);
+                    status.serialize(&mut payload);
+                }
+            }
+            ServerMessage::PlayerStatusChanged(nickname, status) => {
+                serialized.push_str("player_status_changed");
+                nickname.serialize(&mut payload);
+                status.serialize(&mut payload);
+            }
+            ServerMessage::PlayerLoggedOut(nickname) => {
+                serialized.push_str("player_logged_out");
+                nickname.serialize(&mut payload);
+            }
+            ServerMessage::GameRecorded(game_id) => {
+                serialized.push_str("game_recorded");
+                payload.put_string(format!("{:0>16X}", game_id));
+            }
+            ServerMessage::HistoryOk(lines) => {
+                serialized.push_str("history_ok");
+                payload.put_int(lines.len().try_into().unwrap());
+
+                for line in lines {
+                    payload.put_string(line.clone());
+                }
+            }
+            ServerMessage::HistoryFail => {
+                serialized.push_str("history_fail");
             }
         }
 
@@ -151,30 +373,203 @@ impl ServerMessage {
     }
 }
 
+// ---Replay serialize---
+
+impl GameEvent {
+    /// Serialize the event into a line of a replay,
+    /// the time of the start in seconds since the Unix epoch and other times in milliseconds.
+    pub fn serialize(&self) -> String {
+        let mut payload = Payload::empty();
+
+        match self {
+            GameEvent::Started(time, first_player, second_player, rules, mode) => {
+                payload.put_string(String::from("started"));
+                let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                payload.put_string(since_epoch.as_secs().to_string());
+                first_player.serialize(&mut payload);
+                second_player.serialize(&mut payload);
+                rules.serialize(&mut payload);
+                payload.put_string(mode.to_string());
+            }
+            GameEvent::Layout(time, player, layout) => {
+                payload.put_string(String::from("layout"));
+                time.serialize(&mut payload);
+                player.serialize(&mut payload);
+                layout.serialize(&mut payload);
+            }
+            GameEvent::Shot(time, player, position, result) => {
+                payload.put_string(String::from("shot"));
+                time.serialize(&mut payload);
+                player.serialize(&mut payload);
+                position.serialize(&mut payload);
+                result.serialize(&mut payload);
+            }
+            GameEvent::Pass(time, player) => {
+                payload.put_string(String::from("pass"));
+                time.serialize(&mut payload);
+                player.serialize(&mut payload);
+            }
+            GameEvent::Won(time, winner, reason) => {
+                payload.put_string(String::from("won"));
+                time.serialize(&mut payload);
+                winner.serialize(&mut payload);
+                reason.serialize(&mut payload);
+            }
+            GameEvent::Abandoned(time, player) => {
+                payload.put_string(String::from("abandoned"));
+                time.serialize(&mut payload);
+                player.serialize(&mut payload);
+            }
+        }
+
+        payload.serialize().unwrap_or_default()
+    }
+}
+
 /// A trait for items that can be serialized into a message [Payload](Payload).
 trait SerializeIntoPayload {
     /// Serialize self into a message payload.
     fn serialize(&self, payload: &mut Payload);
 }
 
+impl SerializeIntoPayload for Duration {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.as_millis().to_string())
+    }
+}
+
 impl SerializeIntoPayload for Nickname {
     fn serialize(&self, payload: &mut Payload) {
         payload.put_string(self.get().clone())
     }
 }
 
-impl SerializeIntoPayload for ShipKind {
+impl SerializeIntoPayload for ChatMessage {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.get().clone())
+    }
+}
+
+impl SerializeIntoPayload for ChatChannel {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.to_string())
+    }
+}
+
+impl SerializeIntoPayload for PlayerStatus {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.to_string())
+    }
+}
+
+impl SerializeIntoPayload for RoomCode {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.get().clone())
+    }
+}
+
+impl SerializeIntoPayload for ResumeToken {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.get().clone())
+    }
+}
+
+impl SerializeIntoPayload for ShipId {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.to_string())
+    }
+}
+
+impl SerializeIntoPayload for ShipClass {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.code().to_string());
+        payload.put_string(self.name().clone());
+        payload.put_int(self.length() as i32);
+        payload.put_int(self.count() as i32);
+    }
+}
+
+impl SerializeIntoPayload for Fleet {
+    fn serialize(&self, payload: &mut Payload) {
+        let classes = self.classes();
+        payload.put_int(classes.len().try_into().unwrap());
+
+        for class in classes {
+            class.serialize(payload);
+        }
+    }
+}
+
+impl SerializeIntoPayload for ShotsPerTurn {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.to_string())
+    }
+}
+
+impl SerializeIntoPayload for TurnRule {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.to_string())
+    }
+}
+
+impl SerializeIntoPayload for Spacing {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.to_string())
+    }
+}
+
+impl SerializeIntoPayload for TurnExpiry {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.to_string())
+    }
+}
+
+impl SerializeIntoPayload for Option<ShotClock> {
+    /// Serialize the shot clock as the turn time limit in seconds followed by the expiry action,
+    /// or as a zero time limit if there is no shot clock.
+    fn serialize(&self, payload: &mut Payload) {
+        match self {
+            Some(shot_clock) => {
+                payload.put_int(shot_clock.seconds() as i32);
+                shot_clock.expiry().serialize(payload);
+            }
+            None => payload.put_int(0),
+        }
+    }
+}
+
+impl SerializeIntoPayload for Rules {
+    fn serialize(&self, payload: &mut Payload) {
+        self.board_size().serialize(payload);
+        self.fleet().serialize(payload);
+        self.shots_per_turn().serialize(payload);
+        self.turn_rule().serialize(payload);
+        self.spacing().serialize(payload);
+        self.shot_clock().serialize(payload);
+    }
+}
+
+impl SerializeIntoPayload for ShootResult {
     fn serialize(&self, payload: &mut Payload) {
         match self {
-            ShipKind::AircraftCarrier => payload.put_string(String::from("A")),
-            ShipKind::Battleship => payload.put_string(String::from("B")),
-            ShipKind::Cruiser => payload.put_string(String::from("C")),
-            ShipKind::Destroyer => payload.put_string(String::from("D")),
-            ShipKind::PatrolBoat => payload.put_string(String::from("P")),
+            ShootResult::Missed => payload.put_string(String::from("missed")),
+            ShootResult::Hit => payload.put_string(String::from("hit")),
+            ShootResult::Sunk(ship, placement) => {
+                payload.put_string(String::from("sunk"));
+                ship.serialize(payload);
+                placement.serialize(payload);
+            }
         }
     }
 }
 
+impl SerializeIntoPayload for BoardSize {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_int(self.rows() as i32);
+        payload.put_int(self.cols() as i32);
+    }
+}
+
 impl SerializeIntoPayload for Position {
     fn serialize(&self, payload: &mut Payload) {
         payload.put_int(self.row() as i32);
@@ -202,6 +597,12 @@ impl SerializeIntoPayload for Who {
     }
 }
 
+impl SerializeIntoPayload for GameOverReason {
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_string(self.to_string())
+    }
+}
+
 impl SerializeIntoPayload for Placement {
     fn serialize(&self, payload: &mut Payload) {
         self.position().serialize(payload);
@@ -225,8 +626,8 @@ impl SerializeIntoPayload for ShipsPlacements {
         let ships = self.placements();
         payload.put_int(ships.len().try_into().unwrap());
 
-        for (kind, placement) in ships.iter() {
-            kind.serialize(payload);
+        for (ship, placement) in ships.iter() {
+            ship.serialize(payload);
             placement.serialize(payload);
         }
     }
@@ -240,7 +641,9 @@ impl SerializeIntoPayload for RestoreState {
             }
             RestoreState::Game {
                 opponent,
+                rules,
                 on_turn,
+                shots_left,
                 player_board_hits,
                 player_board_misses,
                 layout,
@@ -250,7 +653,9 @@ impl SerializeIntoPayload for RestoreState {
             } => {
                 payload.put_string(String::from("game"));
                 opponent.serialize(payload);
+                rules.serialize(payload);
                 on_turn.serialize(payload);
+                payload.put_int(*shots_left as i32);
                 player_board_hits.serialize(payload);
                 player_board_misses.serialize(payload);
                 layout.serialize(payload);
@@ -262,8 +667,83 @@ impl SerializeIntoPayload for RestoreState {
     }
 }
 
+impl SerializeIntoPayload for BoardView {
+    fn serialize(&self, payload: &mut Payload) {
+        self.hits().serialize(payload);
+        self.misses().serialize(payload);
+        self.sunk_ships().serialize(payload);
+    }
+}
+
+impl SerializeIntoPayload for SpectateState {
+    fn serialize(&self, payload: &mut Payload) {
+        self.first_player.serialize(payload);
+        self.second_player.serialize(payload);
+        self.rules.serialize(payload);
+        self.on_turn.serialize(payload);
+        self.first_board.serialize(payload);
+        self.second_board.serialize(payload);
+    }
+}
+
 impl SerializeIntoPayload for Layout {
     fn serialize(&self, payload: &mut Payload) {
-        self.placements().serialize(payload);
+        let placements = self.placements();
+        payload.put_int(placements.len().try_into().unwrap());
+
+        for (ship, placement) in placements {
+            ship.serialize(payload);
+            placement.serialize(payload);
+        }
+    }
+}
+
+impl SerializeIntoPayload for PlayerStats {
+    /// Serialize the statistics as games, wins, shots, hits, accuracy in percents,
+    /// average turns to win with one decimal place (0.0 without wins) and the longest hit streak.
+    fn serialize(&self, payload: &mut Payload) {
+        payload.put_int(self.games() as i32);
+        payload.put_int(self.wins() as i32);
+        payload.put_int(self.shots() as i32);
+        payload.put_int(self.hits() as i32);
+        payload.put_int(self.accuracy() as i32);
+        payload.put_string(format!(
+            "{:.1}",
+            self.average_turns_to_win().unwrap_or_default()
+        ));
+        payload.put_int(self.longest_streak() as i32);
+    }
+}
+
+impl SerializeIntoPayload for LayoutError {
+    fn serialize(&self, payload: &mut Payload) {
+        match self {
+            LayoutError::Missing(ship) => {
+                payload.put_string(String::from("missing"));
+                ship.serialize(payload);
+            }
+            LayoutError::Unknown(ship) => {
+                payload.put_string(String::from("unknown"));
+                ship.serialize(payload);
+            }
+            LayoutError::Duplicate(ship) => {
+                payload.put_string(String::from("duplicate"));
+                ship.serialize(payload);
+            }
+            LayoutError::OutOfBoard(ship) => {
+                payload.put_string(String::from("out_of_board"));
+                ship.serialize(payload);
+            }
+            LayoutError::Overlaps(ship, other) => {
+                payload.put_string(String::from("overlaps"));
+                ship.serialize(payload);
+                other.serialize(payload);
+            }
+            LayoutError::Touches(ship, other) => {
+                payload.put_string(String::from("touches"));
+                ship.serialize(payload);
+                other.serialize(payload);
+            }
+        }
     }
 }
@@ -0,0 +1,191 @@
+//! A matchmaking queue of players waiting for an opponent.
+
+use crate::types::QueuePreference;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A player waiting in the queue.
+#[derive(Debug, Clone)]
+struct Entry {
+    player: usize,
+    rating: u16,
+    preference: QueuePreference,
+    joined: Instant,
+}
+
+impl Entry {
+    /// Get the maximal rating difference of an acceptable opponent.
+    /// The range widens with the time spent in the queue.
+    fn rating_range(&self, now: Instant) -> u16 {
+        let waited = now.duration_since(self.joined).as_secs();
+        let range = MatchQueue::BASE_RANGE as u64 + MatchQueue::RANGE_GROWTH as u64 * waited;
+
+        range.min(MatchQueue::MAX_RANGE as u64) as u16
+    }
+
+    /// Check whether the player of this entry accepts the player of the other entry as an opponent.
+    fn accepts(&self, other: &Entry, now: Instant) -> bool {
+        self.preference.compatible(&other.preference)
+            && self.rating.abs_diff(other.rating) <= self.rating_range(now)
+    }
+}
+
+/// A queue of players waiting for an opponent.
+/// Players are matched by their preferences and ratings,
+/// players waiting longer are matched first.
+#[derive(Debug, Default)]
+pub struct MatchQueue {
+    entries: Vec<Entry>,
+    /// Waiting times of the last matched players.
+    waits: VecDeque<Duration>,
+}
+
+impl MatchQueue {
+    /// Rating difference accepted right after joining the queue.
+    pub const BASE_RANGE: u16 = 100;
+    /// Widening of the accepted rating difference per second of waiting.
+    pub const RANGE_GROWTH: u16 = 10;
+    /// Maximal accepted rating difference.
+    pub const MAX_RANGE: u16 = 1000;
+    /// Number of the last waiting times the wait estimation is based on.
+    const WAITS_HISTORY: usize = 20;
+
+    /// Check whether the player is waiting in the queue.
+    pub fn contains(&self, player: &usize) -> bool {
+        self.entries.iter().any(|entry| entry.player == *player)
+    }
+
+    /// Put the player at the end of the queue.
+    pub fn push(&mut self, player: usize, rating: u16, preference: QueuePreference) {
+        self.entries.push(Entry {
+            player,
+            rating,
+            preference,
+            joined: Instant::now(),
+        });
+    }
+
+    /// Remove the player from the queue.
+    /// Returns false if the player was not in the queue.
+    pub fn remove(&mut self, player: &usize) -> bool {
+        match self
+            .entries
+            .iter()
+            .position(|entry| entry.player == *player)
+        {
+            Some(index) => {
+                self.entries.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the position of the player in the queue starting from 1.
+    pub fn position(&self, player: &usize) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.player == *player)
+            .map(|index| index + 1)
+    }
+
+    /// Estimate the remaining waiting time of the player from the waiting times of the last matched players.
+    /// Returns None if the player is not in the queue or if nobody was matched yet.
+    pub fn estimated_wait(&self, player: &usize) -> Option<Duration> {
+        let entry = self.entries.iter().find(|entry| entry.player == *player)?;
+
+        if self.waits.is_empty() {
+            return None;
+        }
+
+        let average = self.waits.iter().sum::<Duration>() / self.waits.len() as u32;
+        let waited = entry.joined.elapsed();
+
+        Some(average.checked_sub(waited).unwrap_or_default())
+    }
+
+    /// Remove all pairs of players who accept each other from the queue.
+    /// Returns the pairs as the player waiting longer, the other player and their merged preference.
+    pub fn take_matches(&mut self) -> Vec<(usize, usize, QueuePreference)> {
+        let now = Instant::now();
+        let mut matches = Vec::new();
+
+        let mut i = 0;
+        while i < self.entries.len() {
+            let found = (i + 1..self.entries.len()).find(|&j| {
+                self.entries[i].accepts(&self.entries[j], now)
+                    && self.entries[j].accepts(&self.entries[i], now)
+            });
+
+            match found {
+                Some(j) => {
+                    let second = self.entries.remove(j);
+                    let first = self.entries.remove(i);
+
+                    self.record_wait(now.duration_since(first.joined));
+                    self.record_wait(now.duration_since(second.joined));
+
+                    matches.push((
+                        first.player,
+                        second.player,
+                        first.preference.merge(&second.preference),
+                    ));
+                }
+                None => i += 1,
+            }
+        }
+
+        matches
+    }
+
+    /// Remember the waiting time of a matched player.
+    fn record_wait(&mut self, wait: Duration) {
+        if self.waits.len() == Self::WAITS_HISTORY {
+            self.waits.pop_front();
+        }
+
+        self.waits.push_back(wait);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::MatchQueue;
+    use crate::types::{BoardSize, GameMode, QueuePreference};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_matching() {
+        let small = QueuePreference::new(GameMode::Casual, Some(BoardSize::new(8, 8).unwrap()));
+        let large = QueuePreference::new(GameMode::Casual, Some(BoardSize::new(12, 12).unwrap()));
+        let rated = QueuePreference::new(GameMode::Rated, None);
+
+        let mut queue = MatchQueue::default();
+        queue.push(1, 1500, small);
+        queue.push(2, 1500, large);
+        queue.push(3, 1900, QueuePreference::default());
+        queue.push(4, 1550, QueuePreference::default());
+        queue.push(5, 1500, rated);
+
+        assert_eq!(queue.position(&3), Some(3));
+        assert_eq!(queue.estimated_wait(&3), None);
+
+        // different modes, board sizes and too distant ratings are not matched
+        let matches = queue.take_matches();
+        assert_eq!(matches, vec![(1, 4, small)]);
+        assert_eq!(queue.position(&2), Some(1));
+        assert_eq!(queue.position(&3), Some(2));
+        assert!(queue.estimated_wait(&3).is_some());
+
+        // the rating range widens with the waiting time
+        for entry in queue.entries.iter_mut() {
+            entry.joined = Instant::now() - Duration::from_secs(60);
+        }
+
+        let matches = queue.take_matches();
+        assert_eq!(matches, vec![(2, 3, large)]);
+        assert!(!queue.contains(&2));
+        assert!(!queue.contains(&3));
+        assert_eq!(queue.position(&5), Some(1));
+    }
+}
@@ -0,0 +1,153 @@
+//! Elo ratings of players persisted in a file.
+
+use crate::store::{PlayerRecord, PlayerRecords};
+use std::io;
+use std::path::PathBuf;
+
+/// An Elo rating of a player.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rating {
+    value: u16,
+    games: u32,
+}
+
+impl Rating {
+    /// Rating of players who have not played any rated game yet.
+    pub const INITIAL: u16 = 1500;
+    /// Maximal rating change in one game.
+    const K_FACTOR: f64 = 32.0;
+
+    pub fn value(&self) -> u16 {
+        self.value
+    }
+
+    /// Get the number of rated games played.
+    pub fn games(&self) -> u32 {
+        self.games
+    }
+
+    /// Get the expected score of a game against the opponent, between 0 and 1.
+    fn expected_score(&self, opponent: &Rating) -> f64 {
+        let difference = opponent.value as f64 - self.value as f64;
+        1.0 / (1.0 + 10f64.powf(difference / 400.0))
+    }
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            value: Rating::INITIAL,
+            games: 0,
+        }
+    }
+}
+
+impl PlayerRecord for Rating {
+    fn parse(fields: &[&str]) -> Option<Self> {
+        match fields {
+            [value, games] => Some(Rating {
+                value: value.parse().ok()?,
+                games: games.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![self.value.to_string(), self.games.to_string()]
+    }
+}
+
+/// Ratings of players indexed by nicknames.
+///
+/// If the ratings have a file, they are stored in it as lines of `<nickname>;<rating>;<games>`.
+#[derive(Debug, Default)]
+pub struct Ratings {
+    ratings: PlayerRecords<Rating>,
+}
+
+impl Ratings {
+    /// Load the ratings from the file.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        Ok(Ratings {
+            ratings: PlayerRecords::load(path)?,
+        })
+    }
+
+    /// Save the ratings.
+    pub fn save(&self) -> io::Result<()> {
+        self.ratings.save()
+    }
+
+    /// Get the rating of the player, players without any rated game have the initial rating.
+    pub fn get(&self, nickname: &str) -> Rating {
+        self.ratings.get(nickname).cloned().unwrap_or_default()
+    }
+
+    /// Check whether the player has played any rated game.
+    pub fn contains(&self, nickname: &str) -> bool {
+        self.ratings.contains(nickname)
+    }
+
+    /// Update the ratings of the players after a rated game.
+    /// Returns the rating changes of the winner and of the loser.
+    pub fn record(&mut self, winner: &str, loser: &str) -> (i16, i16) {
+        let winner_rating = self.get(winner);
+        let loser_rating = self.get(loser);
+
+        let change =
+            (Rating::K_FACTOR * (1.0 - winner_rating.expected_score(&loser_rating))).round() as u16;
+
+        let new_winner_rating = Rating {
+            value: winner_rating.value.saturating_add(change),
+            games: winner_rating.games + 1,
+        };
+        let new_loser_rating = Rating {
+            value: loser_rating.value.saturating_sub(change),
+            games: loser_rating.games + 1,
+        };
+
+        self.ratings.insert(winner, new_winner_rating);
+        self.ratings.insert(loser, new_loser_rating);
+
+        (
+            new_winner_rating.value as i16 - winner_rating.value as i16,
+            new_loser_rating.value as i16 - loser_rating.value as i16,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rating::{Rating, Ratings};
+    use crate::store::PlayerRecord;
+
+    #[test]
+    fn test_record() {
+        let mut ratings = Ratings::default();
+
+        assert_eq!(ratings.record("alice", "bob"), (16, -16));
+        assert_eq!(ratings.get("alice").value(), Rating::INITIAL + 16);
+        assert_eq!(ratings.get("bob").value(), Rating::INITIAL - 16);
+        assert_eq!(ratings.get("bob").games(), 1);
+
+        // beating a weaker player gains less
+        let (gain, loss) = ratings.record("alice", "bob");
+        assert!(gain < 16);
+        assert_eq!(gain, -loss);
+
+        assert!(!ratings.contains("carol"));
+        assert_eq!(ratings.get("carol"), Rating::default());
+    }
+
+    #[test]
+    fn test_parse() {
+        let rating = Rating::parse(&["1516", "3"]).unwrap();
+        assert_eq!(rating.value(), 1516);
+        assert_eq!(rating.games(), 3);
+        assert_eq!(rating.fields(), vec!["1516", "3"]);
+
+        assert!(Rating::parse(&["1516"]).is_none());
+        assert!(Rating::parse(&["abc", "3"]).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! Spectators of running games.
+
+use std::collections::HashMap;
+
+/// Players spectating running games, every player spectates at most one game.
+#[derive(Debug, Default)]
+pub struct Spectators {
+    /// Spectator ids indexed by game ids.
+    games_spectators: HashMap<usize, Vec<usize>>,
+    /// Game ids indexed by spectator ids.
+    spectators_games: HashMap<usize, usize>,
+}
+
+impl Spectators {
+    /// Get the number of spectating players.
+    pub fn len(&self) -> usize {
+        self.spectators_games.len()
+    }
+
+    /// Check whether there are no spectating players.
+    pub fn is_empty(&self) -> bool {
+        self.spectators_games.is_empty()
+    }
+
+    /// Get the game spectated by the player.
+    pub fn game(&self, spectator: &usize) -> Option<usize> {
+        self.spectators_games.get(spectator).cloned()
+    }
+
+    /// Get the spectators of the game.
+    pub fn of_game(&self, game: &usize) -> &[usize] {
+        self.games_spectators
+            .get(game)
+            .map_or(&[], |spectators| spectators.as_slice())
+    }
+
+    /// Let the player spectate the game, the previously spectated game is left.
+    pub fn add(&mut self, spectator: usize, game: usize) {
+        self.remove(&spectator);
+
+        self.spectators_games.insert(spectator, game);
+        self.games_spectators
+            .entry(game)
+            .or_default()
+            .push(spectator);
+    }
+
+    /// Stop the player spectating.
+    /// Returns the game the player was spectating.
+    pub fn remove(&mut self, spectator: &usize) -> Option<usize> {
+        let game = self.spectators_games.remove(spectator)?;
+
+        if let Some(spectators) = self.games_spectators.get_mut(&game) {
+            spectators.retain(|id| id != spectator);
+
+            if spectators.is_empty() {
+                self.games_spectators.remove(&game);
+            }
+        }
+
+        Some(game)
+    }
+
+    /// Stop all spectators of the game spectating.
+    /// Returns the spectators of the game.
+    pub fn remove_game(&mut self, game: &usize) -> Vec<usize> {
+        let spectators = self.games_spectators.remove(game).unwrap_or_default();
+
+        for spectator in &spectators {
+            self.spectators_games.remove(spectator);
+        }
+
+        spectators
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spectators::Spectators;
+
+    #[test]
+    fn test_spectators() {
+        let mut spectators = Spectators::default();
+        spectators.add(1, 10);
+        spectators.add(2, 10);
+        spectators.add(3, 20);
+
+        assert_eq!(spectators.len(), 3);
+        assert_eq!(spectators.of_game(&10), &[1, 2]);
+
+        // switching to another game
+        spectators.add(2, 20);
+        assert_eq!(spectators.of_game(&10), &[1]);
+        assert_eq!(spectators.game(&2), Some(20));
+
+        assert_eq!(spectators.remove(&1), Some(10));
+        assert_eq!(spectators.remove(&1), None);
+        assert!(spectators.of_game(&10).is_empty());
+
+        assert_eq!(spectators.remove_game(&20), vec![3, 2]);
+        assert!(spectators.is_empty());
+    }
+}
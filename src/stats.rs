@@ -0,0 +1,216 @@
+//! Statistics of players persisted in a file.
+
+use crate::store::{PlayerRecord, PlayerRecords};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+/// Statistics of one player.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct PlayerStats {
+    games: u32,
+    wins: u32,
+    shots: u32,
+    hits: u32,
+    /// Sum of the turns of all won games.
+    winning_turns: u32,
+    longest_streak: u32,
+}
+
+impl PlayerStats {
+    /// Get the number of finished games.
+    pub fn games(&self) -> u32 {
+        self.games
+    }
+
+    /// Get the number of won games.
+    pub fn wins(&self) -> u32 {
+        self.wins
+    }
+
+    /// Get the number of fired shots.
+    pub fn shots(&self) -> u32 {
+        self.shots
+    }
+
+    /// Get the number of shots which hit a ship.
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// Get the percentage of shots which hit a ship.
+    pub fn accuracy(&self) -> u8 {
+        if self.shots == 0 {
+            0
+        } else {
+            (self.hits as u64 * 100 / self.shots as u64) as u8
+        }
+    }
+
+    /// Get the average number of turns the player needed to win a game.
+    pub fn average_turns_to_win(&self) -> Option<f64> {
+        if self.wins == 0 {
+            None
+        } else {
+            Some(self.winning_turns as f64 / self.wins as f64)
+        }
+    }
+
+    /// Get the longest series of consecutive hits.
+    pub fn longest_streak(&self) -> u32 {
+        self.longest_streak
+    }
+}
+
+impl PlayerRecord for PlayerStats {
+    fn parse(fields: &[&str]) -> Option<Self> {
+        match fields {
+            [games, wins, shots, hits, winning_turns, longest_streak] => Some(PlayerStats {
+                games: games.parse().ok()?,
+                wins: wins.parse().ok()?,
+                shots: shots.parse().ok()?,
+                hits: hits.parse().ok()?,
+                winning_turns: winning_turns.parse().ok()?,
+                longest_streak: longest_streak.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+
+    fn fields(&self) -> Vec<String> {
+        [
+            self.games,
+            self.wins,
+            self.shots,
+            self.hits,
+            self.winning_turns,
+            self.longest_streak,
+        ]
+        .iter()
+        .map(u32::to_string)
+        .collect()
+    }
+}
+
+/// Statistics of players indexed by nicknames.
+///
+/// If the statistics have a file, they are stored in it as lines of
+/// `<nickname>;<games>;<wins>;<shots>;<hits>;<winning turns>;<longest streak>`.
+#[derive(Debug, Default)]
+pub struct Stats {
+    players: PlayerRecords<PlayerStats>,
+    /// Current series of consecutive hits of players in their games.
+    streaks: HashMap<String, u32>,
+}
+
+impl Stats {
+    /// Load the statistics from the file.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        Ok(Stats {
+            players: PlayerRecords::load(path)?,
+            streaks: HashMap::new(),
+        })
+    }
+
+    /// Save the statistics.
+    pub fn save(&self) -> io::Result<()> {
+        self.players.save()
+    }
+
+    /// Get the statistics of the player.
+    pub fn get(&self, nickname: &str) -> PlayerStats {
+        self.players.get(nickname).cloned().unwrap_or_default()
+    }
+
+    /// Record a shot fired by the player.
+    pub fn record_shot(&mut self, nickname: &str, hit: bool) {
+        let stats = self.players.get_mut(nickname);
+        let streak = self.streaks.entry(String::from(nickname)).or_default();
+
+        stats.shots += 1;
+
+        if hit {
+            stats.hits += 1;
+            *streak += 1;
+            stats.longest_streak = stats.longest_streak.max(*streak);
+        } else {
+            *streak = 0;
+        }
+    }
+
+    /// Record a finished game of the player, won in the given number of turns.
+    pub fn record_game(&mut self, nickname: &str, won: bool, turns: u32) {
+        let stats = self.players.get_mut(nickname);
+
+        stats.games += 1;
+
+        if won {
+            stats.wins += 1;
+            stats.winning_turns += turns;
+        }
+
+        self.streaks.remove(nickname);
+    }
+
+    /// Get the players ordered by the number of won games,
+    /// players with the same number of wins are ordered by the number of played games.
+    pub fn leaderboard(&self) -> Vec<(&String, &PlayerStats)> {
+        let mut leaderboard = self
+            .players
+            .iter()
+            .filter(|(_, stats)| stats.games > 0)
+            .collect::<Vec<_>>();
+
+        leaderboard.sort_by(|(a_nickname, a), (b_nickname, b)| {
+            b.wins
+                .cmp(&a.wins)
+                .then(a.games.cmp(&b.games))
+                .then(a_nickname.cmp(b_nickname))
+        });
+
+        leaderboard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{PlayerStats, Stats};
+    use crate::store::PlayerRecord;
+
+    #[test]
+    fn test_stats() {
+        let mut stats = Stats::default();
+
+        for &hit in &[true, true, false, true, true, true, false] {
+            stats.record_shot("alice", hit);
+        }
+        stats.record_shot("bob", false);
+        stats.record_game("alice", true, 10);
+        stats.record_game("bob", false, 9);
+
+        let alice = stats.get("alice");
+        assert_eq!(alice.games(), 1);
+        assert_eq!(alice.wins(), 1);
+        assert_eq!(alice.shots(), 7);
+        assert_eq!(alice.hits(), 5);
+        assert_eq!(alice.accuracy(), 71);
+        assert_eq!(alice.longest_streak(), 3);
+        assert_eq!(alice.average_turns_to_win(), Some(10.0));
+        assert_eq!(stats.get("bob").average_turns_to_win(), None);
+
+        let leaderboard = stats.leaderboard();
+        assert_eq!(leaderboard.len(), 2);
+        assert_eq!(leaderboard[0].0, "alice");
+        assert_eq!(leaderboard[1].0, "bob");
+    }
+
+    #[test]
+    fn test_parse() {
+        let stats = PlayerStats::parse(&["4", "3", "120", "40", "75", "6"]).unwrap();
+        assert_eq!(stats.games(), 4);
+        assert_eq!(stats.average_turns_to_win(), Some(25.0));
+        assert_eq!(stats.fields(), vec!["4", "3", "120", "40", "75", "6"]);
+
+        assert!(PlayerStats::parse(&["4", "3", "120", "40", "75"]).is_none());
+    }
+}
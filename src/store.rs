@@ -0,0 +1,211 @@
+//! Helpers for the files of the persistent data.
+
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A record of one player stored in a [PlayerRecords] file.
+pub trait PlayerRecord: Sized {
+    /// Parse the record from the fields of its line following the nickname.
+    fn parse(fields: &[&str]) -> Option<Self>;
+
+    /// Format the fields of the record following the nickname.
+    fn fields(&self) -> Vec<String>;
+}
+
+/// Records of players indexed by nicknames.
+///
+/// If the records have a file, they are stored in it as lines of
+/// `<nickname>;<fields of the record>`.
+#[derive(Debug)]
+pub struct PlayerRecords<T> {
+    path: Option<PathBuf>,
+    records: HashMap<String, T>,
+}
+
+impl<T> Default for PlayerRecords<T> {
+    fn default() -> Self {
+        PlayerRecords {
+            path: None,
+            records: HashMap::new(),
+        }
+    }
+}
+
+impl<T: PlayerRecord> PlayerRecords<T> {
+    /// Load the records from the file, which is created later if it does not exist.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let mut records = HashMap::new();
+
+        for line in read_lines(&path)? {
+            let (nickname, record) =
+                Self::parse_line(&line).ok_or_else(|| invalid_record(&line))?;
+
+            records.insert(nickname, record);
+        }
+
+        Ok(PlayerRecords {
+            path: Some(path),
+            records,
+        })
+    }
+
+    /// Parse one line of the records file.
+    fn parse_line(line: &str) -> Option<(String, T)> {
+        let parts = line.split(';').collect::<Vec<_>>();
+        let (nickname, fields) = parts.split_first()?;
+
+        Some((String::from(*nickname), T::parse(fields)?))
+    }
+
+    /// Save the records into their file if they have any.
+    pub fn save(&self) -> io::Result<()> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        let lines = self.records.iter().map(|(nickname, record)| {
+            let mut fields = vec![nickname.clone()];
+            fields.extend(record.fields());
+            fields.join(";")
+        });
+
+        write_lines(path, lines)
+    }
+
+    /// Get the record of the player.
+    pub fn get(&self, nickname: &str) -> Option<&T> {
+        self.records.get(nickname)
+    }
+
+    /// Get the record of the player to be changed, a missing record is created by default.
+    pub fn get_mut(&mut self, nickname: &str) -> &mut T
+    where
+        T: Default,
+    {
+        self.records.entry(String::from(nickname)).or_default()
+    }
+
+    /// Check whether the player has a record.
+    pub fn contains(&self, nickname: &str) -> bool {
+        self.records.contains_key(nickname)
+    }
+
+    /// Set the record of the player.
+    pub fn insert(&mut self, nickname: &str, record: T) {
+        self.records.insert(String::from(nickname), record);
+    }
+
+    /// Get all records with the nicknames of their players.
+    pub fn iter(&self) -> hash_map::Iter<'_, String, T> {
+        self.records.iter()
+    }
+}
+
+/// Read the non-empty lines of the data file, a missing file has no lines.
+pub fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Replace the content of the data file by a temporary file synced to the disk before the rename,
+/// so after a crash the file has either the old or the new content.
+pub fn write_lines(path: &Path, lines: impl Iterator<Item = String>) -> io::Result<()> {
+    let mut content = String::new();
+
+    for line in lines {
+        content.push_str(&line);
+        content.push('\n');
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+
+    fs::rename(&tmp_path, path)?;
+    sync_dir(path)
+}
+
+/// Sync the directory of the data file, so the creation or the rename of the file
+/// is on the disk once this returns.
+pub fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+/// Create an error of an invalid record in the data file.
+pub fn invalid_record(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid record: {}", line),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{PlayerRecord, PlayerRecords};
+    use std::fs;
+
+    #[derive(Debug, Default, Eq, PartialEq)]
+    struct Score(u32);
+
+    impl PlayerRecord for Score {
+        fn parse(fields: &[&str]) -> Option<Self> {
+            match fields {
+                [score] => Some(Score(score.parse().ok()?)),
+                _ => None,
+            }
+        }
+
+        fn fields(&self) -> Vec<String> {
+            vec![self.0.to_string()]
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            PlayerRecords::<Score>::parse_line("alice;12"),
+            Some((String::from("alice"), Score(12)))
+        );
+        assert!(PlayerRecords::<Score>::parse_line("alice").is_none());
+        assert!(PlayerRecords::<Score>::parse_line("alice;12;3").is_none());
+        assert!(PlayerRecords::<Score>::parse_line("alice;abc").is_none());
+    }
+
+    #[test]
+    fn test_save_load() {
+        let dir = std::env::temp_dir().join(format!("bssrv-store-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scores");
+
+        let mut records = PlayerRecords::<Score>::load(path.clone()).unwrap();
+        assert!(!records.contains("alice"));
+
+        records.insert("alice", Score(3));
+        records.get_mut("bob").0 += 1;
+        records.save().unwrap();
+
+        let records = PlayerRecords::<Score>::load(path).unwrap();
+        assert_eq!(records.get("alice"), Some(&Score(3)));
+        assert_eq!(records.get("bob"), Some(&Score(1)));
+        assert_eq!(records.iter().count(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
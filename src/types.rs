@@ -1,7 +1,12 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum DomainErrorKind {
@@ -82,43 +87,1277 @@ impl Display for Nickname {
     }
 }
 
-// ---ShipKind---
+// ---RoomCode---
 
+/// A code of a private game room.
+/// Forces string to have 6 characters from the room code alphabet.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RoomCode {
+    code: String,
+}
+
+impl RoomCode {
+    /// Length of a room code.
+    pub const LENGTH: usize = 6;
+    /// Characters of room codes, without the ones easily confused with each other.
+    const ALPHABET: &'static [u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+    pub fn new(code: String) -> Result<Self, DomainError> {
+        let len = code.chars().count();
+        if len != Self::LENGTH {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidLength,
+                format!(
+                    "Room code must have {} characters, but has {}.",
+                    Self::LENGTH,
+                    len
+                ),
+            ));
+        }
+
+        if !code.bytes().all(|c| Self::ALPHABET.contains(&c)) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                String::from("Room code must contain only uppercase letters and digits 2 - 9."),
+            ));
+        }
+
+        Ok(RoomCode { code })
+    }
+
+    /// Generate a random room code.
+    pub fn random() -> Self {
+        let mut rng = rand::thread_rng();
+        let code = (0..Self::LENGTH)
+            .map(|_| *Self::ALPHABET.choose(&mut rng).unwrap() as char)
+            .collect();
+
+        RoomCode { code }
+    }
+
+    pub fn get(&self) -> &String {
+        &self.code
+    }
+}
+
+impl Display for RoomCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.code)
+    }
+}
+
+// ---ResumeToken---
+
+/// An opaque token by which a player restores an offline session.
+/// Forces string to have 32 lowercase hexadecimal digits.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ResumeToken {
+    token: String,
+}
+
+impl ResumeToken {
+    /// Length of a resume token.
+    pub const LENGTH: usize = 32;
+    /// Characters of resume tokens.
+    const ALPHABET: &'static [u8] = b"0123456789abcdef";
+
+    pub fn new(token: String) -> Result<Self, DomainError> {
+        let len = token.chars().count();
+        if len != Self::LENGTH {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidLength,
+                format!(
+                    "Resume token must have {} characters, but has {}.",
+                    Self::LENGTH,
+                    len
+                ),
+            ));
+        }
+
+        if !token.bytes().all(|c| Self::ALPHABET.contains(&c)) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                String::from("Resume token must contain only lowercase hexadecimal digits."),
+            ));
+        }
+
+        Ok(ResumeToken { token })
+    }
+
+    /// Generate a random resume token.
+    pub fn random() -> Self {
+        let mut rng = rand::thread_rng();
+        let token = (0..Self::LENGTH)
+            .map(|_| *Self::ALPHABET.choose(&mut rng).unwrap() as char)
+            .collect();
+
+        ResumeToken { token }
+    }
+
+    pub fn get(&self) -> &String {
+        &self.token
+    }
+
+    /// Get the hash of the token, by which the token is looked up and persisted.
+    pub fn hash(&self) -> TokenHash {
+        let hash = Sha256::digest(self.token.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        TokenHash { hash }
+    }
+}
+
+impl Display for ResumeToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.token)
+    }
+}
+
+// ---TokenHash---
+
+/// A SHA-256 hash of a resume token, so the token itself is never persisted.
+/// Forces string to have 64 lowercase hexadecimal digits.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TokenHash {
+    hash: String,
+}
+
+impl TokenHash {
+    /// Length of a token hash.
+    pub const LENGTH: usize = 64;
+
+    pub fn new(hash: String) -> Result<Self, DomainError> {
+        let len = hash.chars().count();
+        if len != Self::LENGTH {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidLength,
+                format!(
+                    "Token hash must have {} characters, but has {}.",
+                    Self::LENGTH,
+                    len
+                ),
+            ));
+        }
+
+        if !hash.bytes().all(|c| ResumeToken::ALPHABET.contains(&c)) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                String::from("Token hash must contain only lowercase hexadecimal digits."),
+            ));
+        }
+
+        Ok(TokenHash { hash })
+    }
+
+    pub fn get(&self) -> &String {
+        &self.hash
+    }
+}
+
+impl Display for TokenHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.hash)
+    }
+}
+
+// ---ChatMessage---
+
+/// A text of a chat message.
+/// Forces string to have 1 - 200 characters without control characters.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ChatMessage {
+    text: String,
+}
+
+impl ChatMessage {
+    /// Maximal length of a chat message.
+    pub const MAX_LENGTH: usize = 200;
+
+    pub fn new(text: String) -> Result<Self, DomainError> {
+        let len = text.chars().count();
+        if !(1..=Self::MAX_LENGTH).contains(&len) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidLength,
+                format!(
+                    "Chat message must have 1 - {} characters, but has {}.",
+                    Self::MAX_LENGTH,
+                    len
+                ),
+            ));
+        }
+
+        if text.chars().any(|c| c.is_control()) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                String::from("Chat message must not contain control characters."),
+            ));
+        }
+
+        Ok(ChatMessage { text })
+    }
+
+    pub fn get(&self) -> &String {
+        &self.text
+    }
+}
+
+impl Display for ChatMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.text)
+    }
+}
+
+// ---Password---
+
+/// A password of a registered player.
+/// Forces string to have 6 - 64 characters without control characters.
+/// The password is never displayed.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Password {
+    password: String,
+}
+
+impl Password {
+    pub fn new(password: String) -> Result<Self, DomainError> {
+        let len = password.chars().count();
+        if !(6..=64).contains(&len) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidLength,
+                format!("Password must have 6 - 64 characters, but has {}.", len),
+            ));
+        }
+
+        if password.chars().any(|c| c.is_control()) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                String::from("Password must not contain control characters."),
+            ));
+        }
+
+        Ok(Password { password })
+    }
+
+    pub fn get(&self) -> &String {
+        &self.password
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "Password(********)")
+    }
+}
+
+impl Display for Password {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "********")
+    }
+}
+
+// ---ChatChannel---
+
+/// Recipients of a chat message - the opponent in the game or all players in the lobby.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ChatChannel {
+    Opponent,
+    Lobby,
+}
+
+impl FromStr for ChatChannel {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "opponent" => Ok(ChatChannel::Opponent),
+            "lobby" => Ok(ChatChannel::Lobby),
+            _ => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!("Chat channel must be opponent or lobby, but is {}.", s),
+            )),
+        }
+    }
+}
+
+impl Display for ChatChannel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ChatChannel::Opponent => write!(f, "opponent"),
+            ChatChannel::Lobby => write!(f, "lobby"),
+        }
+    }
+}
+
+// ---PlayerStatus---
+
+/// What a logged player is doing, as shown in the lobby.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PlayerStatus {
+    /// Online and not in any game.
+    Lobby,
+    /// Online and in a game.
+    Game,
+    /// Disconnected, but the session can be still restored.
+    Offline,
+}
+
+impl Display for PlayerStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            PlayerStatus::Lobby => write!(f, "lobby"),
+            PlayerStatus::Game => write!(f, "game"),
+            PlayerStatus::Offline => write!(f, "offline"),
+        }
+    }
+}
+
+// ---ShipId---
+
+/// An identifier of a ship in a fleet.
+/// Consists of the code of the ships class and of the index of the ship in the class.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub enum ShipKind {
-    AircraftCarrier,
-    Battleship,
-    Cruiser,
-    Destroyer,
-    PatrolBoat,
+pub struct ShipId {
+    code: char,
+    index: u8,
+}
+
+impl ShipId {
+    pub fn new(code: char, index: u8) -> Result<Self, DomainError> {
+        if !code.is_ascii_uppercase() {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!(
+                    "Ship class code must be an uppercase letter, but is {}.",
+                    code
+                ),
+            ));
+        }
+
+        if index >= Fleet::MAX_SHIPS {
+            return Err(DomainError::new(
+                DomainErrorKind::OutOfRange,
+                format!(
+                    "Ship index must be between 0 - {}. {} given.",
+                    Fleet::MAX_SHIPS - 1,
+                    index
+                ),
+            ));
+        }
+
+        Ok(ShipId { code, index })
+    }
+
+    pub fn code(&self) -> char {
+        self.code
+    }
+
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+}
+
+impl FromStr for ShipId {
+    type Err = DomainError;
+
+    /// Parse the ship id from the `<code>[index]` format, e.g. `A` or `D1`.
+    /// The index is omitted for the first ship of the class.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+
+        let code = match chars.next() {
+            Some(code) => code,
+            None => {
+                return Err(DomainError::new(
+                    DomainErrorKind::InvalidLength,
+                    String::from("Ship id must not be empty."),
+                ))
+            }
+        };
+
+        let index = chars.as_str();
+
+        if index.is_empty() {
+            return ShipId::new(code, 0);
+        }
+
+        match index.parse::<u8>() {
+            Ok(index) => ShipId::new(code, index),
+            Err(_) => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!("Ship index must be a number, but is {}.", index),
+            )),
+        }
+    }
+}
+
+impl Display for ShipId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        if self.index == 0 {
+            write!(f, "{}", self.code)
+        } else {
+            write!(f, "{}{}", self.code, self.index)
+        }
+    }
+}
+
+// ---ShipClass---
+
+/// A class of ships in a fleet, e.g. destroyers.
+/// Defines the ships name, length and how many of them are in the fleet.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ShipClass {
+    code: char,
+    name: String,
+    length: u8,
+    count: u8,
+}
+
+impl ShipClass {
+    pub fn new(code: char, name: String, length: u8, count: u8) -> Result<Self, DomainError> {
+        // validates the code
+        ShipId::new(code, 0)?;
+
+        let len = name.chars().count();
+        if !(1..=32).contains(&len) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidLength,
+                format!(
+                    "Ship class name must have 1 - 32 characters, but has {}.",
+                    len
+                ),
+            ));
+        }
+
+        if !name.chars().all(|c| c.is_alphanumeric()) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                String::from("Ship class name must contain only alphanumeric characters."),
+            ));
+        }
+
+        if !(1..=BoardSize::MAX).contains(&length) {
+            return Err(DomainError::new(
+                DomainErrorKind::OutOfRange,
+                format!(
+                    "Ship length must be between 1 - {}. {} given.",
+                    BoardSize::MAX,
+                    length
+                ),
+            ));
+        }
+
+        if !(1..=Fleet::MAX_SHIPS).contains(&count) {
+            return Err(DomainError::new(
+                DomainErrorKind::OutOfRange,
+                format!(
+                    "Ship class count must be between 1 - {}. {} given.",
+                    Fleet::MAX_SHIPS,
+                    count
+                ),
+            ));
+        }
+
+        Ok(ShipClass {
+            code,
+            name,
+            length,
+            count,
+        })
+    }
+
+    pub fn code(&self) -> char {
+        self.code
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn length(&self) -> u8 {
+        self.length
+    }
+
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+
+    /// Get ids of all ships of this class.
+    pub fn ships(&self) -> Vec<ShipId> {
+        (0..self.count)
+            .map(|index| ShipId::new(self.code, index).unwrap())
+            .collect()
+    }
+}
+
+impl Display for ShipClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.code, self.name, self.length, self.count
+        )
+    }
+}
+
+// ---Fleet---
+
+/// A fleet composition - classes of ships each player places on his board.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Fleet {
+    classes: Vec<ShipClass>,
+}
+
+impl Fleet {
+    /// Maximal number of ships in the fleet.
+    pub const MAX_SHIPS: u8 = 32;
+
+    pub fn new(classes: Vec<ShipClass>) -> Result<Self, DomainError> {
+        if classes.is_empty() {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidLength,
+                String::from("Fleet must have at least one ship class."),
+            ));
+        }
+
+        let ships: usize = classes.iter().map(|class| class.count() as usize).sum();
+        if ships > Self::MAX_SHIPS as usize {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidLength,
+                format!(
+                    "Fleet must have at most {} ships, but has {}.",
+                    Self::MAX_SHIPS,
+                    ships
+                ),
+            ));
+        }
+
+        for (i, class) in classes.iter().enumerate() {
+            if classes[..i].iter().any(|c| c.code() == class.code()) {
+                return Err(DomainError::new(
+                    DomainErrorKind::InvalidCombination,
+                    format!("Ship class code {} is used more than once.", class.code()),
+                ));
+            }
+        }
+
+        Ok(Fleet { classes })
+    }
+
+    pub fn classes(&self) -> &Vec<ShipClass> {
+        &self.classes
+    }
+
+    /// Get the class of the ship with the given id if it is part of the fleet.
+    pub fn class(&self, ship: &ShipId) -> Option<&ShipClass> {
+        self.classes
+            .iter()
+            .find(|class| class.code() == ship.code() && ship.index() < class.count())
+    }
+
+    /// Get the length of the ship with the given id if it is part of the fleet.
+    pub fn ship_length(&self, ship: &ShipId) -> Option<u8> {
+        self.class(ship).map(|class| class.length())
+    }
+
+    /// Get ids of all ships in the fleet.
+    pub fn ships(&self) -> Vec<ShipId> {
+        self.classes
+            .iter()
+            .flat_map(|class| class.ships())
+            .collect()
+    }
+
+    /// Check whether the longest ship of the fleet can be placed on the board.
+    pub fn fits(&self, board_size: &BoardSize) -> bool {
+        let max = board_size.rows().max(board_size.cols());
+        self.classes.iter().all(|class| class.length() <= max)
+    }
+}
+
+impl Default for Fleet {
+    /// The classic fleet of five ships.
+    fn default() -> Self {
+        Fleet {
+            classes: vec![
+                ShipClass::new('A', String::from("AircraftCarrier"), 5, 1).unwrap(),
+                ShipClass::new('B', String::from("Battleship"), 4, 1).unwrap(),
+                ShipClass::new('C', String::from("Cruiser"), 3, 1).unwrap(),
+                ShipClass::new('D', String::from("Destroyer"), 2, 1).unwrap(),
+                ShipClass::new('P', String::from("PatrolBoat"), 1, 1).unwrap(),
+            ],
+        }
+    }
+}
+
+impl FromStr for Fleet {
+    type Err = DomainError;
+
+    /// Parse the fleet from comma separated ship classes
+    /// in the `<code>:<name>:<length>:<count>` format, e.g. `D:Destroyer:2:2,P:PatrolBoat:1:4`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut classes = Vec::new();
+
+        for class in s.split(',') {
+            let parts = class.split(':').collect::<Vec<_>>();
+
+            if parts.len() != 4 || parts[0].chars().count() != 1 {
+                return Err(DomainError::new(
+                    DomainErrorKind::InvalidCombination,
+                    format!(
+                        "Ship class must be in format <code>:<name>:<length>:<count>, but is {}.",
+                        class
+                    ),
+                ));
+            }
+
+            let code = parts[0].chars().next().unwrap();
+            let length = parts[2].parse::<u8>();
+            let count = parts[3].parse::<u8>();
+
+            match (length, count) {
+                (Ok(length), Ok(count)) => {
+                    classes.push(ShipClass::new(code, String::from(parts[1]), length, count)?)
+                }
+                _ => {
+                    return Err(DomainError::new(
+                        DomainErrorKind::InvalidCharacters,
+                        format!("Ship class length and count must be numbers in {}.", class),
+                    ))
+                }
+            }
+        }
+
+        Fleet::new(classes)
+    }
+}
+
+impl Display for Fleet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{}",
+            self.classes
+                .iter()
+                .map(|class| class.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+// ---ShotsPerTurn---
+
+/// A number of shots a player fires in one turn.
+/// More than one shot per turn is known as the salvo mode.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ShotsPerTurn {
+    /// A fixed number of shots, one shot per turn is the classic game.
+    Fixed(u8),
+    /// As many shots as the player has not yet sunk ships.
+    SurvivingShips,
+}
+
+impl Default for ShotsPerTurn {
+    fn default() -> Self {
+        ShotsPerTurn::Fixed(1)
+    }
+}
+
+impl FromStr for ShotsPerTurn {
+    type Err = DomainError;
+
+    /// Parse the shots per turn either from a number of shots or from the `surviving` keyword.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "surviving" {
+            return Ok(ShotsPerTurn::SurvivingShips);
+        }
+
+        match s.parse::<u8>() {
+            Ok(shots) if (1..=Fleet::MAX_SHIPS).contains(&shots) => Ok(ShotsPerTurn::Fixed(shots)),
+            Ok(shots) => Err(DomainError::new(
+                DomainErrorKind::OutOfRange,
+                format!(
+                    "Shots per turn must be between 1 - {}. {} given.",
+                    Fleet::MAX_SHIPS,
+                    shots
+                ),
+            )),
+            Err(_) => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!(
+                    "Shots per turn must be a number or surviving, but is {}.",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+impl Display for ShotsPerTurn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ShotsPerTurn::Fixed(shots) => write!(f, "{}", shots),
+            ShotsPerTurn::SurvivingShips => write!(f, "surviving"),
+        }
+    }
+}
+
+// ---TurnRule---
+
+/// A rule deciding who is on turn after a player fired all his shots.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum TurnRule {
+    /// The player keeps the turn if he hit a ship during the turn.
+    #[default]
+    HitKeepsTurn,
+    /// Players always alternate.
+    AlwaysAlternate,
+}
+
+impl FromStr for TurnRule {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hit_keeps_turn" => Ok(TurnRule::HitKeepsTurn),
+            "alternate" => Ok(TurnRule::AlwaysAlternate),
+            _ => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!(
+                    "Turn rule must be hit_keeps_turn or alternate, but is {}.",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+impl Display for TurnRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            TurnRule::HitKeepsTurn => write!(f, "hit_keeps_turn"),
+            TurnRule::AlwaysAlternate => write!(f, "alternate"),
+        }
+    }
+}
+
+// ---Spacing---
+
+/// A rule deciding which neighbouring cells of a ship must not be occupied by another ship.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Spacing {
+    /// Ships may touch each other.
+    None,
+    /// Ships must not touch each other by sides or ends.
+    #[default]
+    Orthogonal,
+    /// Ships must not touch each other at all, not even by corners.
+    Diagonal,
+}
+
+impl Spacing {
+    /// Get the row and column offsets of the cells which must stay free of other ships.
+    pub fn neighbours(&self) -> &'static [(i32, i32)] {
+        match self {
+            Spacing::None => &[],
+            Spacing::Orthogonal => &[(-1, 0), (0, 1), (1, 0), (0, -1)],
+            Spacing::Diagonal => &[
+                (-1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+                (1, 0),
+                (1, -1),
+                (0, -1),
+                (-1, -1),
+            ],
+        }
+    }
+}
+
+impl FromStr for Spacing {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Spacing::None),
+            "orthogonal" => Ok(Spacing::Orthogonal),
+            "diagonal" => Ok(Spacing::Diagonal),
+            _ => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!(
+                    "Spacing must be none, orthogonal or diagonal, but is {}.",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+impl Display for Spacing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Spacing::None => write!(f, "none"),
+            Spacing::Orthogonal => write!(f, "orthogonal"),
+            Spacing::Diagonal => write!(f, "diagonal"),
+        }
+    }
+}
+
+// ---TurnExpiry---
+
+/// An action taken when the player on turn does not fire in time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TurnExpiry {
+    /// A random shot is fired instead of the player.
+    RandomShot,
+    /// The turn passes to the opponent.
+    Pass,
+    /// The player loses the game.
+    Forfeit,
+}
+
+impl FromStr for TurnExpiry {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random_shot" => Ok(TurnExpiry::RandomShot),
+            "pass" => Ok(TurnExpiry::Pass),
+            "forfeit" => Ok(TurnExpiry::Forfeit),
+            _ => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!(
+                    "Turn expiry must be random_shot, pass or forfeit, but is {}.",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+impl Display for TurnExpiry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            TurnExpiry::RandomShot => write!(f, "random_shot"),
+            TurnExpiry::Pass => write!(f, "pass"),
+            TurnExpiry::Forfeit => write!(f, "forfeit"),
+        }
+    }
+}
+
+// ---ShotClock---
+
+/// A time limit of one turn and the action taken when it expires.
+/// Forces the limit to be between 5 - 600 seconds.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ShotClock {
+    seconds: u16,
+    expiry: TurnExpiry,
+}
+
+impl ShotClock {
+    /// Minimal time limit of one turn in seconds.
+    pub const MIN_SECONDS: u16 = 5;
+    /// Maximal time limit of one turn in seconds.
+    pub const MAX_SECONDS: u16 = 600;
+
+    pub fn new(seconds: u16, expiry: TurnExpiry) -> Result<Self, DomainError> {
+        if !(Self::MIN_SECONDS..=Self::MAX_SECONDS).contains(&seconds) {
+            return Err(DomainError::new(
+                DomainErrorKind::OutOfRange,
+                format!(
+                    "Turn time limit must be between {} - {} seconds. {} given.",
+                    Self::MIN_SECONDS,
+                    Self::MAX_SECONDS,
+                    seconds
+                ),
+            ));
+        }
+
+        Ok(ShotClock { seconds, expiry })
+    }
+
+    pub fn seconds(&self) -> u16 {
+        self.seconds
+    }
+
+    pub fn limit(&self) -> Duration {
+        Duration::from_secs(self.seconds as u64)
+    }
+
+    pub fn expiry(&self) -> TurnExpiry {
+        self.expiry
+    }
+}
+
+impl FromStr for ShotClock {
+    type Err = DomainError;
+
+    /// Parse the shot clock from the format `seconds:expiry`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+
+        if parts.len() != 2 {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!("Shot clock must be in format seconds:expiry, but is {}.", s),
+            ));
+        }
+
+        let seconds = parts[0].parse::<u16>().map_err(|_| {
+            DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!("Turn time limit must be a number, but is {}.", parts[0]),
+            )
+        })?;
+
+        ShotClock::new(seconds, parts[1].parse()?)
+    }
+}
+
+impl Display for ShotClock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}:{}", self.seconds, self.expiry)
+    }
+}
+
+// ---Difficulty---
+
+/// A difficulty of the server-side bot opponent.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Difficulty {
+    /// The bot shoots randomly.
+    Easy,
+    /// The bot hunts randomly and targets the neighbourhood of hits.
+    Medium,
+    /// The bot shoots where a ship is most probably placed.
+    Hard,
+}
+
+impl FromStr for Difficulty {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!("Difficulty must be easy, medium or hard, but is {}.", s),
+            )),
+        }
+    }
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Medium => write!(f, "medium"),
+            Difficulty::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+// ---GameMode---
+
+/// Whether the result of a game changes the ratings of the players.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum GameMode {
+    #[default]
+    Casual,
+    Rated,
+}
+
+impl FromStr for GameMode {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "casual" => Ok(GameMode::Casual),
+            "rated" => Ok(GameMode::Rated),
+            _ => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!("Game mode must be casual or rated, but is {}.", s),
+            )),
+        }
+    }
+}
+
+impl Display for GameMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            GameMode::Casual => write!(f, "casual"),
+            GameMode::Rated => write!(f, "rated"),
+        }
+    }
+}
+
+// ---QueuePreference---
+
+/// Preferences of a player waiting for an opponent.
+/// Players are matched only with players preferring the same game mode,
+/// a missing board size means that the player accepts any.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct QueuePreference {
+    mode: GameMode,
+    board_size: Option<BoardSize>,
+}
+
+impl QueuePreference {
+    pub fn new(mode: GameMode, board_size: Option<BoardSize>) -> Self {
+        QueuePreference { mode, board_size }
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn board_size(&self) -> Option<BoardSize> {
+        self.board_size
+    }
+
+    /// Check whether two players with these preferences can play together.
+    pub fn compatible(&self, other: &QueuePreference) -> bool {
+        if self.mode != other.mode {
+            return false;
+        }
+
+        match (self.board_size, other.board_size) {
+            (Some(board_size), Some(other_board_size)) => board_size == other_board_size,
+            _ => true,
+        }
+    }
+
+    /// Get the preference satisfying both compatible preferences.
+    pub fn merge(&self, other: &QueuePreference) -> Self {
+        QueuePreference {
+            mode: self.mode,
+            board_size: self.board_size.or(other.board_size),
+        }
+    }
 }
 
-impl ShipKind {
-    pub fn cells(&self) -> u8 {
-        match self {
-            ShipKind::AircraftCarrier => 5,
-            ShipKind::Battleship => 4,
-            ShipKind::Cruiser => 3,
-            ShipKind::Destroyer => 2,
-            ShipKind::PatrolBoat => 1,
+impl Display for QueuePreference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self.board_size {
+            None => write!(f, "{} game, any board", self.mode),
+            Some(board_size) => write!(f, "{} game, board {}", self.mode, board_size),
+        }
+    }
+}
+
+// ---Rules---
+
+/// A ruleset of a game.
+/// Forces the fleet to fit on the board.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rules {
+    board_size: BoardSize,
+    fleet: Fleet,
+    shots_per_turn: ShotsPerTurn,
+    turn_rule: TurnRule,
+    spacing: Spacing,
+    shot_clock: Option<ShotClock>,
+}
+
+impl Rules {
+    pub fn new(
+        board_size: BoardSize,
+        fleet: Fleet,
+        shots_per_turn: ShotsPerTurn,
+        turn_rule: TurnRule,
+        spacing: Spacing,
+        shot_clock: Option<ShotClock>,
+    ) -> Result<Self, DomainError> {
+        if !fleet.fits(&board_size) {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCombination,
+                format!("Fleet {} does not fit on the board {}.", fleet, board_size),
+            ));
+        }
+
+        Ok(Rules {
+            board_size,
+            fleet,
+            shots_per_turn,
+            turn_rule,
+            spacing,
+            shot_clock,
+        })
+    }
+
+    pub fn board_size(&self) -> BoardSize {
+        self.board_size
+    }
+
+    pub fn fleet(&self) -> &Fleet {
+        &self.fleet
+    }
+
+    pub fn shots_per_turn(&self) -> ShotsPerTurn {
+        self.shots_per_turn
+    }
+
+    pub fn turn_rule(&self) -> TurnRule {
+        self.turn_rule
+    }
+
+    pub fn spacing(&self) -> Spacing {
+        self.spacing
+    }
+
+    pub fn shot_clock(&self) -> Option<ShotClock> {
+        self.shot_clock
+    }
+
+    /// Get the same rules with a different board size.
+    pub fn with_board_size(&self, board_size: BoardSize) -> Result<Self, DomainError> {
+        Rules::new(
+            board_size,
+            self.fleet.clone(),
+            self.shots_per_turn,
+            self.turn_rule,
+            self.spacing,
+            self.shot_clock,
+        )
+    }
+}
+
+impl Default for Rules {
+    /// The classic rules.
+    fn default() -> Self {
+        Rules {
+            board_size: BoardSize::default(),
+            fleet: Fleet::default(),
+            shots_per_turn: ShotsPerTurn::default(),
+            turn_rule: TurnRule::default(),
+            spacing: Spacing::default(),
+            shot_clock: None,
         }
     }
 }
 
-impl Display for ShipKind {
+impl Display for Rules {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
-        match self {
-            ShipKind::AircraftCarrier => write!(f, "AircraftCarrier 5"),
-            ShipKind::Battleship => write!(f, "Battleship 4"),
-            ShipKind::Cruiser => write!(f, "Cruiser 3"),
-            ShipKind::Destroyer => write!(f, "Destroyer 2"),
-            ShipKind::PatrolBoat => write!(f, "PatrolBoat 1"),
+        write!(
+            f,
+            "({}, {}, {}, {}, {}, ",
+            self.board_size, self.fleet, self.shots_per_turn, self.turn_rule, self.spacing
+        )?;
+
+        match self.shot_clock {
+            Some(shot_clock) => write!(f, "{})", shot_clock),
+            None => write!(f, "no shot clock)"),
+        }
+    }
+}
+
+// ---BoardSize---
+
+/// Dimensions of the game board.
+/// Forces both the number of rows and columns to be between 6 - 26.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BoardSize {
+    rows: u8,
+    cols: u8,
+}
+
+impl BoardSize {
+    /// Minimal number of board rows or columns.
+    pub const MIN: u8 = 6;
+    /// Maximal number of board rows or columns.
+    pub const MAX: u8 = 26;
+
+    pub fn new(rows: u8, cols: u8) -> Result<Self, DomainError> {
+        if !(Self::MIN..=Self::MAX).contains(&rows) {
+            return Err(DomainError::new(
+                DomainErrorKind::OutOfRange,
+                format!(
+                    "Board must have {} - {} rows, but has {}.",
+                    Self::MIN,
+                    Self::MAX,
+                    rows
+                ),
+            ));
+        }
+
+        if !(Self::MIN..=Self::MAX).contains(&cols) {
+            return Err(DomainError::new(
+                DomainErrorKind::OutOfRange,
+                format!(
+                    "Board must have {} - {} columns, but has {}.",
+                    Self::MIN,
+                    Self::MAX,
+                    cols
+                ),
+            ));
+        }
+
+        Ok(BoardSize { rows, cols })
+    }
+
+    pub fn rows(&self) -> u8 {
+        self.rows
+    }
+
+    pub fn cols(&self) -> u8 {
+        self.cols
+    }
+
+    /// Check whether the position lies on the board.
+    pub fn contains(&self, position: &Position) -> bool {
+        position.row() < self.rows && position.col() < self.cols
+    }
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        BoardSize { rows: 10, cols: 10 }
+    }
+}
+
+impl FromStr for BoardSize {
+    type Err = DomainError;
+
+    /// Parse the board size from the `<rows>x<cols>` format, e.g. `10x10`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split('x').collect::<Vec<_>>();
+
+        if parts.len() != 2 {
+            return Err(DomainError::new(
+                DomainErrorKind::InvalidCombination,
+                format!("Board size must be in format <rows>x<cols>, but is {}.", s),
+            ));
         }
+
+        let rows = parts[0].parse::<u8>();
+        let cols = parts[1].parse::<u8>();
+
+        match (rows, cols) {
+            (Ok(rows), Ok(cols)) => BoardSize::new(rows, cols),
+            _ => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!("Board size dimensions must be numbers, but are {}.", s),
+            )),
+        }
+    }
+}
+
+impl Display for BoardSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}x{}", self.rows, self.cols)
     }
 }
 
 // ---Position---
 
+/// A position on the board.
+/// Forces both the row and the column to be less than the maximal board size,
+/// whether the position lies on a concrete board is checked by the [BoardSize](BoardSize).
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Position {
     row: u8,
@@ -127,17 +1366,25 @@ pub struct Position {
 
 impl Position {
     pub fn new(row: u8, col: u8) -> Result<Self, DomainError> {
-        if row >= 10 {
+        if row >= BoardSize::MAX {
             return Err(DomainError::new(
                 DomainErrorKind::OutOfRange,
-                format!("Position row must be between 0 - 9. {} given.", row),
+                format!(
+                    "Position row must be between 0 - {}. {} given.",
+                    BoardSize::MAX - 1,
+                    row
+                ),
             ));
         }
 
-        if row >= 10 {
+        if col >= BoardSize::MAX {
             return Err(DomainError::new(
                 DomainErrorKind::OutOfRange,
-                format!("Position col must be between 0 - 9. {} given.", col),
+                format!(
+                    "Position col must be between 0 - {}. {} given.",
+                    BoardSize::MAX - 1,
+                    col
+                ),
             ));
         }
 
@@ -169,6 +1416,26 @@ pub enum Orientation {
     South,
 }
 
+impl FromStr for Orientation {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "east" => Ok(Orientation::East),
+            "north" => Ok(Orientation::North),
+            "west" => Ok(Orientation::West),
+            "south" => Ok(Orientation::South),
+            _ => Err(DomainError::new(
+                DomainErrorKind::InvalidCharacters,
+                format!(
+                    "Orientation must be east, north, west or south, but is {}.",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
 impl Display for Orientation {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
@@ -228,20 +1495,63 @@ impl Display for Placement {
     }
 }
 
+// ---LayoutError---
+
+/// A reason why a layout was rejected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LayoutError {
+    /// A ship of the fleet is not placed.
+    Missing(ShipId),
+    /// A ship is not a part of the fleet.
+    Unknown(ShipId),
+    /// A ship is placed more than once.
+    Duplicate(ShipId),
+    /// A ship does not fit on the board.
+    OutOfBoard(ShipId),
+    /// A ship overlaps the other ship.
+    Overlaps(ShipId, ShipId),
+    /// A ship touches the other ship which is forbidden by the spacing rule.
+    Touches(ShipId, ShipId),
+}
+
+impl Display for LayoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            LayoutError::Missing(ship) => write!(f, "ship {} is missing", ship),
+            LayoutError::Unknown(ship) => write!(f, "ship {} is not in the fleet", ship),
+            LayoutError::Duplicate(ship) => write!(f, "ship {} is placed more than once", ship),
+            LayoutError::OutOfBoard(ship) => write!(f, "ship {} is out of the board", ship),
+            LayoutError::Overlaps(ship, other) => {
+                write!(f, "ship {} overlaps ship {}", ship, other)
+            }
+            LayoutError::Touches(ship, other) => write!(f, "ship {} touches ship {}", ship, other),
+        }
+    }
+}
+
+impl Error for LayoutError {}
+
 // ---Layout---
 
+/// Ships placements as chosen by a player.
+/// Keeps the placements in the order they were given, including duplicates,
+/// so that the validation can point at the offending ship.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Layout {
-    placements: ShipsPlacements,
+    placements: Vec<(ShipId, Placement)>,
 }
 
 impl Layout {
-    pub fn new(placements: ShipsPlacements) -> Result<Self, DomainError> {
-        if placements.len() != 5 {
+    /// Maximum number of attempts to generate a random layout.
+    pub const RANDOM_ATTEMPTS: usize = 100;
+
+    pub fn new(placements: Vec<(ShipId, Placement)>) -> Result<Self, DomainError> {
+        if placements.is_empty() || placements.len() > Fleet::MAX_SHIPS as usize {
             return Err(DomainError::new(
                 DomainErrorKind::InvalidLength,
                 format!(
-                    "Layout must have exactly 5 placements, but has {}.",
+                    "Layout must have 1 - {} placements, but has {}.",
+                    Fleet::MAX_SHIPS,
                     placements.len()
                 ),
             ));
@@ -250,118 +1560,164 @@ impl Layout {
         Ok(Layout { placements })
     }
 
-    pub fn placements(&self) -> &ShipsPlacements {
+    pub fn placements(&self) -> &Vec<(ShipId, Placement)> {
         &self.placements
     }
 
-    pub fn is_valid(&self) -> bool {
-        let mut board = [[false; 10]; 10];
-
-        for (kind, placement) in self.placements.placements() {
-            let cells = kind.cells();
-            let mut row: i32 = placement.position().row() as i32;
-            let mut col: i32 = placement.position().col() as i32;
-
-            let inc_r: i32;
-            let inc_c: i32;
-
-            match placement.orientation() {
-                Orientation::East => {
-                    inc_r = 0;
-                    inc_c = 1;
-                }
-                Orientation::North => {
-                    inc_r = -1;
-                    inc_c = 0;
-                }
-                Orientation::West => {
-                    inc_r = 0;
-                    inc_c = -1;
-                }
-                Orientation::South => {
-                    inc_r = 1;
-                    inc_c = 0;
-                }
+    /// Generate a random valid layout of the fleet according to the rules.
+    /// Returns None if no valid layout was found in
+    /// [RANDOM_ATTEMPTS](Layout::RANDOM_ATTEMPTS) attempts,
+    /// which may happen if the fleet is too large for the board.
+    pub fn random(rules: &Rules) -> Option<Self> {
+        let mut rng = rand::thread_rng();
+        let fleet = rules.fleet();
+
+        // place the longest ships first as they are the hardest to fit
+        let mut ships = fleet
+            .ships()
+            .into_iter()
+            .map(|ship| (ship, fleet.ship_length(&ship).unwrap()))
+            .collect::<Vec<_>>();
+        ships.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        for _ in 0..Self::RANDOM_ATTEMPTS {
+            if let Some(placements) = Self::random_placements(&ships, rules, &mut rng) {
+                return Some(Layout { placements });
             }
+        }
 
-            // mark ship cells
-            for i in 0..cells {
-                // check if in board bounds
-                if row < 0 || row >= 10 || col < 0 || col >= 10 {
-                    return false;
-                }
+        None
+    }
 
-                if board[row as usize][col as usize] {
-                    // occupied
-                    return false;
+    /// Try to place the ships one by one, each on a random free place.
+    /// Returns None if some ship can't be placed anymore.
+    fn random_placements<R: Rng>(
+        ships: &[(ShipId, u8)],
+        rules: &Rules,
+        rng: &mut R,
+    ) -> Option<Vec<(ShipId, Placement)>> {
+        let rows = rules.board_size().rows() as i32;
+        let cols = rules.board_size().cols() as i32;
+        let neighbours = rules.spacing().neighbours();
+
+        // cells occupied by a ship or forbidden by the spacing rule
+        let mut blocked = vec![vec![false; cols as usize]; rows as usize];
+        let mut placements = Vec::with_capacity(ships.len());
+
+        for &(ship, cells) in ships {
+            let mut candidates = Vec::new();
+
+            for row in 0..rows {
+                for col in 0..cols {
+                    for &(orientation, inc_r, inc_c) in
+                        &[(Orientation::East, 0, 1), (Orientation::South, 1, 0)]
+                    {
+                        let free = (0..cells as i32).all(|i| {
+                            let r = row + i * inc_r;
+                            let c = col + i * inc_c;
+                            r < rows && c < cols && !blocked[r as usize][c as usize]
+                        });
+
+                        if free {
+                            candidates.push((row, col, orientation, inc_r, inc_c));
+                        }
+                    }
                 }
+            }
 
-                board[row as usize][col as usize] = true;
+            let &(row, col, orientation, inc_r, inc_c) = candidates.choose(rng)?;
 
-                // check surroundings
+            // mark ship cells and their surroundings
+            for i in 0..cells as i32 {
+                let r = row + i * inc_r;
+                let c = col + i * inc_c;
+                blocked[r as usize][c as usize] = true;
 
-                if i == 0 {
-                    // first cell
-                    let r = row - inc_r;
-                    let c = col - inc_c;
+                for (off_r, off_c) in neighbours {
+                    let r = r + off_r;
+                    let c = c + off_c;
 
-                    if r < 0 || r >= 10 || c < 0 || c >= 10 {
-                        // not in board
-                    } else {
-                        if board[r as usize][c as usize] {
-                            // neighbor occupied
-                            return false;
-                        }
+                    if r >= 0 && r < rows && c >= 0 && c < cols {
+                        blocked[r as usize][c as usize] = true;
                     }
                 }
+            }
+
+            let position = Position::new(row as u8, col as u8).unwrap();
+            placements.push((ship, Placement::new(position, orientation)));
+        }
 
-                if i == cells - 1 {
-                    // last cell
+        Some(placements)
+    }
 
-                    // first cell
-                    let r = row + inc_r;
-                    let c = col + inc_c;
+    /// Get the placement of the ship.
+    pub fn placement(&self, ship: &ShipId) -> Option<Placement> {
+        self.placements
+            .iter()
+            .find(|(id, _)| id == ship)
+            .map(|(_, placement)| *placement)
+    }
 
-                    if r < 0 || r >= 10 || c < 0 || c >= 10 {
-                        // not in board
-                    } else {
-                        if board[r as usize][c as usize] {
-                            // neighbor occupied
-                            return false;
-                        }
-                    }
-                }
+    /// Check whether the layout places exactly the ships of the fleet,
+    /// all of them fit on the board, no two ships overlap
+    /// and no two ships touch each other in a way forbidden by the spacing rule.
+    pub fn validate(&self, rules: &Rules) -> Result<(), LayoutError> {
+        let fleet = rules.fleet();
+        let rows = rules.board_size().rows() as i32;
+        let cols = rules.board_size().cols() as i32;
+        let mut board: Vec<Vec<Option<ShipId>>> = vec![vec![None; cols as usize]; rows as usize];
+        let neighbours = rules.spacing().neighbours();
+
+        // mark ship cells
+        for (i, (ship, placement)) in self.placements.iter().enumerate() {
+            if self.placements[..i].iter().any(|(id, _)| id == ship) {
+                return Err(LayoutError::Duplicate(*ship));
+            }
+
+            let cells = match fleet.ship_length(ship) {
+                Some(cells) => cells,
+                None => return Err(LayoutError::Unknown(*ship)),
+            };
 
-                let mut r1 = row;
-                let mut c1 = col;
-                let mut r2 = row;
-                let mut c2 = col;
+            let mut row: i32 = placement.position().row() as i32;
+            let mut col: i32 = placement.position().col() as i32;
 
-                if inc_r == 0 {
-                    r1 = row + 1;
-                    r2 = row - 1;
+            let (inc_r, inc_c) = match placement.orientation() {
+                Orientation::East => (0, 1),
+                Orientation::North => (-1, 0),
+                Orientation::West => (0, -1),
+                Orientation::South => (1, 0),
+            };
+
+            for _ in 0..cells {
+                // check if in board bounds
+                if row < 0 || row >= rows || col < 0 || col >= cols {
+                    return Err(LayoutError::OutOfBoard(*ship));
                 }
 
-                if inc_c == 0 {
-                    c1 = col + 1;
-                    c2 = col - 1;
+                if let Some(other) = board[row as usize][col as usize] {
+                    // occupied
+                    return Err(LayoutError::Overlaps(*ship, other));
                 }
 
-                if r1 < 0 || r1 >= 10 || c1 < 0 || c1 >= 10 {
-                    // not in board
-                } else {
-                    if board[r1 as usize][c1 as usize] {
-                        // neighbor occupied
-                        return false;
+                board[row as usize][col as usize] = Some(*ship);
+
+                // check surroundings
+                for (off_r, off_c) in neighbours {
+                    let r = row + off_r;
+                    let c = col + off_c;
+
+                    if r < 0 || r >= rows || c < 0 || c >= cols {
+                        // not in board
+                        continue;
                     }
-                }
 
-                if r2 < 0 || r2 >= 10 || c2 < 0 || c2 >= 10 {
-                    // not in board
-                } else {
-                    if board[r2 as usize][c2 as usize] {
-                        // neighbor occupied
-                        return false;
+                    match board[r as usize][c as usize] {
+                        Some(other) if other != *ship => {
+                            // neighbor occupied by another ship
+                            return Err(LayoutError::Touches(*ship, other));
+                        }
+                        _ => {}
                     }
                 }
 
@@ -370,13 +1726,33 @@ impl Layout {
             }
         }
 
-        return true;
+        // check that no ship is missing
+        for ship in fleet.ships() {
+            if self.placement(&ship).is_none() {
+                return Err(LayoutError::Missing(ship));
+            }
+        }
+
+        Ok(())
     }
 }
 
 impl Display for Layout {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
-        write!(f, "{}", self.placements)
+        let mut string = String::from("{");
+
+        string.push_str(
+            &self
+                .placements
+                .iter()
+                .map(|(k, p)| format!("{} {}", k, p))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+
+        string.push('}');
+
+        write!(f, "{}", string)
     }
 }
 
@@ -384,15 +1760,15 @@ impl Display for Layout {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ShipsPlacements {
-    placements: HashMap<ShipKind, Placement>,
+    placements: HashMap<ShipId, Placement>,
 }
 
 impl ShipsPlacements {
-    pub fn new(ships: HashMap<ShipKind, Placement>) -> Self {
+    pub fn new(ships: HashMap<ShipId, Placement>) -> Self {
         ShipsPlacements { placements: ships }
     }
 
-    pub fn placements(&self) -> &HashMap<ShipKind, Placement> {
+    pub fn placements(&self) -> &HashMap<ShipId, Placement> {
         &self.placements
     }
 
@@ -456,6 +1832,32 @@ impl Display for Hits {
     }
 }
 
+// ---GameOverReason---
+
+/// A reason why a game is over.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GameOverReason {
+    /// All ships of the loser are sunk.
+    FleetSunk,
+    /// The loser resigned.
+    Resigned,
+    /// The loser was disconnected for too long.
+    Disconnected,
+    /// The loser did not fire in the time limit of the turn.
+    TimedOut,
+}
+
+impl Display for GameOverReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            GameOverReason::FleetSunk => write!(f, "fleet_sunk"),
+            GameOverReason::Resigned => write!(f, "resigned"),
+            GameOverReason::Disconnected => write!(f, "disconnected"),
+            GameOverReason::TimedOut => write!(f, "timed_out"),
+        }
+    }
+}
+
 // ---RestoreState---
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -463,7 +1865,9 @@ pub enum RestoreState {
     Lobby,
     Game {
         opponent: Nickname,
+        rules: Rules,
         on_turn: Who,
+        shots_left: u8,
         player_board_hits: Hits,
         player_board_misses: Hits,
         layout: Layout,
@@ -479,7 +1883,9 @@ impl Display for RestoreState {
             RestoreState::Lobby => write!(f, "lobby"),
             RestoreState::Game {
                 opponent,
+                rules,
                 on_turn,
+                shots_left,
                 player_board_hits,
                 player_board_misses,
                 layout,
@@ -488,9 +1894,11 @@ impl Display for RestoreState {
                 sunk_ships,
             } => write!(
                 f,
-                "game ({}, {}, {}, {}, {}, {}, {}, {})",
+                "game ({}, {}, {}, {}, {}, {}, {}, {}, {}, {})",
                 opponent,
+                rules,
                 on_turn,
+                shots_left,
                 player_board_hits,
                 player_board_misses,
                 layout,
@@ -501,3 +1909,274 @@ impl Display for RestoreState {
         }
     }
 }
+
+// ---SpectateState---
+
+/// A board as seen by a spectator - its hits, misses and sunk ships.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BoardView {
+    hits: Hits,
+    misses: Hits,
+    sunk_ships: ShipsPlacements,
+}
+
+impl BoardView {
+    pub fn new(hits: Hits, misses: Hits, sunk_ships: ShipsPlacements) -> Self {
+        BoardView {
+            hits,
+            misses,
+            sunk_ships,
+        }
+    }
+
+    pub fn hits(&self) -> &Hits {
+        &self.hits
+    }
+
+    pub fn misses(&self) -> &Hits {
+        &self.misses
+    }
+
+    pub fn sunk_ships(&self) -> &ShipsPlacements {
+        &self.sunk_ships
+    }
+}
+
+impl Display for BoardView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "({}, {}, {})", self.hits, self.misses, self.sunk_ships)
+    }
+}
+
+/// The state of a spectated game sent to a spectator when the spectating starts.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SpectateState {
+    pub first_player: Nickname,
+    pub second_player: Nickname,
+    pub rules: Rules,
+    pub on_turn: Nickname,
+    pub first_board: BoardView,
+    pub second_board: BoardView,
+}
+
+impl Display for SpectateState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} vs {} ({}, {}, {}, {})",
+            self.first_player,
+            self.second_player,
+            self.rules,
+            self.on_turn,
+            self.first_board,
+            self.second_board
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{
+        BoardSize, ChatMessage, Fleet, Layout, LayoutError, Orientation, Placement, Position,
+        ResumeToken, RoomCode, Rules, ShipId, ShotsPerTurn, Spacing, TokenHash, TurnRule,
+    };
+
+    #[test]
+    fn test_ship_id_from_str() {
+        assert_eq!("A".parse::<ShipId>(), ShipId::new('A', 0));
+        assert_eq!("D12".parse::<ShipId>(), ShipId::new('D', 12));
+        assert!("d".parse::<ShipId>().is_err());
+        assert!("Dx".parse::<ShipId>().is_err());
+        assert!("".parse::<ShipId>().is_err());
+    }
+
+    #[test]
+    fn test_room_code() {
+        let code = RoomCode::random();
+        assert_eq!(RoomCode::new(code.get().clone()).unwrap(), code);
+
+        assert!(RoomCode::new(String::from("AB2C9Z")).is_ok());
+        assert!(RoomCode::new(String::from("AB2C9")).is_err());
+        assert!(RoomCode::new(String::from("AB2C0Z")).is_err());
+        assert!(RoomCode::new(String::from("ab2c9z")).is_err());
+    }
+
+    #[test]
+    fn test_resume_token() {
+        let token = ResumeToken::random();
+        assert_eq!(ResumeToken::new(token.get().clone()).unwrap(), token);
+        assert_ne!(ResumeToken::random(), token);
+
+        assert!(ResumeToken::new(String::from("0123456789abcdef0123456789abcdef")).is_ok());
+        assert!(ResumeToken::new(String::from("0123456789abcdef")).is_err());
+        assert!(ResumeToken::new(String::from("0123456789ABCDEF0123456789ABCDEF")).is_err());
+    }
+
+    #[test]
+    fn test_token_hash() {
+        let token = ResumeToken::random();
+        let hash = token.hash();
+        assert_eq!(TokenHash::new(hash.get().clone()).unwrap(), hash);
+        assert_eq!(token.hash(), hash);
+        assert_ne!(ResumeToken::random().hash(), hash);
+
+        // the known SHA-256 of the token
+        let token = ResumeToken::new(String::from("0123456789abcdef0123456789abcdef")).unwrap();
+        assert_eq!(
+            token.hash().get(),
+            "3eb1bd439947eb762998e566ccc2e099c791118b2f40579cc4f7da2b5061b7f9"
+        );
+
+        assert!(TokenHash::new(token.get().clone()).is_err());
+    }
+
+    #[test]
+    fn test_chat_message() {
+        assert!(ChatMessage::new(String::from("gg; well played :)")).is_ok());
+        assert!(ChatMessage::new("x".repeat(ChatMessage::MAX_LENGTH)).is_ok());
+        assert!(ChatMessage::new("x".repeat(ChatMessage::MAX_LENGTH + 1)).is_err());
+        assert!(ChatMessage::new(String::new()).is_err());
+        assert!(ChatMessage::new(String::from("line\nbreak")).is_err());
+    }
+
+    #[test]
+    fn test_ship_id_to_string() {
+        assert_eq!(ShipId::new('A', 0).unwrap().to_string(), "A");
+        assert_eq!(ShipId::new('D', 3).unwrap().to_string(), "D3");
+    }
+
+    #[test]
+    fn test_fleet_from_str() {
+        let fleet = "D:Destroyer:2:2,P:PatrolBoat:1:4".parse::<Fleet>().unwrap();
+        assert_eq!(fleet.ships().len(), 6);
+        assert_eq!(fleet.ship_length(&ShipId::new('D', 1).unwrap()), Some(2));
+        assert_eq!(fleet.ship_length(&ShipId::new('D', 2).unwrap()), None);
+
+        assert!("D:Destroyer:2:2,D:Dinghy:1:1".parse::<Fleet>().is_err());
+        assert!("D:Destroyer:2".parse::<Fleet>().is_err());
+        assert!("D:Destroyer:0:1".parse::<Fleet>().is_err());
+    }
+
+    #[test]
+    fn test_default_fleet_roundtrip() {
+        let fleet = Fleet::default();
+        assert_eq!(fleet.to_string().parse::<Fleet>(), Ok(fleet));
+    }
+
+    /// Create a layout of the classic fleet with the patrol boat at the given position.
+    fn layout_with_patrol_boat(row: u8, col: u8) -> Layout {
+        let mut placements = Vec::new();
+
+        for (i, code) in ['A', 'B', 'C', 'D'].iter().enumerate() {
+            placements.push((
+                ShipId::new(*code, 0).unwrap(),
+                Placement::new(Position::new(i as u8 * 2, 0).unwrap(), Orientation::East),
+            ));
+        }
+
+        placements.push((
+            ShipId::new('P', 0).unwrap(),
+            Placement::new(Position::new(row, col).unwrap(), Orientation::East),
+        ));
+
+        Layout::new(placements).unwrap()
+    }
+
+    fn rules(spacing: Spacing) -> Rules {
+        Rules::new(
+            BoardSize::default(),
+            Fleet::default(),
+            ShotsPerTurn::default(),
+            TurnRule::default(),
+            spacing,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_layout_spacing() {
+        let a = ShipId::new('A', 0).unwrap();
+        let p = ShipId::new('P', 0).unwrap();
+
+        let apart = layout_with_patrol_boat(8, 0);
+        let by_corner = layout_with_patrol_boat(1, 5);
+        let by_side = layout_with_patrol_boat(1, 4);
+        let overlapping = layout_with_patrol_boat(0, 4);
+
+        assert_eq!(apart.validate(&rules(Spacing::Diagonal)), Ok(()));
+
+        assert_eq!(by_corner.validate(&rules(Spacing::Orthogonal)), Ok(()));
+        assert_eq!(
+            by_corner.validate(&rules(Spacing::Diagonal)),
+            Err(LayoutError::Touches(p, a))
+        );
+
+        assert_eq!(by_side.validate(&rules(Spacing::None)), Ok(()));
+        assert_eq!(
+            by_side.validate(&rules(Spacing::Orthogonal)),
+            Err(LayoutError::Touches(p, a))
+        );
+
+        assert_eq!(
+            overlapping.validate(&rules(Spacing::None)),
+            Err(LayoutError::Overlaps(p, a))
+        );
+    }
+
+    #[test]
+    fn test_layout_fleet_errors() {
+        let rules = rules(Spacing::default());
+        let mut placements = layout_with_patrol_boat(8, 0).placements().clone();
+        let p = placements.pop().unwrap();
+
+        let missing = Layout::new(placements.clone()).unwrap();
+        assert_eq!(missing.validate(&rules), Err(LayoutError::Missing(p.0)));
+
+        placements.push(p);
+        placements.push(p);
+        let duplicate = Layout::new(placements.clone()).unwrap();
+        assert_eq!(duplicate.validate(&rules), Err(LayoutError::Duplicate(p.0)));
+
+        placements.pop();
+        placements.pop();
+        let unknown = ShipId::new('P', 1).unwrap();
+        placements.push((unknown, p.1));
+        let unknown_ship = Layout::new(placements.clone()).unwrap();
+        assert_eq!(
+            unknown_ship.validate(&rules),
+            Err(LayoutError::Unknown(unknown))
+        );
+
+        placements.pop();
+        placements.push(p);
+        let a = placements[0].0;
+        let position = Position::new(9, 9).unwrap();
+        placements[0] = (a, Placement::new(position, Orientation::East));
+        let out_of_board = Layout::new(placements).unwrap();
+        assert_eq!(
+            out_of_board.validate(&rules),
+            Err(LayoutError::OutOfBoard(a))
+        );
+    }
+
+    #[test]
+    fn test_random_layout() {
+        for &spacing in &[Spacing::None, Spacing::Orthogonal, Spacing::Diagonal] {
+            let rules = rules(spacing);
+            let layout = Layout::random(&rules).unwrap();
+            assert_eq!(layout.validate(&rules), Ok(()));
+        }
+
+        let crowded = Rules::new(
+            BoardSize::new(6, 6).unwrap(),
+            "P:PatrolBoat:1:32".parse().unwrap(),
+            ShotsPerTurn::default(),
+            TurnRule::default(),
+            Spacing::Diagonal,
+            None,
+        )
+        .unwrap();
+        assert_eq!(Layout::random(&crowded), None);
+    }
+}